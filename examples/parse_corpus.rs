@@ -0,0 +1,156 @@
+//! Compares parsing into an arena through the boxed AST with parsing
+//! straight into one.
+//!
+//! Parses every `.rb` file under a directory, or a generated corpus if none
+//! is given, and prints the time taken and memory allocated each way.
+//!
+//!     cargo run --release --example parse_corpus [DIRECTORY]
+
+extern crate rubic;
+
+use rubic::ast::arena::Arena;
+use rubic::parse::Parser;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use std::{env, fs};
+use std::path::Path;
+
+/// Counts the allocations made, on top of the system allocator.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// How many times each way of parsing is run, keeping the fastest.
+const RUNS: usize = 5;
+
+/// The time and memory taken to parse the corpus once.
+struct Measurement
+{
+    time: Duration,
+    allocations: usize,
+    bytes: usize,
+}
+
+fn measure<F>(sources: &[String], parse: F) -> Measurement
+    where F: Fn(&str) -> Arena {
+    let mut best: Option<Measurement> = None;
+
+    for _ in 0..RUNS {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+        let start = Instant::now();
+
+        for source in sources.iter() {
+            drop(parse(source));
+        }
+
+        let measurement = Measurement {
+            time: start.elapsed(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+        };
+        if best.as_ref().is_none_or(|best| measurement.time < best.time) {
+            best = Some(measurement);
+        }
+    }
+
+    best.unwrap()
+}
+
+/// Reads every Ruby file under a directory which parses.
+fn read_corpus(directory: &Path, sources: &mut Vec<String>) {
+    for entry in fs::read_dir(directory).expect("failed to read directory") {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            read_corpus(&path, sources);
+        } else if path.extension().is_some_and(|extension| extension == "rb") {
+            if let Ok(source) = fs::read_to_string(&path) {
+                if Parser::from(&source[..]).parse().is_ok() {
+                    sources.push(source);
+                }
+            }
+        }
+    }
+}
+
+/// Generates classes using most of the syntax the parser knows.
+fn generate_corpus() -> Vec<String> {
+    (0..200).map(|file| {
+        let mut source = format!("# frozen_string_literal: true\n\nmodule Generated{}\n", file);
+
+        for class in 0..10 {
+            source.push_str(&format!("  class Class{} < Base::Record\n    attr_reader :a, :b\n\n", class));
+
+            for function in 0..10 {
+                source.push_str(&format!(concat!(
+                    "    def method_{}(a, b)\n",
+                    "      @total = a.value + b * {} - (c ** 2)\n",
+                    "      items.each do |item, index|\n",
+                    "        yield item[index], key: 'value {}', other: :symbol\n",
+                    "      end\n",
+                    "      x, *y = compute(a, b), Other::CONSTANT\n",
+                    "      for z in y\n        total.add z\n      end\n",
+                    "      begin\n        risky!(a ? b : c)\n      rescue Error => error\n        log error\n      end\n",
+                    "      return @total == 0 ? nil : -@total\n",
+                    "    end\n\n",
+                ), function, function, function));
+            }
+
+            source.push_str("    private :method_0, :method_1\n  end\n\n");
+        }
+
+        source.push_str("end\n");
+        source
+    }).collect()
+}
+
+fn main() {
+    let sources = match env::args().nth(1) {
+        Some(directory) => {
+            let mut sources = Vec::new();
+            read_corpus(Path::new(&directory), &mut sources);
+            sources
+        },
+        None => generate_corpus(),
+    };
+
+    let lines: usize = sources.iter().map(|source| source.lines().count()).sum();
+    let bytes: usize = sources.iter().map(String::len).sum();
+    println!("corpus: {} files, {} lines, {} KiB", sources.len(), lines, bytes / 1024);
+
+    let converted = measure(&sources, |source| {
+        Arena::from_program(&Parser::from(source).parse().expect("failed to parse"))
+    });
+    let direct = measure(&sources, |source| Parser::from(source).parse_arena().expect("failed to parse"));
+
+    for &(name, measurement) in [("parse + from_program", &converted), ("parse_arena", &direct)].iter() {
+        println!("{:>22}: {:>8.2?} {:>10} allocations {:>8} KiB allocated",
+                 name, measurement.time, measurement.allocations, measurement.bytes / 1024);
+    }
+}
@@ -0,0 +1,1105 @@
+//! The AST stored in an arena.
+//!
+//! Each node lives in one `Arena` and is known by its `NodeId`, so analyses
+//! can keep side tables keyed by node, and move up the tree as well as down.
+//! Children are ids rather than boxes, lists of children are ranges of one
+//! shared list, and names are interned `Symbol`s.
+//!
+//! An arena is built from a parsed `Program`, or by the parser itself with
+//! `Parser::parse_arena`. Nodes are numbered children first, in source
+//! order, so the same program always gets the same ids.
+//!
+//! ```
+//! use rubic::ast::arena::{Arena, Node};
+//! use rubic::parse::Parser;
+//!
+//! let program = Parser::from("foo(1)").parse().unwrap();
+//! let arena = Arena::from_program(&program);
+//!
+//! let call = arena.children(arena.root()).next().unwrap();
+//! let argument = arena.children(call).next().unwrap();
+//! assert_eq!(arena[argument], Node::IntegerLiteral { value: 1 });
+//! assert_eq!(arena.parent(argument), Some(call));
+//! ```
+
+use ast;
+use ast::{AliasKind, BinaryOp, PathSeparator, Visibility};
+use parse::{Build, VisibilityModifier};
+use parse::build::{CallTarget, variable_target};
+use cst::SyntaxKind;
+use span::Span;
+use symbol::Symbol;
+
+use std::{fmt, slice};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Index;
+
+/// The identity of a node in an arena.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(u32);
+
+/// A list of values stored in an arena.
+pub struct List<T>
+{
+    start: u32,
+    len: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// A string stored in an arena.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Text
+{
+    start: u32,
+    end: u32,
+}
+
+/// A part of a call's path, `.foo` or `::Bar`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PathSegment
+{
+    pub name: Symbol,
+    pub separator: PathSeparator,
+}
+
+/// Where a constant is looked up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConstantScope
+{
+    /// The enclosing classes and modules, `Foo`.
+    Lexical,
+    /// The top level, `::Foo`.
+    TopLevel,
+    /// The module a node evaluates to, `obj.class::Foo`, or the constant
+    /// before it in a path such as `Foo::Bar`.
+    Expr(NodeId),
+}
+
+/// A node.
+///
+/// Statements are stored as the expressions they wrap, and arguments as
+/// their values. Assignment targets are nodes of their own, other than
+/// constants and elements, which are `Constant` and `Index` nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Node
+{
+    Program { items: List<NodeId>, data: Option<Text> },
+    Module { name: NodeId, items: List<NodeId> },
+    Class { name: NodeId, superclass: Option<NodeId>, items: List<NodeId> },
+    Function { name: Symbol, singleton: bool, visibility: Visibility, parameters: List<NodeId>, statements: List<NodeId> },
+    /// A parameter of a function, `a` or `a = 1`.
+    Parameter { name: Symbol, default: Option<NodeId> },
+    BeginBlock { statements: List<NodeId> },
+    EndBlock { statements: List<NodeId> },
+
+    Alias { kind: AliasKind, new_name: Symbol, old_name: Symbol },
+    Undef { names: List<Symbol> },
+
+    Assignment { target: NodeId, value: NodeId },
+    ConstantAssignment { constant: NodeId, value: NodeId },
+    Constant { scope: ConstantScope, name: Symbol },
+    Paren { inner: NodeId },
+    Call { callee: List<PathSegment>, arguments: List<NodeId>, block: Option<NodeId> },
+    Block { parameters: List<NodeId>, statements: List<NodeId> },
+    StringLiteral { value: Text },
+    IntegerLiteral { value: i64 },
+    CharacterLiteral { value: char },
+    Symbol { name: Symbol },
+    KeyValue { key: Symbol, value: NodeId },
+    Negate { inner: NodeId },
+    Defined { inner: NodeId },
+    Super { arguments: List<NodeId> },
+    ZSuper,
+    Yield { arguments: List<NodeId> },
+    Return { arguments: List<NodeId> },
+    Binary { operator: BinaryOp, lhs: NodeId, rhs: NodeId },
+    Ternary { condition: NodeId, if_true: NodeId, if_false: NodeId },
+    MultipleAssignment { targets: List<NodeId>, values: List<NodeId> },
+    Index { receiver: NodeId, arguments: List<NodeId> },
+    For { targets: List<NodeId>, iterable: NodeId, statements: List<NodeId> },
+    Begin {
+        statements: List<NodeId>,
+        rescue_clauses: List<NodeId>,
+        else_statements: Option<List<NodeId>>,
+        ensure_statements: Option<List<NodeId>>,
+    },
+    RescueClause { exceptions: List<NodeId>, target: Option<NodeId>, statements: List<NodeId> },
+    Error,
+    Missing,
+
+    /// A local variable target, `a`.
+    Local { name: Symbol },
+    /// An instance variable target, `@a`, without its sigil.
+    InstanceVariable { name: Symbol },
+    /// A global variable target, `$a`, without its sigil.
+    GlobalVariable { name: Symbol },
+    /// A class variable target, `@@a`, without its sigil.
+    ClassVariable { name: Symbol },
+    /// An attribute writer target, `a.b`, without the trailing `=`.
+    Attribute { receiver: NodeId, name: Symbol },
+    Splat { target: Option<NodeId> },
+    Group { targets: List<NodeId> },
+}
+
+/// The nodes of a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Arena
+{
+    nodes: Vec<Node>,
+    parents: Vec<Option<NodeId>>,
+    lists: Vec<NodeId>,
+    symbols: Vec<Symbol>,
+    segments: Vec<PathSegment>,
+    text: String,
+    pragmas: Vec<ast::Pragma>,
+}
+
+/// How much of an arena has been built.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mark
+{
+    nodes: usize,
+    lists: usize,
+    symbols: usize,
+    segments: usize,
+    text: usize,
+}
+
+/// Adds the nodes of the AST to an arena.
+struct Builder<'a>
+{
+    arena: &'a mut Arena,
+}
+
+impl NodeId
+{
+    /// Gets the position of the node in the arena, for indexing side tables.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<T> List<T>
+{
+    pub fn len(self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len == 0
+    }
+
+    fn range(self) -> ::std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+impl PathSegment
+{
+    /// Checks if the segment names a constant rather than a method.
+    pub fn is_constant(&self) -> bool {
+        self.name.as_str().starts_with(char::is_uppercase)
+    }
+}
+
+impl Arena
+{
+    /// Creates an empty arena, for the parser to build a program in.
+    pub(crate) fn new() -> Self {
+        Arena {
+            nodes: Vec::new(),
+            parents: Vec::new(),
+            lists: Vec::new(),
+            symbols: Vec::new(),
+            segments: Vec::new(),
+            text: String::new(),
+            pragmas: Vec::new(),
+        }
+    }
+
+    /// Builds an arena holding a program.
+    ///
+    /// `Parser::parse_arena` builds the same arena without building the
+    /// boxed AST first.
+    pub fn from_program(program: &ast::Program) -> Self {
+        let mut arena = Arena::new();
+        Builder { arena: &mut arena }.program(program);
+
+        arena.link_parents()
+    }
+
+    /// Records the parent of every node, once the program has been built.
+    pub(crate) fn link_parents(mut self) -> Self {
+        let mut parents = vec![None; self.nodes.len()];
+        for parent in self.ids() {
+            for child in self.children(parent) {
+                parents[child.index()] = Some(parent);
+            }
+        }
+
+        self.parents = parents;
+        self
+    }
+
+    /// Gets the `Program` node, which every other node is inside.
+    pub fn root(&self) -> NodeId {
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    /// Gets the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Gets every node id, children before their parents.
+    pub fn ids(&self) -> impl Iterator<Item=NodeId> {
+        (0..self.nodes.len() as u32).map(NodeId)
+    }
+
+    /// Gets the node a node is directly inside, or nothing for the root.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id.index()]
+    }
+
+    /// Gets the nodes a node is inside, innermost first.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item=NodeId> + '_ {
+        let mut current = id;
+        ::std::iter::from_fn(move || {
+            let parent = self.parent(current)?;
+            current = parent;
+            Some(parent)
+        })
+    }
+
+    /// Gets the nodes directly inside a node, in source order.
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item=NodeId> + '_ {
+        // Every node's children are in at most four runs.
+        let none: &[NodeId] = &[];
+        let list = |list: List<NodeId>| &self[list];
+        let one = slice::from_ref;
+
+        let runs: [&[NodeId]; 4] = match self.nodes[id.index()] {
+            Node::Program { items, .. } => [list(items), none, none, none],
+            Node::Module { ref name, items } => [one(name), list(items), none, none],
+            Node::Class { ref name, ref superclass, items } => [one(name), superclass.as_slice(), list(items), none],
+            Node::Function { parameters, statements, .. } => [list(parameters), list(statements), none, none],
+            Node::BeginBlock { statements } | Node::EndBlock { statements } => [list(statements), none, none, none],
+            Node::Assignment { target: ref first, value: ref second } |
+                Node::ConstantAssignment { constant: ref first, value: ref second } |
+                Node::Binary { lhs: ref first, rhs: ref second, .. } => [one(first), one(second), none, none],
+            Node::Constant { scope: ConstantScope::Expr(ref inner), .. } | Node::Paren { ref inner } |
+                Node::Negate { ref inner } | Node::Defined { ref inner } |
+                Node::KeyValue { value: ref inner, .. } |
+                Node::Attribute { receiver: ref inner, .. } => [one(inner), none, none, none],
+            Node::Parameter { ref default, .. } => [default.as_slice(), none, none, none],
+            Node::Call { arguments, ref block, .. } => [list(arguments), block.as_slice(), none, none],
+            Node::Block { parameters: first, statements: second } |
+                Node::MultipleAssignment { targets: first, values: second } => [list(first), list(second), none, none],
+            Node::Super { arguments } | Node::Yield { arguments } | Node::Return { arguments } |
+                Node::Group { targets: arguments } => [list(arguments), none, none, none],
+            Node::Ternary { ref condition, ref if_true, ref if_false } => {
+                [one(condition), one(if_true), one(if_false), none]
+            },
+            Node::Index { ref receiver, arguments } => [one(receiver), list(arguments), none, none],
+            Node::For { targets, ref iterable, statements } => [list(targets), one(iterable), list(statements), none],
+            Node::Begin { statements, rescue_clauses, else_statements, ensure_statements } => [
+                list(statements),
+                list(rescue_clauses),
+                else_statements.map_or(none, list),
+                ensure_statements.map_or(none, list),
+            ],
+            Node::RescueClause { exceptions, ref target, statements } => {
+                [list(exceptions), target.as_slice(), list(statements), none]
+            },
+            Node::Splat { ref target } => [target.as_slice(), none, none, none],
+            Node::Alias { .. } | Node::Undef { .. } | Node::Constant { .. } |
+                Node::StringLiteral { .. } | Node::IntegerLiteral { .. } |
+                Node::CharacterLiteral { .. } | Node::Symbol { .. } | Node::ZSuper |
+                Node::Error | Node::Missing | Node::Local { .. } |
+                Node::InstanceVariable { .. } | Node::GlobalVariable { .. } |
+                Node::ClassVariable { .. } => [none; 4],
+        };
+
+        IntoIterator::into_iter(runs).flatten().cloned()
+    }
+
+    /// Gets the magic comments of the program.
+    pub fn pragmas(&self) -> &[ast::Pragma] {
+        &self.pragmas
+    }
+
+    fn push(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    fn push_text(&mut self, text: &str) -> Text {
+        let start = self.text.len() as u32;
+        self.text.push_str(text);
+        Text { start, end: self.text.len() as u32 }
+    }
+
+    fn list(&mut self, ids: Vec<NodeId>) -> List<NodeId> {
+        push_list(&mut self.lists, ids)
+    }
+}
+
+impl Build for Arena
+{
+    type Program = NodeId;
+    type Item = NodeId;
+    type Stmt = NodeId;
+    type Expr = NodeId;
+    type Target = NodeId;
+    type ConstantPath = NodeId;
+    type Block = NodeId;
+    type RescueClause = NodeId;
+    type Parameter = NodeId;
+    type Mark = Mark;
+
+    fn mark(&self) -> Mark {
+        Mark {
+            nodes: self.nodes.len(),
+            lists: self.lists.len(),
+            symbols: self.symbols.len(),
+            segments: self.segments.len(),
+            text: self.text.len(),
+        }
+    }
+
+    fn discard(&mut self, mark: Mark) {
+        self.nodes.truncate(mark.nodes);
+        self.lists.truncate(mark.lists);
+        self.symbols.truncate(mark.symbols);
+        self.segments.truncate(mark.segments);
+        self.text.truncate(mark.text);
+    }
+
+    fn program(&mut self, items: Vec<NodeId>, data: Option<&str>, pragmas: &[ast::Pragma]) -> NodeId {
+        let items = self.list(items);
+        let data = data.map(|data| self.push_text(data));
+        self.pragmas = pragmas.to_owned();
+
+        self.push(Node::Program { items, data })
+    }
+
+    fn module(&mut self, name: NodeId, items: Vec<NodeId>) -> NodeId {
+        let items = self.list(items);
+        self.push(Node::Module { name, items })
+    }
+
+    fn class(&mut self, name: NodeId, superclass: Option<NodeId>, items: Vec<NodeId>) -> NodeId {
+        let items = self.list(items);
+        self.push(Node::Class { name, superclass, items })
+    }
+
+    fn function(&mut self, name: Symbol, singleton: bool, visibility: Visibility, parameters: Vec<NodeId>,
+                statements: Vec<NodeId>) -> NodeId {
+        let parameters = self.list(parameters);
+        let statements = self.list(statements);
+        self.push(Node::Function { name, singleton, visibility, parameters, statements })
+    }
+
+    fn parameter(&mut self, name: Symbol, default: Option<NodeId>) -> NodeId {
+        self.push(Node::Parameter { name, default })
+    }
+
+    fn begin_block(&mut self, statements: Vec<NodeId>) -> NodeId {
+        let statements = self.list(statements);
+        self.push(Node::BeginBlock { statements })
+    }
+
+    fn end_block(&mut self, statements: Vec<NodeId>) -> NodeId {
+        let statements = self.list(statements);
+        self.push(Node::EndBlock { statements })
+    }
+
+    fn stmt_item(&mut self, stmt: NodeId) -> NodeId { stmt }
+
+    fn visibility_modifier(&self, &item: &NodeId) -> Option<VisibilityModifier> {
+        let (callee, arguments) = match self[item] {
+            Node::Call { callee, arguments, .. } => (callee, arguments),
+            _ => return None,
+        };
+        let (singleton, visibility) = match self[callee] {
+            [segment] => Visibility::from_modifier(segment.name.as_str())?,
+            _ => return None,
+        };
+
+        let names = if arguments.is_empty() {
+            None
+        } else {
+            Some(self[arguments].iter().filter_map(|&argument| match self[argument] {
+                Node::Symbol { name } => Some(name),
+                Node::StringLiteral { value } => Some(Symbol::intern(&self[value])),
+                _ => None,
+            }).collect())
+        };
+
+        Some(VisibilityModifier { singleton, visibility, names })
+    }
+
+    fn apply_visibility_modifier(&mut self, &mut item: &mut NodeId, modifier: &VisibilityModifier) {
+        if let Node::Function { name, singleton, ref mut visibility, .. } = self.nodes[item.index()] {
+            if singleton == modifier.singleton && modifier.names(name) {
+                *visibility = modifier.visibility;
+            }
+        }
+    }
+
+    fn expr_stmt(&mut self, expr: NodeId) -> NodeId { expr }
+
+    fn alias(&mut self, kind: AliasKind, new_name: Symbol, old_name: Symbol) -> NodeId {
+        self.push(Node::Alias { kind, new_name, old_name })
+    }
+
+    fn undef(&mut self, names: Vec<Symbol>) -> NodeId {
+        let names = push_list(&mut self.symbols, names);
+        self.push(Node::Undef { names })
+    }
+
+    fn target(&mut self, expr: NodeId) -> Result<NodeId, NodeId> {
+        let (callee, arguments) = match self[expr] {
+            Node::Constant { .. } | Node::Index { .. } => return Ok(expr),
+            Node::Call { callee, arguments, block: None } if arguments.is_empty() => (callee, arguments),
+            _ => return Err(expr),
+        };
+
+        // The call was the last thing built, so the target replaces it, and
+        // its path is the last in the arena.
+        debug_assert_eq!(expr.index(), self.nodes.len() - 1, "targets are made from the last node built");
+        self.nodes.pop();
+
+        let segments = callee.range();
+        let target = CallTarget::of(self.segments[segments.clone()].iter().map(|segment| {
+            (segment.name, segment.separator)
+        }));
+
+        let id = match target {
+            CallTarget::Constant => {
+                let mut scope = ConstantScope::Lexical;
+                let mut id = None;
+                for index in segments.clone() {
+                    let constant = self.push(Node::Constant { scope, name: self.segments[index].name });
+                    scope = ConstantScope::Expr(constant);
+                    id = Some(constant);
+                }
+
+                self.segments.truncate(segments.start);
+                id.expect("paths are never empty")
+            },
+            CallTarget::Variable => {
+                let name = self.segments[segments.start].name;
+                self.segments.truncate(segments.start);
+
+                let node = variable(&variable_target(name)).expect("variable targets are variables");
+                self.push(node)
+            },
+            CallTarget::Attribute => {
+                let name = self.segments.pop().unwrap().name;
+                let callee = List { len: callee.len - 1, ..callee };
+                let receiver = self.push(Node::Call { callee, arguments, block: None });
+
+                self.push(Node::Attribute { receiver, name })
+            },
+        };
+
+        Ok(id)
+    }
+
+    fn splat_target(&mut self, target: Option<NodeId>) -> NodeId {
+        self.push(Node::Splat { target })
+    }
+
+    fn group_target(&mut self, targets: Vec<NodeId>) -> NodeId {
+        let targets = self.list(targets);
+        self.push(Node::Group { targets })
+    }
+
+    fn assignment(&mut self, target: NodeId, value: NodeId) -> NodeId {
+        match self[target] {
+            Node::Constant { .. } => self.push(Node::ConstantAssignment { constant: target, value }),
+            _ => self.push(Node::Assignment { target, value }),
+        }
+    }
+
+    fn multiple_assignment(&mut self, targets: Vec<NodeId>, values: Vec<NodeId>) -> NodeId {
+        let targets = self.list(targets);
+        let values = self.list(values);
+        self.push(Node::MultipleAssignment { targets, values })
+    }
+
+    fn ternary(&mut self, condition: NodeId, if_true: NodeId, if_false: NodeId) -> NodeId {
+        self.push(Node::Ternary { condition, if_true, if_false })
+    }
+
+    fn binary(&mut self, operator: BinaryOp, lhs: NodeId, rhs: NodeId) -> NodeId {
+        self.push(Node::Binary { operator, lhs, rhs })
+    }
+
+    fn index(&mut self, receiver: NodeId, arguments: Vec<NodeId>) -> NodeId {
+        let arguments = self.list(arguments);
+        self.push(Node::Index { receiver, arguments })
+    }
+
+    /// Adds a constant path as a chain of `Constant` nodes, giving back the
+    /// last.
+    fn constant_path(&mut self, top_level: bool, parts: Vec<ast::Constant>) -> NodeId {
+        let mut scope = if top_level { ConstantScope::TopLevel } else { ConstantScope::Lexical };
+
+        let mut id = None;
+        for part in parts {
+            let constant = self.push(Node::Constant { scope, name: part.0 });
+            scope = ConstantScope::Expr(constant);
+            id = Some(constant);
+        }
+
+        id.expect("constant paths are never empty")
+    }
+
+    fn constant(&mut self, path: NodeId) -> NodeId { path }
+
+    fn scoped_constant(&mut self, scope: NodeId, name: ast::Constant) -> NodeId {
+        self.push(Node::Constant { scope: ConstantScope::Expr(scope), name: name.0 })
+    }
+
+    fn call(&mut self, callee: ast::Path, arguments: Vec<NodeId>, block: Option<NodeId>) -> NodeId {
+        let segments = callee.parts.iter().map(|segment| PathSegment {
+            name: segment.kind.name(),
+            separator: segment.separator,
+        });
+        let callee = push_list(&mut self.segments, segments);
+        let arguments = self.list(arguments);
+
+        self.push(Node::Call { callee, arguments, block })
+    }
+
+    fn block(&mut self, parameters: Vec<NodeId>, statements: Vec<NodeId>) -> NodeId {
+        let parameters = self.list(parameters);
+        let statements = self.list(statements);
+        self.push(Node::Block { parameters, statements })
+    }
+
+    fn for_loop(&mut self, targets: Vec<NodeId>, iterable: NodeId, statements: Vec<NodeId>) -> NodeId {
+        let targets = self.list(targets);
+        let statements = self.list(statements);
+        self.push(Node::For { targets, iterable, statements })
+    }
+
+    fn begin(&mut self, statements: Vec<NodeId>, rescue_clauses: Vec<NodeId>,
+             else_statements: Option<Vec<NodeId>>, ensure_statements: Option<Vec<NodeId>>) -> NodeId {
+        let statements = self.list(statements);
+        let rescue_clauses = self.list(rescue_clauses);
+        let else_statements = else_statements.map(|statements| self.list(statements));
+        let ensure_statements = ensure_statements.map(|statements| self.list(statements));
+
+        self.push(Node::Begin { statements, rescue_clauses, else_statements, ensure_statements })
+    }
+
+    fn rescue_clause(&mut self, exceptions: Vec<NodeId>, target: Option<NodeId>, statements: Vec<NodeId>) -> NodeId {
+        let exceptions = self.list(exceptions);
+        let statements = self.list(statements);
+        self.push(Node::RescueClause { exceptions, target, statements })
+    }
+
+    fn string(&mut self, value: String) -> NodeId {
+        let value = self.push_text(&value);
+        self.push(Node::StringLiteral { value })
+    }
+
+    fn integer(&mut self, value: i64) -> NodeId { self.push(Node::IntegerLiteral { value }) }
+    fn character(&mut self, value: char) -> NodeId { self.push(Node::CharacterLiteral { value }) }
+    fn symbol(&mut self, name: Symbol) -> NodeId { self.push(Node::Symbol { name }) }
+    fn key_value(&mut self, key: Symbol, value: NodeId) -> NodeId { self.push(Node::KeyValue { key, value }) }
+    fn paren(&mut self, inner: NodeId) -> NodeId { self.push(Node::Paren { inner }) }
+    fn negate(&mut self, inner: NodeId) -> NodeId { self.push(Node::Negate { inner }) }
+    fn defined(&mut self, inner: NodeId) -> NodeId { self.push(Node::Defined { inner }) }
+
+    fn super_call(&mut self, arguments: Vec<NodeId>) -> NodeId {
+        let arguments = self.list(arguments);
+        self.push(Node::Super { arguments })
+    }
+
+    fn zsuper(&mut self) -> NodeId { self.push(Node::ZSuper) }
+
+    fn yield_expr(&mut self, arguments: Vec<NodeId>) -> NodeId {
+        let arguments = self.list(arguments);
+        self.push(Node::Yield { arguments })
+    }
+
+    fn return_expr(&mut self, arguments: Vec<NodeId>) -> NodeId {
+        let arguments = self.list(arguments);
+        self.push(Node::Return { arguments })
+    }
+
+    fn error(&mut self) -> NodeId { self.push(Node::Error) }
+    fn missing(&mut self) -> NodeId { self.push(Node::Missing) }
+
+    // Nodes do not keep where they are in the source.
+    fn locate_item(&mut self, _: &mut NodeId, _: Span) {}
+    fn locate_stmt(&mut self, _: &mut NodeId, _: Span) {}
+    fn locate_expr(&mut self, _: &mut NodeId, _: Span) {}
+
+    fn describe(&self, &expr: &NodeId) -> &'static str {
+        match self[expr] {
+            Node::Assignment { .. } | Node::ConstantAssignment { .. } => "an assignment",
+            Node::MultipleAssignment { .. } => "a multiple assignment",
+            Node::Constant { .. } => "a constant",
+            Node::Paren { .. } => "a parenthesized expression",
+            Node::Call { .. } => "a method call",
+            Node::StringLiteral { .. } => "a string literal",
+            Node::IntegerLiteral { .. } => "an integer literal",
+            Node::CharacterLiteral { .. } => "a character literal",
+            Node::Symbol { .. } => "a symbol",
+            Node::KeyValue { .. } => "a `key: value` pair",
+            Node::Negate { .. } => "a negation",
+            Node::Defined { .. } => "a `defined?` expression",
+            Node::Super { .. } | Node::ZSuper => "a `super` call",
+            Node::Yield { .. } => "a `yield`",
+            Node::Return { .. } => "a `return`",
+            Node::Binary { .. } => "a binary operation",
+            Node::Ternary { .. } => "a ternary expression",
+            Node::Index { .. } => "an index expression",
+            Node::For { .. } => "a `for` loop",
+            Node::Begin { .. } => "a `begin` block",
+            Node::Error | Node::Missing => "an invalid expression",
+            ref node => unreachable!("{:?} is not an expression", node),
+        }
+    }
+
+    fn syntax_kind(&self, &expr: &NodeId) -> SyntaxKind {
+        match self[expr] {
+            Node::Assignment { .. } | Node::ConstantAssignment { .. } => SyntaxKind::Assignment,
+            Node::Constant { .. } => SyntaxKind::Constant,
+            Node::Paren { .. } => SyntaxKind::Paren,
+            Node::Call { .. } => SyntaxKind::Call,
+            Node::StringLiteral { .. } | Node::IntegerLiteral { .. } |
+                Node::CharacterLiteral { .. } => SyntaxKind::Literal,
+            Node::Symbol { .. } => SyntaxKind::Symbol,
+            Node::KeyValue { .. } => SyntaxKind::KeyValue,
+            Node::Negate { .. } => SyntaxKind::Negate,
+            Node::Defined { .. } => SyntaxKind::Defined,
+            Node::Super { .. } | Node::ZSuper => SyntaxKind::Super,
+            Node::Yield { .. } => SyntaxKind::Yield,
+            Node::Return { .. } => SyntaxKind::Return,
+            Node::Binary { .. } => SyntaxKind::Binary,
+            Node::Ternary { .. } => SyntaxKind::Ternary,
+            Node::MultipleAssignment { .. } => SyntaxKind::MultipleAssignment,
+            Node::Index { .. } => SyntaxKind::Index,
+            Node::For { .. } => SyntaxKind::For,
+            Node::Begin { .. } => SyntaxKind::Begin,
+            Node::Error => SyntaxKind::Error,
+            Node::Missing => SyntaxKind::Missing,
+            ref node => unreachable!("{:?} is not an expression", node),
+        }
+    }
+}
+
+impl<'a> Builder<'a>
+{
+    fn program(&mut self, program: &ast::Program) -> NodeId {
+        let items = program.items.iter().map(|item| self.item(item)).collect();
+        self.arena.program(items, program.data.as_deref(), &program.pragmas)
+    }
+
+    fn item(&mut self, item: &ast::Item) -> NodeId {
+        match *item {
+            ast::Item::Module(ref module) => {
+                let name = self.constant_path(&module.name);
+                let items = module.items.iter().map(|item| self.item(item)).collect();
+                self.arena.module(name, items)
+            },
+            ast::Item::Class(ref class) => {
+                let name = self.constant_path(&class.name);
+                let superclass = class.superclass.as_ref().map(|superclass| self.expr(superclass));
+                let items = class.items.iter().map(|item| self.item(item)).collect();
+                self.arena.class(name, superclass, items)
+            },
+            ast::Item::Function(ref function) => {
+                let parameters = function.parameters.iter().map(|parameter| {
+                    let default = parameter.default.as_ref().map(|default| self.expr(default));
+                    self.arena.parameter(parameter.name, default)
+                }).collect();
+                let statements = self.statements(&function.statements);
+                self.arena.function(function.name, function.singleton, function.visibility, parameters, statements)
+            },
+            ast::Item::Stmt(ref stmt) => self.stmt(stmt),
+            ast::Item::BeginBlock(ref block) => {
+                let statements = self.statements(&block.statements);
+                self.arena.begin_block(statements)
+            },
+            ast::Item::EndBlock(ref block) => {
+                let statements = self.statements(&block.statements);
+                self.arena.end_block(statements)
+            },
+        }
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) -> NodeId {
+        match *stmt {
+            ast::Stmt::Expr(ref expr) => self.expr(expr),
+            ast::Stmt::Alias(ref alias) => self.arena.alias(alias.kind, alias.new_name, alias.old_name),
+            ast::Stmt::Undef(ref undef) => self.arena.undef(undef.names.clone()),
+        }
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) -> NodeId {
+        match *expr {
+            ast::Expr::Assignment(ref assignment) => {
+                let target = self.assign_target(&assignment.assignee);
+                let value = self.expr(&assignment.value);
+                self.arena.assignment(target, value)
+            },
+            ast::Expr::ConstantAssignment(ref assignment) => {
+                let constant = self.constant_path(&assignment.constant);
+                let value = self.expr(&assignment.value);
+                self.arena.assignment(constant, value)
+            },
+            ast::Expr::Constant(ref constant) => self.constant_path(&constant.path),
+            ast::Expr::Paren(ref paren) => {
+                let inner = self.expr(&paren.inner);
+                self.arena.paren(inner)
+            },
+            ast::Expr::Call(ref call) => {
+                let arguments = self.arguments(&call.arguments);
+                let block = call.block.as_ref().map(|block| {
+                    let parameters = self.assign_targets(&block.parameters);
+                    let statements = self.statements(&block.statements);
+                    self.arena.block(parameters, statements)
+                });
+
+                self.arena.call(call.callee.clone(), arguments, block)
+            },
+            ast::Expr::StringLiteral(ref literal) => self.arena.string(literal.value.clone()),
+            ast::Expr::IntegerLiteral(ref literal) => self.arena.integer(literal.value),
+            ast::Expr::CharacterLiteral(ref literal) => self.arena.character(literal.value),
+            ast::Expr::Symbol(ref symbol) => self.arena.symbol(symbol.name),
+            ast::Expr::KeyValue(ref key_value) => {
+                let value = self.expr(&key_value.value);
+                self.arena.key_value(key_value.key, value)
+            },
+            ast::Expr::Negate(ref negate) => {
+                let inner = self.expr(&negate.inner);
+                self.arena.negate(inner)
+            },
+            ast::Expr::Defined(ref defined) => {
+                let inner = self.expr(&defined.inner);
+                self.arena.defined(inner)
+            },
+            ast::Expr::Super(ref super_expr) => {
+                let arguments = self.arguments(&super_expr.arguments);
+                self.arena.super_call(arguments)
+            },
+            ast::Expr::ZSuper(..) => self.arena.zsuper(),
+            ast::Expr::Yield(ref yield_expr) => {
+                let arguments = self.arguments(&yield_expr.arguments);
+                self.arena.yield_expr(arguments)
+            },
+            ast::Expr::Return(ref return_expr) => {
+                let arguments = self.arguments(&return_expr.arguments);
+                self.arena.return_expr(arguments)
+            },
+            ast::Expr::Binary(ref binary) => {
+                let lhs = self.expr(&binary.lhs);
+                let rhs = self.expr(&binary.rhs);
+                self.arena.binary(binary.operator, lhs, rhs)
+            },
+            ast::Expr::Ternary(ref ternary) => {
+                let condition = self.expr(&ternary.condition);
+                let if_true = self.expr(&ternary.if_true);
+                let if_false = self.expr(&ternary.if_false);
+                self.arena.ternary(condition, if_true, if_false)
+            },
+            ast::Expr::MultipleAssignment(ref assignment) => {
+                let targets = self.assign_targets(&assignment.targets);
+                let values = assignment.values.iter().map(|value| self.expr(value)).collect();
+                self.arena.multiple_assignment(targets, values)
+            },
+            ast::Expr::Index(ref index) => self.index(index),
+            ast::Expr::For(ref for_expr) => {
+                let targets = self.assign_targets(&for_expr.targets);
+                let iterable = self.expr(&for_expr.iterable);
+                let statements = self.statements(&for_expr.statements);
+                self.arena.for_loop(targets, iterable, statements)
+            },
+            ast::Expr::Begin(ref begin) => {
+                let statements = self.statements(&begin.statements);
+                let rescue_clauses = begin.rescue_clauses.iter().map(|clause| self.rescue_clause(clause)).collect();
+                let else_statements = begin.else_statements.as_ref().map(|statements| self.statements(statements));
+                let ensure_statements = begin.ensure_statements.as_ref().map(|statements| self.statements(statements));
+
+                self.arena.begin(statements, rescue_clauses, else_statements, ensure_statements)
+            },
+            ast::Expr::Error(..) => self.arena.error(),
+            ast::Expr::Missing(..) => self.arena.missing(),
+        }
+    }
+
+    fn rescue_clause(&mut self, clause: &ast::RescueClause) -> NodeId {
+        let exceptions = clause.exceptions.iter().map(|exception| self.expr(exception)).collect();
+        let target = clause.target.as_ref().map(|target| self.assign_target(target));
+        let statements = self.statements(&clause.statements);
+
+        self.arena.rescue_clause(exceptions, target, statements)
+    }
+
+    fn index(&mut self, index: &ast::IndexExpr) -> NodeId {
+        let receiver = self.expr(&index.receiver);
+        let arguments = self.arguments(&index.arguments);
+
+        self.arena.index(receiver, arguments)
+    }
+
+    fn statements(&mut self, statements: &[ast::Stmt]) -> Vec<NodeId> {
+        statements.iter().map(|stmt| self.stmt(stmt)).collect()
+    }
+
+    fn arguments(&mut self, arguments: &[ast::Argument]) -> Vec<NodeId> {
+        arguments.iter().map(|argument| match *argument {
+            ast::Argument::Positional(ref expr) => self.expr(expr),
+        }).collect()
+    }
+
+    fn assign_targets(&mut self, targets: &[ast::AssignTarget]) -> Vec<NodeId> {
+        targets.iter().map(|target| self.assign_target(target)).collect()
+    }
+
+    fn assign_target(&mut self, target: &ast::AssignTarget) -> NodeId {
+        if let Some(node) = variable(target) {
+            return self.arena.push(node);
+        }
+
+        match *target {
+            ast::AssignTarget::Constant(ref path) => self.constant_path(path),
+            ast::AssignTarget::Attribute(ref attribute) => {
+                let receiver = self.expr(&attribute.receiver);
+                self.arena.push(Node::Attribute { receiver, name: attribute.name })
+            },
+            ast::AssignTarget::Index(ref index) => self.index(index),
+            ast::AssignTarget::Splat(ref target) => {
+                let target = target.as_ref().map(|target| self.assign_target(target));
+                self.arena.splat_target(target)
+            },
+            ast::AssignTarget::Group(ref targets) => {
+                let targets = self.assign_targets(targets);
+                self.arena.group_target(targets)
+            },
+            ast::AssignTarget::Local(..) | ast::AssignTarget::InstanceVariable(..) |
+                ast::AssignTarget::GlobalVariable(..) | ast::AssignTarget::ClassVariable(..) => unreachable!(),
+        }
+    }
+
+    /// Adds a constant path as a chain of `Constant` nodes, giving back the
+    /// last.
+    fn constant_path(&mut self, path: &ast::ConstantPath) -> NodeId {
+        match path.scope {
+            ast::ConstantScope::Lexical => self.arena.constant_path(false, path.parts.clone()),
+            ast::ConstantScope::TopLevel => self.arena.constant_path(true, path.parts.clone()),
+            ast::ConstantScope::Expr(ref scope) => {
+                let scope = self.expr(scope);
+                path.parts.iter().fold(scope, |scope, &part| self.arena.scoped_constant(scope, part))
+            },
+        }
+    }
+}
+
+/// Gets the node for a variable target, if it is one.
+fn variable(target: &ast::AssignTarget) -> Option<Node> {
+    match *target {
+        ast::AssignTarget::Local(name) => Some(Node::Local { name: name.0 }),
+        ast::AssignTarget::InstanceVariable(name) => Some(Node::InstanceVariable { name: name.0 }),
+        ast::AssignTarget::GlobalVariable(name) => Some(Node::GlobalVariable { name: name.0 }),
+        ast::AssignTarget::ClassVariable(name) => Some(Node::ClassVariable { name: name.0 }),
+        _ => None,
+    }
+}
+
+/// Adds values to the end of a list's storage.
+fn push_list<T, I>(storage: &mut Vec<T>, values: I) -> List<T>
+    where I: IntoIterator<Item=T> {
+    let start = storage.len();
+    storage.extend(values);
+
+    List { start: start as u32, len: (storage.len() - start) as u32, marker: PhantomData }
+}
+
+impl Index<NodeId> for Arena
+{
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.index()]
+    }
+}
+
+impl Index<List<NodeId>> for Arena
+{
+    type Output = [NodeId];
+
+    fn index(&self, list: List<NodeId>) -> &[NodeId] {
+        &self.lists[list.range()]
+    }
+}
+
+impl Index<List<Symbol>> for Arena
+{
+    type Output = [Symbol];
+
+    fn index(&self, list: List<Symbol>) -> &[Symbol] {
+        &self.symbols[list.range()]
+    }
+}
+
+impl Index<List<PathSegment>> for Arena
+{
+    type Output = [PathSegment];
+
+    fn index(&self, list: List<PathSegment>) -> &[PathSegment] {
+        &self.segments[list.range()]
+    }
+}
+
+impl Index<Text> for Arena
+{
+    type Output = str;
+
+    fn index(&self, text: Text) -> &str {
+        &self.text[text.start as usize..text.end as usize]
+    }
+}
+
+// `List` is a handle whatever it holds, so these are written out rather than
+// derived with bounds on `T`.
+impl<T> Copy for List<T> {}
+
+impl<T> Clone for List<T>
+{
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> PartialEq for List<T>
+{
+    fn eq(&self, other: &Self) -> bool {
+        (self.start, self.len) == (other.start, other.len)
+    }
+}
+
+impl<T> Eq for List<T> {}
+
+impl<T> Hash for List<T>
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.start, self.len).hash(state)
+    }
+}
+
+impl<T> fmt::Debug for List<T>
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "List({}..{})", self.start, self.start + self.len)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::Parser;
+
+    use std::fs;
+    use std::path::Path;
+
+    fn arena(s: &str) -> Arena {
+        Arena::from_program(&Parser::from(s).parse().expect("failed to parse"))
+    }
+
+    #[test]
+    fn parses_into_the_same_arena_as_it_converts() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sexp");
+        let mut sources: Vec<_> = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "rb"))
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        sources.push("class A\n  def b(d, e = f(d)); end\n  def self.c; end\n  private :b, 'c'\n  private_class_method :c\nend\n".to_owned());
+        sources.push("a.b.c = x::Y\nd&.e = f(g)::H::I\nj(k)::L = 1\n$m, @@n = o, 1\n".to_owned());
+        sources.push("alias p q\nundef r, :s\nt = begin; 'u'; rescue V => w; ?x; else; y; ensure; z; end\n__END__\ndata\n".to_owned());
+
+        for source in sources.iter() {
+            let parsed = Parser::from(&source[..]).parse_arena().expect("failed to parse");
+            assert_eq!(parsed, arena(source), "parsing {:?} straight into an arena differs", source);
+        }
+    }
+
+    #[test]
+    fn stores_nodes_with_their_children() {
+        let arena = arena("class ::A::B < C::D\n  def d\n    e.f(1, :g => 'h')\n  end\nend\n");
+
+        let class = arena.children(arena.root()).next().unwrap();
+        let (name, superclass, function) = match arena[class] {
+            Node::Class { name, superclass: Some(superclass), items } => (name, superclass, arena[items][0]),
+            ref node => panic!("expected a class, found {:?}", node),
+        };
+        match arena[name] {
+            Node::Constant { scope: ConstantScope::Expr(scope), name } => {
+                assert_eq!(name.as_str(), "B");
+                assert_eq!(arena[scope], Node::Constant { scope: ConstantScope::TopLevel, name: Symbol::intern("A") });
+            },
+            ref node => panic!("expected a constant, found {:?}", node),
+        }
+        match arena[superclass] {
+            Node::Call { callee, .. } => assert!(arena[callee].iter().all(PathSegment::is_constant)),
+            ref node => panic!("expected a call, found {:?}", node),
+        }
+
+        let call = arena.children(function).next().unwrap();
+        match arena[call] {
+            Node::Call { callee, arguments, block: None } => {
+                let names: Vec<_> = arena[callee].iter().map(|segment| segment.name.as_str()).collect();
+                assert_eq!(names, ["e", "f"]);
+                assert_eq!(arena[arena[arguments][0]], Node::IntegerLiteral { value: 1 });
+                match arena[arena[arguments][1]] {
+                    Node::KeyValue { key, value } => {
+                        assert_eq!(key, Symbol::intern("g"));
+                        match arena[value] {
+                            Node::StringLiteral { value } => assert_eq!(&arena[value], "h"),
+                            ref node => panic!("expected a string, found {:?}", node),
+                        }
+                    },
+                    ref node => panic!("expected a key-value pair, found {:?}", node),
+                }
+            },
+            ref node => panic!("expected a call, found {:?}", node),
+        }
+    }
+
+    #[test]
+    fn links_children_to_their_parents() {
+        let arena = arena("module A\n  x = z.each { |y| y + 2 }\nend\n");
+
+        assert_eq!(arena.parent(arena.root()), None);
+        for id in arena.ids() {
+            for child in arena.children(id) {
+                assert_eq!(arena.parent(child), Some(id));
+                assert!(child < id, "children come before their parents");
+            }
+        }
+
+        let two = arena.ids().find(|&id| arena[id] == Node::IntegerLiteral { value: 2 }).unwrap();
+        let kinds: Vec<_> = arena.ancestors(two).map(|id| match arena[id] {
+            Node::Binary { .. } => "binary",
+            Node::Block { .. } => "block",
+            Node::Call { .. } => "call",
+            Node::Assignment { .. } => "assignment",
+            Node::Module { .. } => "module",
+            Node::Program { .. } => "program",
+            ref node => panic!("unexpected ancestor {:?}", node),
+        }).collect();
+        assert_eq!(kinds, ["binary", "block", "call", "assignment", "module", "program"]);
+    }
+}
@@ -0,0 +1,454 @@
+//! Comments, and the nodes they are attached to.
+//!
+//! Comments can be attached to classes, modules, functions, statements and
+//! the `key: value` elements of a hash. Each comment is attached to one
+//! node, picked from where the comment is:
+//!
+//! * A comment on its own line before a node leads that node.
+//! * A comment after a node, either at the end of its last line or on its
+//!   own line with no node following it, trails that node.
+//! * A comment inside a node which is not next to any of its children, as
+//!   in an empty class, dangles in that node. Comments which are not next
+//!   to anything at all dangle in the program.
+//!
+//! The parser records where every node comments can be attached to is, in
+//! the order the nodes start. The same nodes are found again in the AST by
+//! visiting it, as the visitor goes through the nodes in source order too.
+//!
+//! A comment remembers its node by what the node contains rather than where
+//! it is, so that it finds the node again after the program is folded,
+//! edited or deserialized. Classes, modules and functions are known by their
+//! first line, and statements and `key: value` elements by all of their
+//! code, along with the node they are in. Where several nodes in the same
+//! node look the same, they are told apart by how many of them come first,
+//! so removing one of them moves its comments onto the next. Otherwise, a
+//! comment whose node changes or goes away is no longer attached to anything.
+
+use ast::*;
+use span::Span;
+
+use std::collections::HashMap;
+use std::ptr;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// A comment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Comment
+{
+    /// The text of the comment, from the `#` up to the end of the line, or
+    /// from `=begin` to `=end`.
+    pub text: String,
+    /// Where the comment is in the source.
+    pub span: Span,
+    /// The line the comment starts on, starting from one.
+    pub line: usize,
+    /// Whether the comment has its line to itself, rather than following
+    /// code.
+    pub own_line: bool,
+    /// Where the comment is relative to the node it is attached to.
+    pub placement: CommentPlacement,
+    /// The node the comment is attached to, or nothing for the program.
+    pub(crate) node: Option<NodeKey>,
+}
+
+/// Picks out a node by what it contains.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct NodeKey
+{
+    /// A hash of the code of the node and the key of the node it is in.
+    fingerprint: u64,
+    /// How many nodes with the same fingerprint come before the node.
+    occurrence: usize,
+}
+
+/// Where a comment is relative to the node it is attached to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CommentPlacement
+{
+    /// Before the node.
+    Leading,
+    /// After the node.
+    Trailing,
+    /// Inside the node, but not next to any of its children.
+    Dangling,
+}
+
+/// A node that comments can be attached to.
+#[derive(Copy, Clone, Debug)]
+pub enum CommentTarget<'a>
+{
+    Program(&'a Program),
+    Module(&'a Module),
+    Class(&'a Class),
+    Function(&'a Function),
+    Stmt(&'a Stmt),
+    KeyValue(&'a KeyValueExpr),
+}
+
+/// Where a node that comments can be attached to is in the source.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct CommentableNode
+{
+    pub span: Span,
+    /// The line the last token of the node is on.
+    pub last_line: usize,
+}
+
+impl Comment
+{
+    /// Creates a comment which is not yet attached to a node.
+    pub(crate) fn new(text: String, span: Span, line: usize, own_line: bool) -> Self {
+        Comment { text, span, line, own_line, placement: CommentPlacement::Dangling, node: None }
+    }
+}
+
+impl Program
+{
+    /// Gets the comments attached to a node in the program, in source order.
+    ///
+    /// The node must be part of this program, rather than a copy of it.
+    pub fn comments_for<'a, N>(&'a self, node: N) -> Vec<&'a Comment>
+        where N: Into<CommentTarget<'a>> {
+        let key = match node.into() {
+            CommentTarget::Program(program) if ptr::eq(program, self) => None,
+            CommentTarget::Program(..) => return Vec::new(),
+            target => {
+                let mut keys = Keys::new(Some(target));
+                keys.visit_program(self);
+
+                match keys.found {
+                    Some(key) => Some(key),
+                    None => return Vec::new(),
+                }
+            },
+        };
+
+        self.comments.iter().filter(|comment| comment.node == key).collect()
+    }
+}
+
+/// Works out the keys of the nodes comments can be attached to, in source
+/// order.
+struct Keys<'a>
+{
+    /// The node to look for, or nothing to keep the key of every node.
+    target: Option<CommentTarget<'a>>,
+    /// How many nodes have been seen with each fingerprint.
+    seen: HashMap<u64, usize>,
+    /// The keys of the nodes containing the current one.
+    parents: Vec<NodeKey>,
+    keys: Vec<NodeKey>,
+    found: Option<NodeKey>,
+}
+
+impl<'a> Keys<'a>
+{
+    fn new(target: Option<CommentTarget<'a>>) -> Self {
+        Keys { target, seen: HashMap::new(), parents: Vec::new(), keys: Vec::new(), found: None }
+    }
+
+    /// Works out the key of a node, returning whether to go on into it.
+    ///
+    /// The node must be left with `exit` after visiting its children.
+    fn enter(&mut self, node: CommentTarget<'a>) -> bool {
+        if self.found.is_some() {
+            return false;
+        }
+
+        let fingerprint = node.fingerprint(self.parents.last().cloned());
+        let occurrence = self.seen.entry(fingerprint).or_insert(0);
+        let key = NodeKey { fingerprint, occurrence: *occurrence };
+        *occurrence += 1;
+
+        match self.target {
+            Some(target) if node.is(target) => self.found = Some(key),
+            Some(..) => (),
+            None => self.keys.push(key),
+        }
+
+        self.parents.push(key);
+        self.found.is_none()
+    }
+
+    fn exit(&mut self) {
+        self.parents.pop();
+    }
+}
+
+impl<'a> Visitor<'a> for Keys<'a>
+{
+    fn visit_module(&mut self, module: &'a Module) {
+        if self.enter(module.into()) {
+            visit::walk_module(self, module);
+            self.exit();
+        }
+    }
+
+    fn visit_class(&mut self, class: &'a Class) {
+        if self.enter(class.into()) {
+            visit::walk_class(self, class);
+            self.exit();
+        }
+    }
+
+    fn visit_function(&mut self, function: &'a Function) {
+        if self.enter(function.into()) {
+            visit::walk_function(self, function);
+            self.exit();
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        if self.enter(stmt.into()) {
+            visit::walk_stmt(self, stmt);
+            self.exit();
+        }
+    }
+
+    fn visit_key_value_expr(&mut self, expr: &'a KeyValueExpr) {
+        if self.enter(expr.into()) {
+            visit::walk_key_value_expr(self, expr);
+            self.exit();
+        }
+    }
+}
+
+impl<'a> CommentTarget<'a>
+{
+    /// Checks if two targets are the very same node.
+    fn is(self, other: CommentTarget) -> bool {
+        match (self, other) {
+            (CommentTarget::Program(a), CommentTarget::Program(b)) => ptr::eq(a, b),
+            (CommentTarget::Module(a), CommentTarget::Module(b)) => ptr::eq(a, b),
+            (CommentTarget::Class(a), CommentTarget::Class(b)) => ptr::eq(a, b),
+            (CommentTarget::Function(a), CommentTarget::Function(b)) => ptr::eq(a, b),
+            (CommentTarget::Stmt(a), CommentTarget::Stmt(b)) => ptr::eq(a, b),
+            (CommentTarget::KeyValue(a), CommentTarget::KeyValue(b)) => ptr::eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Hashes the code which picks out the node, along with the key of the
+    /// node it is in.
+    ///
+    /// Definitions are known by their first line, so that changing their
+    /// bodies leaves their own comments alone.
+    fn fingerprint(self, parent: Option<NodeKey>) -> u64 {
+        let code = match self {
+            CommentTarget::Program(..) => String::new(),
+            CommentTarget::Module(module) => {
+                Item::Module(Module { name: module.name.clone(), items: Vec::new(), span: module.span }).to_string()
+            },
+            CommentTarget::Class(class) => {
+                Item::Class(Class {
+                    name: class.name.clone(),
+                    items: Vec::new(),
+                    superclass: class.superclass.clone(),
+                    span: class.span,
+                }).to_string()
+            },
+            CommentTarget::Function(function) => {
+                Item::Function(Function {
+                    name: function.name,
+                    singleton: function.singleton,
+                    visibility: Visibility::Public,
+                    parameters: function.parameters.clone(),
+                    statements: Vec::new(),
+                    span: function.span,
+                }).to_string()
+            },
+            CommentTarget::Stmt(stmt) => stmt.to_string(),
+            CommentTarget::KeyValue(expr) => Expr::KeyValue(expr.clone()).to_string(),
+        };
+
+        let parent = parent.map(|parent| format!("{:x}:{}", parent.fingerprint, parent.occurrence));
+        fnv1a(format!("{}\n{}", parent.unwrap_or_default(), code).as_bytes())
+    }
+}
+
+/// Hashes bytes with 64-bit FNV-1a, which unlike the standard library's
+/// hasher gives the same hash in every version, so keys can be serialized.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl<'a> From<&'a Program> for CommentTarget<'a> { fn from(node: &'a Program) -> Self { CommentTarget::Program(node) } }
+impl<'a> From<&'a Module> for CommentTarget<'a> { fn from(node: &'a Module) -> Self { CommentTarget::Module(node) } }
+impl<'a> From<&'a Class> for CommentTarget<'a> { fn from(node: &'a Class) -> Self { CommentTarget::Class(node) } }
+impl<'a> From<&'a Function> for CommentTarget<'a> { fn from(node: &'a Function) -> Self { CommentTarget::Function(node) } }
+impl<'a> From<&'a Stmt> for CommentTarget<'a> { fn from(node: &'a Stmt) -> Self { CommentTarget::Stmt(node) } }
+impl<'a> From<&'a KeyValueExpr> for CommentTarget<'a> { fn from(node: &'a KeyValueExpr) -> Self { CommentTarget::KeyValue(node) } }
+
+/// Attaches the comments of a program to the nodes around them.
+///
+/// The nodes must be in the order they start in, with every node coming
+/// after the nodes containing it, and be the nodes of the program.
+pub(crate) fn attach(program: &mut Program, nodes: &[CommentableNode]) {
+    if program.comments.is_empty() {
+        return;
+    }
+
+    let mut keys = Keys::new(None);
+    keys.visit_program(program);
+
+    let keys = keys.keys;
+    let parents = parents(nodes);
+
+    for comment in program.comments.iter_mut() {
+        let contains = |node: &CommentableNode| {
+            node.span.start <= comment.span.start && comment.span.end <= node.span.end
+        };
+
+        // The innermost node containing the comment is the last one to start.
+        let enclosing = nodes.iter().rposition(contains);
+        let children: Vec<usize> = (0..nodes.len()).filter(|&index| parents[index] == enclosing).collect();
+
+        let before = children.iter().cloned().rfind(|&index| nodes[index].span.end <= comment.span.start);
+        let after = children.iter().cloned().find(|&index| nodes[index].span.start >= comment.span.end);
+
+        let (node, placement) = match (before, after) {
+            (Some(before), _) if !comment.own_line && nodes[before].last_line == comment.line => {
+                (Some(before), CommentPlacement::Trailing)
+            },
+            (_, Some(after)) if comment.own_line => (Some(after), CommentPlacement::Leading),
+            (Some(before), _) if comment.own_line => (Some(before), CommentPlacement::Trailing),
+            _ => (enclosing, CommentPlacement::Dangling),
+        };
+
+        comment.node = node.map(|index| keys[index]);
+        comment.placement = placement;
+    }
+}
+
+/// Finds the innermost node containing each node.
+fn parents(nodes: &[CommentableNode]) -> Vec<Option<usize>> {
+    let mut parents = Vec::with_capacity(nodes.len());
+    let mut open: Vec<usize> = Vec::new();
+
+    for (index, node) in nodes.iter().enumerate() {
+        while let Some(&last) = open.last() {
+            if nodes[last].span.end >= node.span.end && nodes[last].span.start <= node.span.start {
+                break;
+            }
+            open.pop();
+        }
+
+        parents.push(open.last().cloned());
+        open.push(index);
+    }
+
+    parents
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::Parser;
+
+    fn parse(s: &str) -> Program {
+        Parser::new(s.chars()).parse().expect("failed to parse")
+    }
+
+    /// Gets the text and placement of the comments attached to a node.
+    fn comments<'a, N>(program: &'a Program, node: N) -> Vec<(&'a str, CommentPlacement)>
+        where N: Into<CommentTarget<'a>> {
+        program.comments_for(node).into_iter().map(|comment| (&comment.text[..], comment.placement)).collect()
+    }
+
+    #[test]
+    fn attaches_comments_to_definitions() {
+        let program = parse("# A class.\nclass A # the name\n  # Does b.\n  def b\n    c # trailing\n    # after c\n  end\n\n  def d\n    # nothing yet\n  end\nend\n# the end\n");
+        let class = match program.items[0] {
+            Item::Class(ref class) => class,
+            ref item => panic!("expected a class but got {:?}", item),
+        };
+        let (b, d) = match (&class.items[0], &class.items[1]) {
+            (Item::Function(b), Item::Function(d)) => (b, d),
+            items => panic!("expected functions but got {:?}", items),
+        };
+
+        assert_eq!(comments(&program, class), vec![
+            ("# A class.", CommentPlacement::Leading),
+            ("# the name", CommentPlacement::Dangling),
+            ("# the end", CommentPlacement::Trailing),
+        ]);
+        assert_eq!(comments(&program, b), vec![("# Does b.", CommentPlacement::Leading)]);
+        assert_eq!(comments(&program, &b.statements[0]), vec![
+            ("# trailing", CommentPlacement::Trailing),
+            ("# after c", CommentPlacement::Trailing),
+        ]);
+        assert_eq!(comments(&program, d), vec![("# nothing yet", CommentPlacement::Dangling)]);
+        assert!(comments(&program, &program).is_empty());
+    }
+
+    #[test]
+    fn attaches_comments_to_hash_elements() {
+        let program = parse("a(\n  # the b\n  b: 1,\n  c: 2, # the c\n)\n");
+        let arguments = match program.items[0] {
+            Item::Stmt(Stmt::Expr(Expr::Call(ref call))) => &call.arguments,
+            ref item => panic!("expected a call but got {:?}", item),
+        };
+        let pairs: Vec<_> = arguments.iter().filter_map(|argument| match *argument {
+            Argument::Positional(Expr::KeyValue(ref pair)) => Some(pair),
+            _ => None,
+        }).collect();
+
+        assert_eq!(comments(&program, pairs[0]), vec![("# the b", CommentPlacement::Leading)]);
+        assert_eq!(comments(&program, pairs[1]), vec![("# the c", CommentPlacement::Trailing)]);
+    }
+
+    #[test]
+    fn keeps_comments_attached_after_folding_away_an_item() {
+        /// Removes the function `foo`, and renames `qux` to `quux`.
+        struct Rewrite;
+
+        impl Fold for Rewrite {
+            fn fold_item(&mut self, item: Item) -> Vec<Item> {
+                match item {
+                    Item::Function(ref function) if function.name.as_str() == "foo" => Vec::new(),
+                    item => fold::walk_item(self, item),
+                }
+            }
+
+            fn fold_call_expr(&mut self, mut expr: CallExpr) -> Expr {
+                if let [PathSegment { kind: PathSegmentKind::Identifier(ref mut name), .. }] = expr.callee.parts[..] {
+                    if name.0.as_str() == "qux" {
+                        *name = Identifier(Symbol::intern("quux"));
+                    }
+                }
+
+                fold::walk_call_expr(self, expr).into()
+            }
+        }
+
+        let program = Rewrite.fold_program(parse("# Does foo.\ndef foo\n  baz # in foo\nend\n\n# Does bar.\ndef bar\n  baz # in bar\n  qux # renamed\nend\n"));
+        let bar = match program.items[..] {
+            [Item::Function(ref bar)] => bar,
+            ref items => panic!("expected one function but got {:?}", items),
+        };
+
+        assert_eq!(comments(&program, bar), vec![("# Does bar.", CommentPlacement::Leading)]);
+        assert_eq!(comments(&program, &bar.statements[0]), vec![("# in bar", CommentPlacement::Trailing)]);
+        assert!(comments(&program, &bar.statements[1]).is_empty());
+        assert!(comments(&program, &program).is_empty());
+    }
+
+    #[test]
+    fn attaches_comments_to_the_program_when_there_is_nothing_else() {
+        let program = parse("=begin\nnothing\n=end\n# here\n");
+
+        assert_eq!(comments(&program, &program), vec![
+            ("=begin\nnothing\n=end", CommentPlacement::Dangling),
+            ("# here", CommentPlacement::Dangling),
+        ]);
+        assert!(comments(&program, &program.clone()).is_empty());
+    }
+}
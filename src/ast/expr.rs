@@ -1,10 +1,18 @@
 use ast;
+use symbol::Symbol;
+use span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 /// An expression.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Expr
 {
     Assignment(AssignmentExpr),
+    ConstantAssignment(ConstantAssignmentExpr),
+    Constant(ConstantExpr),
     Paren(ParenExpr),
     Call(CallExpr),
     StringLiteral(StringLiteral),
@@ -12,76 +20,446 @@ pub enum Expr
     Symbol(SymbolExpr),
     KeyValue(KeyValueExpr),
     Negate(NegateExpr),
+    Defined(DefinedExpr),
+    Super(SuperExpr),
+    ZSuper(ZSuperExpr),
+    Yield(YieldExpr),
+    Return(ReturnExpr),
+    Binary(BinaryExpr),
+    CharacterLiteral(CharacterLiteral),
+    Ternary(TernaryExpr),
+    MultipleAssignment(MultipleAssignmentExpr),
+    Index(IndexExpr),
+    For(ForExpr),
+    Begin(BeginExpr),
+    Error(ErrorExpr),
+    Missing(MissingExpr),
 }
 
 /// A `a = b` expression.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AssignmentExpr
 {
     /// The LHS
-    pub assignee: ast::Path,
+    pub assignee: ast::AssignTarget,
+    /// The new value.
+    pub value: Box<Expr>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `FOO = a` or `Foo::BAR = a` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConstantAssignmentExpr
+{
+    pub constant: ast::ConstantPath,
     /// The new value.
     pub value: Box<Expr>,
+    pub span: ast::NodeSpan,
+}
+
+/// A constant lookup which is not a plain path, `::Foo` or `obj.class::CONST`.
+///
+/// Plain paths such as `Foo::Bar` are part of a call's `Path`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConstantExpr
+{
+    pub path: ast::ConstantPath,
+    pub span: ast::NodeSpan,
+}
+
+/// A `a, b = c, d` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MultipleAssignmentExpr
+{
+    pub targets: Vec<ast::AssignTarget>,
+    pub values: Vec<Expr>,
+    pub span: ast::NodeSpan,
 }
 
 /// A `:key => value` or `key: value` expression.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KeyValueExpr
 {
-    pub key: String,
+    pub key: Symbol,
     pub value: Box<Expr>,
+    pub span: ast::NodeSpan,
 }
 
 /// A parenthesized expression.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ParenExpr
 {
     /// The expression in the parentheses.
     pub inner: Box<Expr>,
+    pub span: ast::NodeSpan,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CallExpr
 {
     pub callee: ast::Path,
-    pub arguments: Vec<ast::Argument>
+    pub arguments: Vec<ast::Argument>,
+    pub block: Option<ast::Block>,
+    pub span: ast::NodeSpan,
 }
 
+/// An `a[b]` expression.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IndexExpr
+{
+    pub receiver: Box<Expr>,
+    pub arguments: Vec<ast::Argument>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `for a in b ... end` loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ForExpr
+{
+    /// The loop variables.
+    pub targets: Vec<ast::AssignTarget>,
+    /// The value being iterated over.
+    pub iterable: Box<Expr>,
+    pub statements: Vec<ast::Stmt>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `begin ... rescue ... else ... ensure ... end` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BeginExpr
+{
+    pub statements: Vec<ast::Stmt>,
+    pub rescue_clauses: Vec<RescueClause>,
+    /// The statements run if no exception was raised.
+    pub else_statements: Option<Vec<ast::Stmt>>,
+    /// The statements which are always run.
+    pub ensure_statements: Option<Vec<ast::Stmt>>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `rescue A, B => target` clause.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RescueClause
+{
+    /// The exception classes which are rescued. Empty means `StandardError`.
+    pub exceptions: Vec<Expr>,
+    /// Where the rescued exception is stored.
+    pub target: Option<ast::AssignTarget>,
+    pub statements: Vec<ast::Stmt>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StringLiteral
 {
     pub value: String,
+    pub span: ast::NodeSpan,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct IntegerLiteral
 {
     pub value: i64,
+    pub span: ast::NodeSpan,
 }
 
+/// A character literal, such as `?a`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CharacterLiteral
+{
+    pub value: char,
+    pub span: ast::NodeSpan,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SymbolExpr
 {
-    pub name: String,
+    pub name: Symbol,
+    pub span: ast::NodeSpan,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NegateExpr
 {
     pub inner: Box<Expr>,
+    pub span: ast::NodeSpan,
 }
 
+/// A `defined?(expr)` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DefinedExpr
+{
+    pub inner: Box<Expr>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `super(a, b)` or `super a, b` expression with explicit arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SuperExpr
+{
+    pub arguments: Vec<ast::Argument>,
+    pub span: ast::NodeSpan,
+}
+
+/// Code which could not be parsed, in place of a statement or expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorExpr
+{
+    pub span: ast::NodeSpan,
+}
+
+/// An expression which was expected but not written, as in `a = `.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MissingExpr
+{
+    pub span: ast::NodeSpan,
+}
+
+/// A bare `super`, which implicitly passes along the current arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ZSuperExpr
+{
+    pub span: ast::NodeSpan,
+}
+
+/// A `yield` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct YieldExpr
+{
+    pub arguments: Vec<ast::Argument>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `return` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReturnExpr
+{
+    /// The returned values, empty for a bare `return`.
+    pub arguments: Vec<ast::Argument>,
+    pub span: ast::NodeSpan,
+}
+
+/// A `condition ? a : b` expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TernaryExpr
+{
+    pub condition: Box<Expr>,
+    pub if_true: Box<Expr>,
+    pub if_false: Box<Expr>,
+    pub span: ast::NodeSpan,
+}
+
+/// A binary operator expression, such as `a + b`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BinaryExpr
+{
+    pub operator: BinaryOp,
+    pub lhs: Box<Expr>,
+    pub rhs: Box<Expr>,
+    pub span: ast::NodeSpan,
+}
+
+/// A binary operator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BinaryOp
+{
+    /// `**`
+    Power,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `%`
+    Modulo,
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `<<`
+    ShiftLeft,
+    /// `>>`
+    ShiftRight,
+    /// `&`
+    BitAnd,
+    /// `|`
+    BitOr,
+    /// `^`
+    BitXor,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `<=>`
+    Compare,
+    /// `==`
+    Equal,
+    /// `===`
+    CaseEqual,
+    /// `!=`
+    NotEqual,
+    /// `=~`
+    Match,
+    /// `!~`
+    NotMatch,
+    /// `&&`
+    LogicalAnd,
+    /// `||`
+    LogicalOr,
+}
+
+impl CallExpr
+{
+    /// Gets what a call to a visibility modifier such as `private` or
+    /// `private_class_method` does, if this is one.
+    ///
+    /// Gives back whether it affects singleton functions, and the visibility
+    /// it sets.
+    pub fn visibility_modifier(&self) -> Option<(bool, ast::Visibility)> {
+        match self.callee.parts.as_slice() {
+            [ast::PathSegment { kind: ast::PathSegmentKind::Identifier(ast::Identifier(name)), .. }] => {
+                ast::Visibility::from_modifier(name.as_str())
+            },
+            _ => None,
+        }
+    }
+}
+
+impl BinaryOp
+{
+    /// Every binary operator.
+    pub const ALL: &'static [BinaryOp] = &[
+        BinaryOp::Power, BinaryOp::Multiply, BinaryOp::Divide, BinaryOp::Modulo,
+        BinaryOp::Add, BinaryOp::Subtract, BinaryOp::ShiftLeft, BinaryOp::ShiftRight,
+        BinaryOp::BitAnd, BinaryOp::BitOr, BinaryOp::BitXor,
+        BinaryOp::Less, BinaryOp::LessEqual, BinaryOp::Greater, BinaryOp::GreaterEqual,
+        BinaryOp::Compare, BinaryOp::Equal, BinaryOp::CaseEqual, BinaryOp::NotEqual,
+        BinaryOp::Match, BinaryOp::NotMatch, BinaryOp::LogicalAnd, BinaryOp::LogicalOr,
+    ];
+
+    /// Looks up an operator from its spelling.
+    pub fn from_symbol(symbol: &str) -> Option<BinaryOp> {
+        BinaryOp::ALL.iter().cloned().find(|op| op.symbol() == symbol)
+    }
+
+    /// Gets the spelling of the operator.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Power => "**",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Compare => "<=>",
+            BinaryOp::Equal => "==",
+            BinaryOp::CaseEqual => "===",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Match => "=~",
+            BinaryOp::NotMatch => "!~",
+            BinaryOp::LogicalAnd => "&&",
+            BinaryOp::LogicalOr => "||",
+        }
+    }
+
+    /// Gets how tightly the operator binds. Higher binds more tightly.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::LogicalOr => 1,
+            BinaryOp::LogicalAnd => 2,
+            BinaryOp::Compare | BinaryOp::Equal | BinaryOp::CaseEqual |
+                BinaryOp::NotEqual | BinaryOp::Match | BinaryOp::NotMatch => 3,
+            BinaryOp::Less | BinaryOp::LessEqual |
+                BinaryOp::Greater | BinaryOp::GreaterEqual => 4,
+            BinaryOp::BitOr | BinaryOp::BitXor => 5,
+            BinaryOp::BitAnd => 6,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 7,
+            BinaryOp::Add | BinaryOp::Subtract => 8,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 9,
+            BinaryOp::Power => 10,
+        }
+    }
+
+    /// Checks if `a op b op c` groups as `a op (b op c)`.
+    pub fn is_right_associative(self) -> bool {
+        self == BinaryOp::Power
+    }
+}
+
+/// Gets and sets the span of every kind of expression.
+macro_rules! expr_spans {
+    ($($variant:ident),*) => {
+        impl Expr
+        {
+            /// Gets where the expression is in the source, if it was parsed.
+            pub fn span(&self) -> Option<Span> {
+                match *self {
+                    $(Expr::$variant(ref expr) => expr.span.get(),)*
+                }
+            }
+
+            /// Records where the expression is in the source.
+            pub fn set_span(&mut self, span: Span) {
+                match *self {
+                    $(Expr::$variant(ref mut expr) => expr.span = ast::NodeSpan::new(span),)*
+                }
+            }
+        }
+    }
+}
+
+expr_spans!(Assignment, ConstantAssignment, Constant, Paren, Call, StringLiteral, IntegerLiteral, Symbol, KeyValue, Negate, Defined, Super, ZSuper, Yield, Return, Binary, CharacterLiteral, Ternary, MultipleAssignment, Index, For, Begin, Error, Missing);
+
 macro_rules! expr_boilerplate {
     ($ty:ty => $shortname:ident) => {
-        impl Into<Expr> for $ty {
-            fn into(self) -> Expr {
-                Expr::$shortname(self)
+        impl From<$ty> for Expr {
+            fn from(expr: $ty) -> Expr {
+                Expr::$shortname(expr)
             }
         }
     }
 }
 
 expr_boilerplate!(AssignmentExpr => Assignment);
+expr_boilerplate!(ConstantAssignmentExpr => ConstantAssignment);
+expr_boilerplate!(ConstantExpr => Constant);
 expr_boilerplate!(ParenExpr => Paren);
 expr_boilerplate!(CallExpr => Call);
 expr_boilerplate!(StringLiteral => StringLiteral);
@@ -89,3 +467,17 @@ expr_boilerplate!(IntegerLiteral => IntegerLiteral);
 expr_boilerplate!(SymbolExpr => Symbol);
 expr_boilerplate!(KeyValueExpr => KeyValue);
 expr_boilerplate!(NegateExpr => Negate);
+expr_boilerplate!(DefinedExpr => Defined);
+expr_boilerplate!(SuperExpr => Super);
+expr_boilerplate!(ZSuperExpr => ZSuper);
+expr_boilerplate!(YieldExpr => Yield);
+expr_boilerplate!(ReturnExpr => Return);
+expr_boilerplate!(BinaryExpr => Binary);
+expr_boilerplate!(CharacterLiteral => CharacterLiteral);
+expr_boilerplate!(TernaryExpr => Ternary);
+expr_boilerplate!(MultipleAssignmentExpr => MultipleAssignment);
+expr_boilerplate!(IndexExpr => Index);
+expr_boilerplate!(ForExpr => For);
+expr_boilerplate!(BeginExpr => Begin);
+expr_boilerplate!(ErrorExpr => Error);
+expr_boilerplate!(MissingExpr => Missing);
@@ -0,0 +1,467 @@
+//! Rewriting the AST.
+//!
+//! Implement `Fold` and override the methods for the nodes to be rewritten.
+//! Each method takes ownership of a node and gives back what replaces it,
+//! so an expression can be replaced by a different kind of expression, and
+//! an item or statement by any number of them, including none.
+//!
+//! ```
+//! use rubic::ast::{self, fold, Fold};
+//!
+//! /// Removes every `BEGIN { ... }` block.
+//! struct RemoveBeginBlocks;
+//!
+//! impl Fold for RemoveBeginBlocks {
+//!     fn fold_item(&mut self, item: ast::Item) -> Vec<ast::Item> {
+//!         match item {
+//!             ast::Item::BeginBlock(..) => Vec::new(),
+//!             item => fold::walk_item(self, item),
+//!         }
+//!     }
+//! }
+//! ```
+
+use ast::*;
+
+use std::mem;
+
+/// Something which rebuilds the AST, node by node.
+///
+/// Every method folds the children of its node by default.
+pub trait Fold
+{
+    fn fold_program(&mut self, program: Program) -> Program { walk_program(self, program) }
+    fn fold_items(&mut self, items: Vec<Item>) -> Vec<Item> { walk_items(self, items) }
+    fn fold_item(&mut self, item: Item) -> Vec<Item> { walk_item(self, item) }
+    fn fold_module(&mut self, module: Module) -> Module { walk_module(self, module) }
+    fn fold_class(&mut self, class: Class) -> Class { walk_class(self, class) }
+    fn fold_function(&mut self, function: Function) -> Function { walk_function(self, function) }
+    fn fold_begin_block(&mut self, block: BeginBlock) -> BeginBlock { walk_begin_block(self, block) }
+    fn fold_end_block(&mut self, block: EndBlock) -> EndBlock { walk_end_block(self, block) }
+
+    fn fold_stmts(&mut self, statements: Vec<Stmt>) -> Vec<Stmt> { walk_stmts(self, statements) }
+    fn fold_stmt(&mut self, stmt: Stmt) -> Vec<Stmt> { vec![walk_stmt(self, stmt)] }
+    fn fold_alias(&mut self, alias: Alias) -> Alias { alias }
+    fn fold_undef(&mut self, undef: Undef) -> Undef { undef }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr { walk_expr(self, expr) }
+    fn fold_assignment_expr(&mut self, expr: AssignmentExpr) -> Expr { walk_assignment_expr(self, expr).into() }
+    fn fold_constant_assignment_expr(&mut self, expr: ConstantAssignmentExpr) -> Expr { walk_constant_assignment_expr(self, expr).into() }
+    fn fold_constant_expr(&mut self, expr: ConstantExpr) -> Expr { walk_constant_expr(self, expr).into() }
+    fn fold_paren_expr(&mut self, expr: ParenExpr) -> Expr { walk_paren_expr(self, expr).into() }
+    fn fold_call_expr(&mut self, expr: CallExpr) -> Expr { walk_call_expr(self, expr).into() }
+    fn fold_string_literal(&mut self, literal: StringLiteral) -> Expr { literal.into() }
+    fn fold_integer_literal(&mut self, literal: IntegerLiteral) -> Expr { literal.into() }
+    fn fold_symbol_expr(&mut self, expr: SymbolExpr) -> Expr { expr.into() }
+    fn fold_key_value_expr(&mut self, expr: KeyValueExpr) -> Expr { walk_key_value_expr(self, expr).into() }
+    fn fold_negate_expr(&mut self, expr: NegateExpr) -> Expr { walk_negate_expr(self, expr).into() }
+    fn fold_defined_expr(&mut self, expr: DefinedExpr) -> Expr { walk_defined_expr(self, expr).into() }
+    fn fold_super_expr(&mut self, expr: SuperExpr) -> Expr { walk_super_expr(self, expr).into() }
+    fn fold_zsuper_expr(&mut self, expr: ZSuperExpr) -> Expr { expr.into() }
+    fn fold_yield_expr(&mut self, expr: YieldExpr) -> Expr { walk_yield_expr(self, expr).into() }
+    fn fold_return_expr(&mut self, expr: ReturnExpr) -> Expr { walk_return_expr(self, expr).into() }
+    fn fold_binary_expr(&mut self, expr: BinaryExpr) -> Expr { walk_binary_expr(self, expr).into() }
+    fn fold_character_literal(&mut self, literal: CharacterLiteral) -> Expr { literal.into() }
+    fn fold_ternary_expr(&mut self, expr: TernaryExpr) -> Expr { walk_ternary_expr(self, expr).into() }
+    fn fold_multiple_assignment_expr(&mut self, expr: MultipleAssignmentExpr) -> Expr { walk_multiple_assignment_expr(self, expr).into() }
+    fn fold_index_expr(&mut self, expr: IndexExpr) -> Expr { walk_index_expr(self, expr).into() }
+    fn fold_for_expr(&mut self, expr: ForExpr) -> Expr { walk_for_expr(self, expr).into() }
+    fn fold_begin_expr(&mut self, expr: BeginExpr) -> Expr { walk_begin_expr(self, expr).into() }
+    fn fold_rescue_clause(&mut self, clause: RescueClause) -> RescueClause { walk_rescue_clause(self, clause) }
+    fn fold_error_expr(&mut self, expr: ErrorExpr) -> Expr { expr.into() }
+    fn fold_missing_expr(&mut self, expr: MissingExpr) -> Expr { expr.into() }
+
+    fn fold_block(&mut self, block: Block) -> Block { walk_block(self, block) }
+    fn fold_assign_target(&mut self, target: AssignTarget) -> AssignTarget { walk_assign_target(self, target) }
+    fn fold_attribute_target(&mut self, target: AttributeTarget) -> AttributeTarget { walk_attribute_target(self, target) }
+    fn fold_constant_path(&mut self, path: ConstantPath) -> ConstantPath { walk_constant_path(self, path) }
+    fn fold_path(&mut self, path: Path) -> Path { walk_path(self, path) }
+    fn fold_path_segment(&mut self, segment: PathSegment) -> PathSegment { segment }
+    fn fold_argument(&mut self, argument: Argument) -> Argument { walk_argument(self, argument) }
+    fn fold_parameter(&mut self, parameter: Parameter) -> Parameter { walk_parameter(self, parameter) }
+}
+
+pub fn walk_program<F>(folder: &mut F, program: Program) -> Program
+    where F: Fold + ?Sized {
+    Program { items: folder.fold_items(program.items), ..program }
+}
+
+pub fn walk_items<F>(folder: &mut F, items: Vec<Item>) -> Vec<Item>
+    where F: Fold + ?Sized {
+    items.into_iter().flat_map(|item| folder.fold_item(item)).collect()
+}
+
+/// Folds the children of an item.
+///
+/// A statement item is replaced by however many statements it folds into.
+pub fn walk_item<F>(folder: &mut F, item: Item) -> Vec<Item>
+    where F: Fold + ?Sized {
+    let item = match item {
+        Item::Module(module) => folder.fold_module(module).into(),
+        Item::Class(class) => folder.fold_class(class).into(),
+        Item::Function(function) => folder.fold_function(function).into(),
+        Item::Stmt(stmt) => return folder.fold_stmt(stmt).into_iter().map(Item::Stmt).collect(),
+        Item::BeginBlock(block) => folder.fold_begin_block(block).into(),
+        Item::EndBlock(block) => folder.fold_end_block(block).into(),
+    };
+
+    vec![item]
+}
+
+pub fn walk_module<F>(folder: &mut F, module: Module) -> Module
+    where F: Fold + ?Sized {
+    Module {
+        name: folder.fold_constant_path(module.name),
+        items: folder.fold_items(module.items),
+        span: module.span,
+    }
+}
+
+pub fn walk_class<F>(folder: &mut F, class: Class) -> Class
+    where F: Fold + ?Sized {
+    Class {
+        name: folder.fold_constant_path(class.name),
+        superclass: class.superclass.map(|superclass| folder.fold_expr(superclass)),
+        items: folder.fold_items(class.items),
+        span: class.span,
+    }
+}
+
+pub fn walk_function<F>(folder: &mut F, function: Function) -> Function
+    where F: Fold + ?Sized {
+    Function {
+        parameters: function.parameters.into_iter().map(|parameter| folder.fold_parameter(parameter)).collect(),
+        statements: folder.fold_stmts(function.statements),
+        ..function
+    }
+}
+
+pub fn walk_begin_block<F>(folder: &mut F, block: BeginBlock) -> BeginBlock
+    where F: Fold + ?Sized {
+    BeginBlock { statements: folder.fold_stmts(block.statements), span: block.span }
+}
+
+pub fn walk_end_block<F>(folder: &mut F, block: EndBlock) -> EndBlock
+    where F: Fold + ?Sized {
+    EndBlock { statements: folder.fold_stmts(block.statements), span: block.span }
+}
+
+pub fn walk_stmts<F>(folder: &mut F, statements: Vec<Stmt>) -> Vec<Stmt>
+    where F: Fold + ?Sized {
+    statements.into_iter().flat_map(|stmt| folder.fold_stmt(stmt)).collect()
+}
+
+pub fn walk_stmt<F>(folder: &mut F, stmt: Stmt) -> Stmt
+    where F: Fold + ?Sized {
+    match stmt {
+        Stmt::Expr(expr) => Stmt::Expr(folder.fold_expr(expr)),
+        Stmt::Alias(alias) => Stmt::Alias(folder.fold_alias(alias)),
+        Stmt::Undef(undef) => Stmt::Undef(folder.fold_undef(undef)),
+    }
+}
+
+pub fn walk_expr<F>(folder: &mut F, expr: Expr) -> Expr
+    where F: Fold + ?Sized {
+    match expr {
+        Expr::Assignment(expr) => folder.fold_assignment_expr(expr),
+        Expr::ConstantAssignment(expr) => folder.fold_constant_assignment_expr(expr),
+        Expr::Constant(expr) => folder.fold_constant_expr(expr),
+        Expr::Paren(expr) => folder.fold_paren_expr(expr),
+        Expr::Call(expr) => folder.fold_call_expr(expr),
+        Expr::StringLiteral(literal) => folder.fold_string_literal(literal),
+        Expr::IntegerLiteral(literal) => folder.fold_integer_literal(literal),
+        Expr::Symbol(expr) => folder.fold_symbol_expr(expr),
+        Expr::KeyValue(expr) => folder.fold_key_value_expr(expr),
+        Expr::Negate(expr) => folder.fold_negate_expr(expr),
+        Expr::Defined(expr) => folder.fold_defined_expr(expr),
+        Expr::Super(expr) => folder.fold_super_expr(expr),
+        Expr::ZSuper(expr) => folder.fold_zsuper_expr(expr),
+        Expr::Yield(expr) => folder.fold_yield_expr(expr),
+        Expr::Return(expr) => folder.fold_return_expr(expr),
+        Expr::Binary(expr) => folder.fold_binary_expr(expr),
+        Expr::CharacterLiteral(literal) => folder.fold_character_literal(literal),
+        Expr::Ternary(expr) => folder.fold_ternary_expr(expr),
+        Expr::MultipleAssignment(expr) => folder.fold_multiple_assignment_expr(expr),
+        Expr::Index(expr) => folder.fold_index_expr(expr),
+        Expr::For(expr) => folder.fold_for_expr(expr),
+        Expr::Begin(expr) => folder.fold_begin_expr(expr),
+        Expr::Error(expr) => folder.fold_error_expr(expr),
+        Expr::Missing(expr) => folder.fold_missing_expr(expr),
+    }
+}
+
+pub fn walk_assignment_expr<F>(folder: &mut F, expr: AssignmentExpr) -> AssignmentExpr
+    where F: Fold + ?Sized {
+    AssignmentExpr {
+        assignee: folder.fold_assign_target(expr.assignee),
+        value: fold_boxed(folder, expr.value),
+        span: expr.span,
+    }
+}
+
+pub fn walk_constant_assignment_expr<F>(folder: &mut F, expr: ConstantAssignmentExpr) -> ConstantAssignmentExpr
+    where F: Fold + ?Sized {
+    ConstantAssignmentExpr {
+        constant: folder.fold_constant_path(expr.constant),
+        value: fold_boxed(folder, expr.value),
+        span: expr.span,
+    }
+}
+
+pub fn walk_constant_expr<F>(folder: &mut F, expr: ConstantExpr) -> ConstantExpr
+    where F: Fold + ?Sized {
+    ConstantExpr { path: folder.fold_constant_path(expr.path), span: expr.span }
+}
+
+pub fn walk_paren_expr<F>(folder: &mut F, expr: ParenExpr) -> ParenExpr
+    where F: Fold + ?Sized {
+    ParenExpr { inner: fold_boxed(folder, expr.inner), span: expr.span }
+}
+
+pub fn walk_call_expr<F>(folder: &mut F, expr: CallExpr) -> CallExpr
+    where F: Fold + ?Sized {
+    CallExpr {
+        callee: folder.fold_path(expr.callee),
+        arguments: fold_arguments(folder, expr.arguments),
+        block: expr.block.map(|block| folder.fold_block(block)),
+        span: expr.span,
+    }
+}
+
+pub fn walk_key_value_expr<F>(folder: &mut F, expr: KeyValueExpr) -> KeyValueExpr
+    where F: Fold + ?Sized {
+    KeyValueExpr { value: fold_boxed(folder, expr.value), ..expr }
+}
+
+pub fn walk_negate_expr<F>(folder: &mut F, expr: NegateExpr) -> NegateExpr
+    where F: Fold + ?Sized {
+    NegateExpr { inner: fold_boxed(folder, expr.inner), span: expr.span }
+}
+
+pub fn walk_defined_expr<F>(folder: &mut F, expr: DefinedExpr) -> DefinedExpr
+    where F: Fold + ?Sized {
+    DefinedExpr { inner: fold_boxed(folder, expr.inner), span: expr.span }
+}
+
+pub fn walk_super_expr<F>(folder: &mut F, expr: SuperExpr) -> SuperExpr
+    where F: Fold + ?Sized {
+    SuperExpr { arguments: fold_arguments(folder, expr.arguments), span: expr.span }
+}
+
+pub fn walk_yield_expr<F>(folder: &mut F, expr: YieldExpr) -> YieldExpr
+    where F: Fold + ?Sized {
+    YieldExpr { arguments: fold_arguments(folder, expr.arguments), span: expr.span }
+}
+
+pub fn walk_return_expr<F>(folder: &mut F, expr: ReturnExpr) -> ReturnExpr
+    where F: Fold + ?Sized {
+    ReturnExpr { arguments: fold_arguments(folder, expr.arguments), span: expr.span }
+}
+
+pub fn walk_binary_expr<F>(folder: &mut F, expr: BinaryExpr) -> BinaryExpr
+    where F: Fold + ?Sized {
+    BinaryExpr {
+        operator: expr.operator,
+        lhs: fold_boxed(folder, expr.lhs),
+        rhs: fold_boxed(folder, expr.rhs),
+        span: expr.span,
+    }
+}
+
+pub fn walk_ternary_expr<F>(folder: &mut F, expr: TernaryExpr) -> TernaryExpr
+    where F: Fold + ?Sized {
+    TernaryExpr {
+        condition: fold_boxed(folder, expr.condition),
+        if_true: fold_boxed(folder, expr.if_true),
+        if_false: fold_boxed(folder, expr.if_false),
+        span: expr.span,
+    }
+}
+
+pub fn walk_multiple_assignment_expr<F>(folder: &mut F, expr: MultipleAssignmentExpr) -> MultipleAssignmentExpr
+    where F: Fold + ?Sized {
+    MultipleAssignmentExpr {
+        targets: fold_assign_targets(folder, expr.targets),
+        values: expr.values.into_iter().map(|value| folder.fold_expr(value)).collect(),
+        span: expr.span,
+    }
+}
+
+pub fn walk_index_expr<F>(folder: &mut F, expr: IndexExpr) -> IndexExpr
+    where F: Fold + ?Sized {
+    IndexExpr {
+        receiver: fold_boxed(folder, expr.receiver),
+        arguments: fold_arguments(folder, expr.arguments),
+        span: expr.span,
+    }
+}
+
+pub fn walk_for_expr<F>(folder: &mut F, expr: ForExpr) -> ForExpr
+    where F: Fold + ?Sized {
+    ForExpr {
+        targets: fold_assign_targets(folder, expr.targets),
+        iterable: fold_boxed(folder, expr.iterable),
+        statements: folder.fold_stmts(expr.statements),
+        span: expr.span,
+    }
+}
+
+pub fn walk_begin_expr<F>(folder: &mut F, expr: BeginExpr) -> BeginExpr
+    where F: Fold + ?Sized {
+    BeginExpr {
+        statements: folder.fold_stmts(expr.statements),
+        rescue_clauses: expr.rescue_clauses.into_iter().map(|clause| folder.fold_rescue_clause(clause)).collect(),
+        else_statements: expr.else_statements.map(|statements| folder.fold_stmts(statements)),
+        ensure_statements: expr.ensure_statements.map(|statements| folder.fold_stmts(statements)),
+        span: expr.span,
+    }
+}
+
+pub fn walk_rescue_clause<F>(folder: &mut F, clause: RescueClause) -> RescueClause
+    where F: Fold + ?Sized {
+    RescueClause {
+        exceptions: clause.exceptions.into_iter().map(|exception| folder.fold_expr(exception)).collect(),
+        target: clause.target.map(|target| folder.fold_assign_target(target)),
+        statements: folder.fold_stmts(clause.statements),
+    }
+}
+
+pub fn walk_block<F>(folder: &mut F, block: Block) -> Block
+    where F: Fold + ?Sized {
+    Block {
+        parameters: fold_assign_targets(folder, block.parameters),
+        statements: folder.fold_stmts(block.statements),
+    }
+}
+
+/// Folds the children of an assignment target.
+///
+/// An `a[b]` target is an `IndexExpr` but must stay one, so it has its
+/// children folded rather than going through `fold_index_expr`.
+pub fn walk_assign_target<F>(folder: &mut F, target: AssignTarget) -> AssignTarget
+    where F: Fold + ?Sized {
+    match target {
+        AssignTarget::Constant(path) => AssignTarget::Constant(folder.fold_constant_path(path)),
+        AssignTarget::Attribute(target) => AssignTarget::Attribute(folder.fold_attribute_target(target)),
+        AssignTarget::Index(expr) => AssignTarget::Index(walk_index_expr(folder, expr)),
+        AssignTarget::Splat(target) => AssignTarget::Splat(target.map(|target| {
+            Box::new(folder.fold_assign_target(*target))
+        })),
+        AssignTarget::Group(targets) => AssignTarget::Group(fold_assign_targets(folder, targets)),
+        target => target,
+    }
+}
+
+pub fn walk_attribute_target<F>(folder: &mut F, target: AttributeTarget) -> AttributeTarget
+    where F: Fold + ?Sized {
+    AttributeTarget { receiver: fold_boxed(folder, target.receiver), ..target }
+}
+
+pub fn walk_constant_path<F>(folder: &mut F, path: ConstantPath) -> ConstantPath
+    where F: Fold + ?Sized {
+    let scope = match path.scope {
+        ConstantScope::Expr(scope) => ConstantScope::Expr(fold_boxed(folder, scope)),
+        scope => scope,
+    };
+
+    ConstantPath { scope, ..path }
+}
+
+pub fn walk_path<F>(folder: &mut F, path: Path) -> Path
+    where F: Fold + ?Sized {
+    path.parts.into_iter().map(|segment| folder.fold_path_segment(segment)).collect()
+}
+
+pub fn walk_argument<F>(folder: &mut F, argument: Argument) -> Argument
+    where F: Fold + ?Sized {
+    match argument {
+        Argument::Positional(expr) => Argument::Positional(folder.fold_expr(expr)),
+    }
+}
+
+pub fn walk_parameter<F>(folder: &mut F, parameter: Parameter) -> Parameter
+    where F: Fold + ?Sized {
+    Parameter { default: parameter.default.map(|default| fold_boxed(folder, default)), ..parameter }
+}
+
+/// Folds a boxed expression, reusing its box.
+fn fold_boxed<F>(folder: &mut F, mut expr: Box<Expr>) -> Box<Expr>
+    where F: Fold + ?Sized {
+    let inner = mem::replace(&mut *expr, MissingExpr { span: NodeSpan::default() }.into());
+    *expr = folder.fold_expr(inner);
+    expr
+}
+
+fn fold_arguments<F>(folder: &mut F, arguments: Vec<Argument>) -> Vec<Argument>
+    where F: Fold + ?Sized {
+    arguments.into_iter().map(|argument| folder.fold_argument(argument)).collect()
+}
+
+fn fold_assign_targets<F>(folder: &mut F, targets: Vec<AssignTarget>) -> Vec<AssignTarget>
+    where F: Fold + ?Sized {
+    targets.into_iter().map(|target| folder.fold_assign_target(target)).collect()
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::Parser;
+
+    fn parse(s: &str) -> Program {
+        Parser::from(s).parse().expect("failed to parse")
+    }
+
+    /// Gets the name of the method a call calls.
+    fn method_name(call: &CallExpr) -> Option<&str> {
+        match call.callee.parts.last()?.kind {
+            PathSegmentKind::Identifier(Identifier(name)) => Some(name.as_str()),
+            PathSegmentKind::Constant(..) => None,
+        }
+    }
+
+    /// Desugars `a.freeze` into `a` and `twice(a)` into `a; a`, and removes
+    /// `END` blocks.
+    struct Desugar;
+
+    impl Fold for Desugar
+    {
+        fn fold_call_expr(&mut self, call: CallExpr) -> Expr {
+            let mut call = walk_call_expr(self, call);
+
+            if method_name(&call) == Some("freeze") && call.callee.parts.len() > 1 {
+                call.callee.parts.pop();
+            }
+            call.into()
+        }
+
+        fn fold_stmt(&mut self, stmt: Stmt) -> Vec<Stmt> {
+            match stmt {
+                Stmt::Expr(Expr::Call(ref call)) if method_name(call) == Some("twice") => {
+                    let Argument::Positional(ref argument) = call.arguments[0];
+                    vec![Stmt::Expr(argument.clone()); 2]
+                },
+                stmt => vec![walk_stmt(self, stmt)],
+            }
+        }
+
+        fn fold_item(&mut self, item: Item) -> Vec<Item> {
+            match item {
+                Item::EndBlock(..) => Vec::new(),
+                item => walk_item(self, item),
+            }
+        }
+    }
+
+    #[test]
+    fn replaces_expressions() {
+        let program = Desugar.fold_program(parse("x = a.freeze + 1\n"));
+        assert_eq!(program, parse("x = a + 1\n"));
+    }
+
+    #[test]
+    fn splices_and_drops_statements_and_items() {
+        let program = Desugar.fold_program(parse("def foo\n  twice(bar)\nend\ntwice 1\nEND { baz }\n"));
+        assert_eq!(program, parse("def foo\n  bar\n  bar\nend\n1\n1\n"));
+    }
+}
@@ -1,85 +1,222 @@
 pub use self::expr::*;
 pub use self::stmt::*;
+pub use self::visit::Visitor;
+pub use self::fold::Fold;
+pub use self::visit_mut::VisitorMut;
+pub use self::print::Printer;
+pub use self::sexp::Sexp;
+pub use self::comment::{Comment, CommentPlacement, CommentTarget};
 
 pub mod expr;
 pub mod stmt;
+pub mod visit;
+pub mod fold;
+pub mod visit_mut;
+pub mod print;
+pub mod sexp;
+pub mod arena;
+pub mod comment;
+
+use symbol::Symbol;
+use span::Span;
 
 use std::iter::FromIterator;
 
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
 /// A Ruby program.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Program
 {
     pub items: Vec<Item>,
+    /// The text following the `__END__` marker, exposed to Ruby as `DATA`.
+    pub data: Option<String>,
+    /// The magic comments in the program.
+    pub pragmas: Vec<Pragma>,
+    /// Every comment in the program, in source order.
+    ///
+    /// Comments are attached to nodes by what the nodes contain, so changing
+    /// a node leaves its comments attached to nothing.
+    pub comments: Vec<Comment>,
+}
+
+/// A magic comment, which changes how a file is interpreted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Pragma
+{
+    /// `# frozen_string_literal: true`.
+    FrozenStringLiteral(bool),
+    /// `# encoding: utf-8`, or `# -*- coding: utf-8 -*-`.
+    Encoding(String),
+    /// `# typed: strict`, used by Sorbet.
+    Typed(String),
+    /// `# shareable_constant_value: literal`.
+    ShareableConstantValue(String),
 }
 
 /// An item.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Item
 {
     Module(Module),
     Class(Class),
     Function(Function),
     Stmt(Stmt),
+    BeginBlock(BeginBlock),
+    EndBlock(EndBlock),
 }
 
 /// A module.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Module
 {
     /// The name of the module.
-    pub name: String,
+    pub name: ConstantPath,
     /// The items contained in the module.
     pub items: Vec<Item>,
+    /// Where the module is in the source.
+    pub span: NodeSpan,
 }
 
 /// A class.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Class
 {
     /// The name of the class.
-    pub name: String,
+    pub name: ConstantPath,
     /// The items contained in the class.
     pub items: Vec<Item>,
     /// The parent class.
-    pub superclass: Option<Path>,
+    ///
+    /// This can be any expression, such as `Struct.new(:a, :b)`.
+    pub superclass: Option<Expr>,
+    /// Where the class is in the source.
+    pub span: NodeSpan,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Function
 {
     /// The name of the function.
-    pub name: String,
+    pub name: Symbol,
+    /// Whether the function is defined on `self`, as in `def self.foo`.
+    pub singleton: bool,
+    /// The effective visibility of the function.
+    pub visibility: Visibility,
+    /// The parameters of the function, in order.
+    pub parameters: Vec<Parameter>,
     /// The statements in the function.
     pub statements: Vec<Stmt>,
+    /// Where the function is in the source.
+    pub span: NodeSpan,
 }
 
-/// An identifier.
+/// The visibility of a function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Visibility
+{
+    Public,
+    Protected,
+    Private,
+    /// A private instance method with a public singleton copy.
+    ///
+    /// Created by `module_function`.
+    ModuleFunction,
+}
+
+/// A `BEGIN { ... }` block, run before the rest of the program.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Identifier(pub String);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BeginBlock
+{
+    pub statements: Vec<Stmt>,
+    /// Where the block is in the source.
+    pub span: NodeSpan,
+}
+
+/// An `END { ... }` block, run when the program exits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EndBlock
+{
+    pub statements: Vec<Stmt>,
+    /// Where the block is in the source.
+    pub span: NodeSpan,
+}
+
+/// Where a node is in the source, if it was parsed rather than built.
+///
+/// This never makes nodes unequal, so the same code compares equal wherever
+/// it is and however it was made.
+#[derive(Copy, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct NodeSpan(Option<Span>);
+
+/// An identifier.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Identifier(pub Symbol);
 
 /// A constant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Constant(pub Symbol);
+
+/// A list of constants separated by double colons.
+///
+/// `Foo::Bar`, `::TopLevel` or `obj.class::CONST`.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Constant(pub String);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConstantPath
+{
+    /// Where the first constant is looked up.
+    pub scope: ConstantScope,
+    /// The constants that make up the path, outermost first.
+    pub parts: Vec<Constant>,
+}
+
+/// Where the first constant of a path is looked up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConstantScope
+{
+    /// The enclosing classes and modules, `Foo`.
+    Lexical,
+    /// The top level, `::Foo`.
+    TopLevel,
+    /// The module an expression evaluates to, `obj.class::Foo`.
+    Expr(Box<Expr>),
+}
 
 /// A list of identifiers separated by periods.
 ///
 /// `my.object.do_thing`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Path
 {
     /// The parts that make up the path.
     pub parts: Vec<PathSegment>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PathSegment
 {
     pub kind: PathSegmentKind,
     pub separator: PathSeparator,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PathSegmentKind
 {
     Constant(Constant),
@@ -87,6 +224,7 @@ pub enum PathSegmentKind
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PathSeparator
 {
     /// The root part of a path.
@@ -94,58 +232,284 @@ pub enum PathSeparator
     Root,
     Dot,
     DoubleColon,
+    /// The `&.` operator.
+    SafeNavigation,
+}
+
+/// Something a value can be bound to.
+///
+/// This is shared by everything that binds names: assignments, `for` loops,
+/// `rescue => target` and block parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AssignTarget
+{
+    /// A local variable, `a`.
+    Local(Identifier),
+    /// An instance variable, `@a`, stored without its sigil.
+    InstanceVariable(Identifier),
+    /// A global variable, `$a`, stored without its sigil.
+    GlobalVariable(Identifier),
+    /// A class variable, `@@a`, stored without its sigil.
+    ClassVariable(Identifier),
+    /// A constant, `A` or `Foo::A`.
+    Constant(ConstantPath),
+    /// An attribute writer, `a.b`.
+    Attribute(AttributeTarget),
+    /// An element, `a[b]`.
+    Index(IndexExpr),
+    /// A splat collecting the remaining values, `*a`, or an anonymous `*`.
+    Splat(Option<Box<AssignTarget>>),
+    /// A nested group of targets, `(a, b)`.
+    Group(Vec<AssignTarget>),
+}
+
+/// An attribute writer target, `receiver.name = value`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AttributeTarget
+{
+    pub receiver: Box<Expr>,
+    /// The attribute name, without the trailing `=`.
+    pub name: Symbol,
 }
 
+/// A block passed to a method, `do |a| ... end` or `{ |a| ... }`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Block
+{
+    pub parameters: Vec<AssignTarget>,
+    pub statements: Vec<Stmt>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Argument
 {
     /// A standard positional argument.
     Positional(Expr),
 }
 
+/// A parameter of a function.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Parameter
 {
-    pub name: String,
+    pub name: Symbol,
+    /// The value used when no argument is given, as in `a = 1`.
     pub default: Option<Box<Expr>>,
 }
 
 impl Program
 {
     pub fn new() -> Self {
-        Program { items: Vec::new() }
+        Program { items: Vec::new(), data: None, pragmas: Vec::new(), comments: Vec::new() }
     }
 }
 
 impl Module
 {
-    pub fn new<S>(name: S) -> Self where S: Into<String> {
-        Module { name: name.into(), items: Vec::new() }
+    pub fn new<S>(name: S) -> Self where S: Into<Symbol> {
+        Module { name: ConstantPath::new(name), items: Vec::new(), span: NodeSpan::default() }
     }
 }
 
 impl Class
 {
-    pub fn new<S>(name: S) -> Self where S: Into<String> {
-        Class { name: name.into(), items: Vec::new(), superclass: None }
+    pub fn new<S>(name: S) -> Self where S: Into<Symbol> {
+        Class { name: ConstantPath::new(name), items: Vec::new(), superclass: None, span: NodeSpan::default() }
+    }
+}
+
+impl Item
+{
+    /// Gets where the item is in the source, if it was parsed.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Item::Module(ref module) => module.span.get(),
+            Item::Class(ref class) => class.span.get(),
+            Item::Function(ref function) => function.span.get(),
+            Item::Stmt(ref stmt) => stmt.span(),
+            Item::BeginBlock(ref block) => block.span.get(),
+            Item::EndBlock(ref block) => block.span.get(),
+        }
+    }
+
+    /// Records where the item is in the source.
+    pub fn set_span(&mut self, span: Span) {
+        match *self {
+            Item::Module(ref mut module) => module.span = NodeSpan::new(span),
+            Item::Class(ref mut class) => class.span = NodeSpan::new(span),
+            Item::Function(ref mut function) => function.span = NodeSpan::new(span),
+            Item::Stmt(ref mut stmt) => stmt.set_span(span),
+            Item::BeginBlock(ref mut block) => block.span = NodeSpan::new(span),
+            Item::EndBlock(ref mut block) => block.span = NodeSpan::new(span),
+        }
+    }
+
+    /// Gets the visibility a bare modifier such as `private` gives the
+    /// functions defined after it, if this is one.
+    pub fn default_visibility(&self) -> Option<Visibility> {
+        match *self {
+            Item::Stmt(Stmt::Expr(Expr::Call(ref call))) if call.arguments.is_empty() => {
+                match call.visibility_modifier() {
+                    Some((false, visibility)) => Some(visibility),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks if this is a modifier such as `private :foo` which names a
+    /// function.
+    fn names_function(&self, function: &Function) -> bool {
+        let call = match *self {
+            Item::Stmt(Stmt::Expr(Expr::Call(ref call))) => call,
+            _ => return false,
+        };
+
+        match call.visibility_modifier() {
+            Some((singleton, _)) if singleton == function.singleton => {
+                call.arguments.iter().any(|argument| match *argument {
+                    Argument::Positional(Expr::Symbol(ref symbol)) => symbol.name == function.name,
+                    Argument::Positional(Expr::StringLiteral(ref s)) => function.name == *s.value,
+                    _ => false,
+                })
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Function
+{
+    /// Checks if a function is always private, regardless of visibility
+    /// modifiers, as `initialize` is.
+    pub fn is_implicitly_private(name: &str) -> bool {
+        matches!(name, "initialize" | "initialize_copy" | "initialize_clone" |
+                 "initialize_dup" | "respond_to_missing?")
+    }
+
+    /// Gets the visibility the function has if nothing changes it, given the
+    /// visibility functions get by default where it is defined.
+    pub fn implied_visibility(&self, default_visibility: Visibility) -> Visibility {
+        if self.singleton {
+            Visibility::Public
+        } else if Function::is_implicitly_private(self.name.as_str()) {
+            Visibility::Private
+        } else {
+            default_visibility
+        }
+    }
+}
+
+/// Finds the modifier written before each function in a body, as in
+/// `private def foo`, given the visibility functions get by default at the
+/// start of the body.
+///
+/// Gives back an entry for each item, which is only set for functions whose
+/// visibility is not explained by the other modifiers in the body.
+pub fn inline_visibility_modifiers(items: &[Item], default_visibility: Visibility) -> Vec<Option<Visibility>> {
+    let mut default_visibility = default_visibility;
+
+    items.iter().enumerate().map(|(index, item)| {
+        if let Some(visibility) = item.default_visibility() {
+            default_visibility = visibility;
+        }
+
+        match *item {
+            Item::Function(ref function) => {
+                // A modifier naming the function later on sets its visibility.
+                let named = items[index + 1..].iter().any(|other| other.names_function(function));
+                Some(function.visibility)
+                    .filter(|&visibility| !named && visibility != function.implied_visibility(default_visibility))
+            },
+            _ => None,
+        }
+    }).collect()
+}
+
+impl Visibility
+{
+    /// Looks up the visibility set by a method such as `private`, along with
+    /// whether it applies to singleton functions, as `private_class_method`
+    /// does.
+    pub fn from_modifier(modifier: &str) -> Option<(bool, Visibility)> {
+        match modifier {
+            "public" => Some((false, Visibility::Public)),
+            "protected" => Some((false, Visibility::Protected)),
+            "private" => Some((false, Visibility::Private)),
+            "module_function" => Some((false, Visibility::ModuleFunction)),
+            "public_class_method" => Some((true, Visibility::Public)),
+            "private_class_method" => Some((true, Visibility::Private)),
+            _ => None,
+        }
+    }
+
+    /// Gets the method which gives instance functions this visibility.
+    pub fn modifier(self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Protected => "protected",
+            Visibility::Private => "private",
+            Visibility::ModuleFunction => "module_function",
+        }
+    }
+}
+
+impl NodeSpan
+{
+    pub fn new(span: Span) -> Self {
+        NodeSpan(Some(span))
+    }
+
+    /// Gets the span, unless the node was built rather than parsed.
+    pub fn get(self) -> Option<Span> {
+        self.0
+    }
+}
+
+impl PartialEq for NodeSpan
+{
+    fn eq(&self, _: &NodeSpan) -> bool { true }
+}
+
+impl Eq for NodeSpan { }
+
+impl ConstantPath
+{
+    /// Creates a path consisting of a single constant.
+    pub fn new<S>(name: S) -> Self where S: Into<Symbol> {
+        ConstantPath { scope: ConstantScope::Lexical, parts: vec![Constant(name.into())] }
     }
 }
 
 impl PathSegmentKind
 {
-    pub fn new(text: String) -> Self {
-        if text.chars().next().unwrap().is_uppercase() {
-            PathSegmentKind::Constant(Constant(text))
+    pub fn new(name: Symbol) -> Self {
+        if name.as_str().starts_with(char::is_uppercase) {
+            PathSegmentKind::Constant(Constant(name))
         } else {
-            PathSegmentKind::Identifier(Identifier(text))
+            PathSegmentKind::Identifier(Identifier(name))
+        }
+    }
+
+    /// Gets the name of the segment.
+    pub fn name(self) -> Symbol {
+        match self {
+            PathSegmentKind::Constant(Constant(name)) | PathSegmentKind::Identifier(Identifier(name)) => name,
         }
     }
 }
 
-impl Into<Item> for Class { fn into(self) -> Item { Item::Class(self) } }
-impl Into<Item> for Module { fn into(self) -> Item { Item::Module(self) } }
-impl Into<Item> for Function { fn into(self) -> Item { Item::Function(self) } }
-impl Into<Item> for Stmt { fn into(self) -> Item { Item::Stmt(self) } }
+impl From<Class> for Item { fn from(class: Class) -> Item { Item::Class(class) } }
+impl From<Module> for Item { fn from(module: Module) -> Item { Item::Module(module) } }
+impl From<Function> for Item { fn from(function: Function) -> Item { Item::Function(function) } }
+impl From<Stmt> for Item { fn from(stmt: Stmt) -> Item { Item::Stmt(stmt) } }
+impl From<BeginBlock> for Item { fn from(block: BeginBlock) -> Item { Item::BeginBlock(block) } }
+impl From<EndBlock> for Item { fn from(block: EndBlock) -> Item { Item::EndBlock(block) } }
 
 impl FromIterator<PathSegment> for Path
 {
@@ -153,3 +517,30 @@ impl FromIterator<PathSegment> for Path
         Path { parts: it.into_iter().collect() }
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod test
+{
+    use parse::Parser;
+    use span::Span;
+    use serde_json;
+
+    #[test]
+    fn round_trips_through_json() {
+        let program = Parser::from("class A < B\n  def c\n    d(1, :e => 'f') { |g| -g ** 2 }\n  end\nend\n").parse().unwrap();
+        let json = serde_json::to_string(&program).unwrap();
+
+        assert_eq!(serde_json::from_str::<super::Program>(&json).unwrap(), program);
+    }
+
+    #[test]
+    fn writes_where_nodes_are() {
+        let program = Parser::from("a = 1\n").parse().unwrap();
+        let json = serde_json::to_value(&program).unwrap();
+        let assignment = &json["items"][0]["Stmt"]["Expr"]["Assignment"];
+
+        assert_eq!(assignment["span"], serde_json::to_value(Span::new(0, 5)).unwrap());
+        assert_eq!(assignment["value"]["IntegerLiteral"]["span"], serde_json::to_value(Span::new(4, 5)).unwrap());
+        assert_eq!(serde_json::from_value::<super::Program>(json).unwrap().items[0].span(), program.items[0].span());
+    }
+}
@@ -0,0 +1,701 @@
+//! Printing the AST as Ruby source.
+//!
+//! Parentheses are added wherever precedence needs them, so that the printed
+//! code parses back into the same AST, other than for the `ParenExpr`s that
+//! parentheses parse into.
+//!
+//! The AST does not keep comments or layout, so those are lost. Error and
+//! missing expressions print as nothing. Strings are printed as they are, as
+//! the tokenizer has no escapes and so strings never contain quotes.
+
+use ast::*;
+use parse::token;
+
+use std::{fmt, slice};
+
+/// The text each level of nesting is indented by.
+const INDENT: &str = "  ";
+
+/// How tightly an expression binds, for deciding where it needs
+/// parentheses. Higher binds more tightly.
+type Binding = u8;
+
+/// Multiple assignments, which can only be statements.
+const STATEMENT: Binding = 0;
+/// Expressions which take everything to their right, such as assignments.
+const OPEN: Binding = 1;
+const TERNARY: Binding = 2;
+/// Negation binds more tightly than everything but `**`.
+const NEGATE: Binding = 21;
+/// Expressions which never need parentheses.
+const PRIMARY: Binding = Binding::MAX;
+
+/// Prints the AST as Ruby source.
+#[derive(Clone, Debug, Default)]
+pub struct Printer
+{
+    output: String,
+    /// The current level of nesting.
+    indent: usize,
+}
+
+impl Printer
+{
+    pub fn new() -> Self { Printer::default() }
+
+    pub fn print_program(mut self, program: &Program) -> String {
+        self.program(program);
+        self.output
+    }
+
+    pub fn print_item(mut self, item: &Item) -> String {
+        let modifiers = inline_visibility_modifiers(slice::from_ref(item), Visibility::Private);
+        self.item(item, modifiers[0]);
+        self.output
+    }
+
+    pub fn print_stmt(mut self, stmt: &Stmt) -> String {
+        self.stmt(stmt);
+        self.output
+    }
+
+    pub fn print_expr(mut self, expr: &Expr) -> String {
+        self.expr(expr);
+        self.output
+    }
+
+    fn program(&mut self, program: &Program) {
+        for pragma in program.pragmas.iter() {
+            self.write(&pragma_comment(pragma));
+            self.write("\n");
+        }
+
+        if !program.pragmas.is_empty() && !program.items.is_empty() {
+            self.write("\n");
+        }
+
+        // Top-level functions are private methods on `Object`.
+        let modifiers = inline_visibility_modifiers(&program.items, Visibility::Private);
+        for (index, item) in program.items.iter().enumerate() {
+            if index > 0 && (is_definition(item) || is_definition(&program.items[index - 1])) {
+                self.write("\n");
+            }
+
+            self.item(item, modifiers[index]);
+            self.write("\n");
+        }
+
+        if let Some(ref data) = program.data {
+            self.write("__END__\n");
+            self.write(data);
+        }
+    }
+
+    /// Prints the items of a class or module, followed by a new line at the
+    /// indentation of the `end`.
+    fn body(&mut self, items: &[Item]) {
+        let modifiers = inline_visibility_modifiers(items, Visibility::Public);
+
+        self.indent += 1;
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 && (is_definition(item) || is_definition(&items[index - 1])) {
+                self.write("\n");
+            }
+
+            self.newline();
+            self.item(item, modifiers[index]);
+        }
+        self.indent -= 1;
+
+        self.newline();
+    }
+
+    /// Prints an item, along with the visibility modifier written before it
+    /// if it is a function such as `private def foo`.
+    fn item(&mut self, item: &Item, modifier: Option<Visibility>) {
+        match *item {
+            Item::Module(ref module) => {
+                self.write("module ");
+                self.constant_path(&module.name);
+                self.body(&module.items);
+                self.write("end");
+            },
+            Item::Class(ref class) => {
+                self.write("class ");
+                self.constant_path(&class.name);
+                if let Some(ref superclass) = class.superclass {
+                    self.write(" < ");
+                    self.expr(superclass);
+                }
+                self.body(&class.items);
+                self.write("end");
+            },
+            Item::Function(ref function) => self.function(function, modifier),
+            Item::Stmt(ref stmt) => self.stmt(stmt),
+            Item::BeginBlock(ref block) => {
+                self.write("BEGIN {");
+                self.statements(&block.statements);
+                self.write("}");
+            },
+            Item::EndBlock(ref block) => {
+                self.write("END {");
+                self.statements(&block.statements);
+                self.write("}");
+            },
+        }
+    }
+
+    fn function(&mut self, function: &Function, modifier: Option<Visibility>) {
+        // `private def foo` changes the visibility of just one function.
+        if let Some(visibility) = modifier {
+            self.write(visibility.modifier());
+            self.write(" ");
+        }
+
+        self.write("def ");
+        if function.singleton {
+            self.write("self.");
+        }
+        self.write(function.name.as_str());
+        if !function.parameters.is_empty() {
+            self.write("(");
+            self.separated(&function.parameters, Self::parameter);
+            self.write(")");
+        }
+        self.statements(&function.statements);
+        self.write("end");
+    }
+
+    /// Prints statements on their own lines, indented, followed by a new line
+    /// at the indentation of whatever closes them.
+    fn statements(&mut self, statements: &[Stmt]) {
+        self.indent += 1;
+        for stmt in statements.iter() {
+            self.newline();
+            self.stmt(stmt);
+        }
+        self.indent -= 1;
+
+        self.newline();
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match *stmt {
+            Stmt::Expr(ref expr) => self.operand(expr, STATEMENT, true),
+            Stmt::Alias(ref alias) => {
+                let sigil = match alias.kind {
+                    AliasKind::Method => "",
+                    AliasKind::GlobalVariable => "$",
+                };
+
+                self.write(&format!("alias {}{} {}{}", sigil, alias.new_name, sigil, alias.old_name));
+            },
+            Stmt::Undef(ref undef) => {
+                self.write("undef ");
+                let names: Vec<_> = undef.names.iter().map(|name| name.as_str()).collect();
+                self.write(&names.join(", "));
+            },
+        }
+    }
+
+    /// Prints an expression which is delimited from whatever follows it, as
+    /// arguments are by commas.
+    fn expr(&mut self, expr: &Expr) {
+        self.operand(expr, OPEN, true);
+    }
+
+    /// Prints an expression, in parentheses unless it binds at least as
+    /// tightly as `min_binding`.
+    ///
+    /// `rightmost` is whether nothing binding more tightly than the
+    /// enclosing expression follows, so that an assignment or negation can
+    /// take everything to its right without changing the meaning.
+    fn operand(&mut self, expr: &Expr, min_binding: Binding, rightmost: bool) {
+        let takes_rest = matches!(*expr, Expr::Assignment(..) | Expr::ConstantAssignment(..) | Expr::Negate(..));
+        let parenthesize = binding(expr) < min_binding && !(rightmost && takes_rest);
+
+        if parenthesize {
+            self.write("(");
+            self.expr_kind(expr, true);
+            self.write(")");
+        } else {
+            self.expr_kind(expr, rightmost);
+        }
+    }
+
+    fn expr_kind(&mut self, expr: &Expr, rightmost: bool) {
+        match *expr {
+            Expr::Assignment(ref assignment) => {
+                self.assign_target(&assignment.assignee);
+                self.write(" = ");
+                self.operand(&assignment.value, OPEN, rightmost);
+            },
+            Expr::ConstantAssignment(ref assignment) => {
+                self.constant_path(&assignment.constant);
+                self.write(" = ");
+                self.operand(&assignment.value, OPEN, rightmost);
+            },
+            Expr::Constant(ref constant) => self.constant_path(&constant.path),
+            Expr::Paren(ref paren) => {
+                self.write("(");
+                self.expr(&paren.inner);
+                self.write(")");
+            },
+            Expr::Call(ref call) => {
+                self.path(&call.callee);
+                if !call.arguments.is_empty() {
+                    self.arguments(&call.arguments);
+                }
+                if let Some(ref block) = call.block {
+                    self.block(block);
+                }
+            },
+            Expr::StringLiteral(ref literal) => self.write(&format!("'{}'", literal.value)),
+            Expr::IntegerLiteral(ref literal) => self.write(&literal.value.to_string()),
+            Expr::Symbol(ref symbol) => self.write(&format!(":{}", symbol.name)),
+            Expr::KeyValue(ref key_value) => {
+                if is_label(key_value.key.as_str()) {
+                    self.write(&format!("{}: ", key_value.key));
+                } else {
+                    self.write(&format!(":{} => ", key_value.key));
+                }
+                self.operand(&key_value.value, OPEN, rightmost);
+            },
+            Expr::Negate(ref negate) => {
+                self.write("-");
+                self.operand(&negate.inner, binary_binding(BinaryOp::Power), rightmost);
+            },
+            Expr::Defined(ref defined) => {
+                self.write("defined?(");
+                self.expr(&defined.inner);
+                self.write(")");
+            },
+            Expr::Super(ref super_expr) => {
+                self.write("super");
+                self.arguments(&super_expr.arguments);
+            },
+            Expr::ZSuper(..) => self.write("super"),
+            Expr::Yield(ref yield_expr) => {
+                self.write("yield");
+                if !yield_expr.arguments.is_empty() {
+                    self.arguments(&yield_expr.arguments);
+                }
+            },
+            Expr::Return(ref return_expr) => {
+                self.write("return");
+                for (index, argument) in return_expr.arguments.iter().enumerate() {
+                    self.write(if index == 0 { " " } else { ", " });
+                    self.argument(argument);
+                }
+            },
+            Expr::Binary(ref binary) => {
+                let binding = binary_binding(binary.operator);
+                let (lhs_binding, rhs_binding) = if binary.operator.is_right_associative() {
+                    (binding + 1, binding)
+                } else {
+                    (binding, binding + 1)
+                };
+
+                self.operand(&binary.lhs, lhs_binding, false);
+                self.write(&format!(" {} ", binary.operator.symbol()));
+                self.operand(&binary.rhs, rhs_binding, rightmost);
+            },
+            Expr::CharacterLiteral(ref literal) => {
+                self.write(&format!("?{}", token::escape_character(literal.value)));
+            },
+            Expr::Ternary(ref ternary) => {
+                self.operand(&ternary.condition, binary_binding(BinaryOp::LogicalOr), false);
+                self.write(" ? ");
+                self.expr(&ternary.if_true);
+                self.write(" : ");
+                self.operand(&ternary.if_false, OPEN, rightmost);
+            },
+            Expr::MultipleAssignment(ref assignment) => {
+                self.assign_targets(&assignment.targets);
+                // `a, = b` assigns the first value.
+                if assignment.targets.len() == 1 {
+                    self.write(",");
+                }
+                self.write(" = ");
+                self.separated(&assignment.values, Self::expr);
+            },
+            Expr::Index(ref index) => self.index(index),
+            Expr::For(ref for_expr) => {
+                self.write("for ");
+                self.assign_targets(&for_expr.targets);
+                self.write(" in ");
+                self.expr(&for_expr.iterable);
+                self.statements(&for_expr.statements);
+                self.write("end");
+            },
+            Expr::Begin(ref begin) => {
+                self.write("begin");
+                self.statements(&begin.statements);
+
+                for clause in begin.rescue_clauses.iter() {
+                    self.write("rescue");
+                    if !clause.exceptions.is_empty() {
+                        self.write(" ");
+                        self.separated(&clause.exceptions, Self::expr);
+                    }
+                    if let Some(ref target) = clause.target {
+                        self.write(" => ");
+                        self.assign_target(target);
+                    }
+                    self.statements(&clause.statements);
+                }
+
+                if let Some(ref statements) = begin.else_statements {
+                    self.write("else");
+                    self.statements(statements);
+                }
+
+                if let Some(ref statements) = begin.ensure_statements {
+                    self.write("ensure");
+                    self.statements(statements);
+                }
+
+                self.write("end");
+            },
+            Expr::Error(..) | Expr::Missing(..) => (),
+        }
+    }
+
+    /// Prints a block, on one line in braces if it is short enough.
+    fn block(&mut self, block: &Block) {
+        let single_line = match *block.statements {
+            [] => Some(String::new()),
+            [ref stmt] => {
+                let mut printer = Printer { output: String::new(), indent: self.indent };
+                printer.stmt(stmt);
+                Some(printer.output).filter(|text| !text.contains('\n'))
+            },
+            _ => None,
+        };
+
+        match single_line {
+            Some(ref text) if text.is_empty() && block.parameters.is_empty() => self.write(" {}"),
+            Some(text) => {
+                self.write(" {");
+                self.block_parameters(&block.parameters);
+                self.write(&format!(" {} }}", text));
+            },
+            None => {
+                self.write(" do");
+                self.block_parameters(&block.parameters);
+                self.statements(&block.statements);
+                self.write("end");
+            },
+        }
+    }
+
+    fn block_parameters(&mut self, parameters: &[AssignTarget]) {
+        if !parameters.is_empty() {
+            self.write(" |");
+            self.assign_targets(parameters);
+            self.write("|");
+        }
+    }
+
+    fn index(&mut self, index: &IndexExpr) {
+        self.operand(&index.receiver, PRIMARY, false);
+        self.write("[");
+        self.separated(&index.arguments, Self::argument);
+        self.write("]");
+    }
+
+    fn arguments(&mut self, arguments: &[Argument]) {
+        self.write("(");
+        self.separated(arguments, Self::argument);
+        self.write(")");
+    }
+
+    fn argument(&mut self, argument: &Argument) {
+        match *argument {
+            Argument::Positional(ref expr) => self.expr(expr),
+        }
+    }
+
+    fn parameter(&mut self, parameter: &Parameter) {
+        self.write(parameter.name.as_str());
+        if let Some(ref default) = parameter.default {
+            self.write(" = ");
+            self.expr(default);
+        }
+    }
+
+    fn assign_targets(&mut self, targets: &[AssignTarget]) {
+        self.separated(targets, Self::assign_target);
+    }
+
+    fn assign_target(&mut self, target: &AssignTarget) {
+        match *target {
+            AssignTarget::Local(ref name) => self.write(name.0.as_str()),
+            AssignTarget::InstanceVariable(ref name) => self.write(&format!("@{}", name.0)),
+            AssignTarget::GlobalVariable(ref name) => self.write(&format!("${}", name.0)),
+            AssignTarget::ClassVariable(ref name) => self.write(&format!("@@{}", name.0)),
+            AssignTarget::Constant(ref path) => self.constant_path(path),
+            AssignTarget::Attribute(ref attribute) => {
+                self.operand(&attribute.receiver, PRIMARY, false);
+                self.write(&format!(".{}", attribute.name));
+            },
+            AssignTarget::Index(ref index) => self.index(index),
+            AssignTarget::Splat(ref target) => {
+                self.write("*");
+                if let Some(ref target) = *target {
+                    self.assign_target(target);
+                }
+            },
+            AssignTarget::Group(ref targets) => {
+                self.write("(");
+                self.assign_targets(targets);
+                self.write(")");
+            },
+        }
+    }
+
+    fn constant_path(&mut self, path: &ConstantPath) {
+        match path.scope {
+            ConstantScope::Lexical => (),
+            ConstantScope::TopLevel => self.write("::"),
+            ConstantScope::Expr(ref scope) => {
+                self.operand(scope, PRIMARY, false);
+                self.write("::");
+            },
+        }
+
+        let names: Vec<_> = path.parts.iter().map(|constant| constant.0.as_str()).collect();
+        self.write(&names.join("::"));
+    }
+
+    fn path(&mut self, path: &Path) {
+        for segment in path.parts.iter() {
+            self.write(match segment.separator {
+                PathSeparator::Root => "",
+                PathSeparator::Dot => ".",
+                PathSeparator::DoubleColon => "::",
+                PathSeparator::SafeNavigation => "&.",
+            });
+
+            self.write(segment.kind.name().as_str());
+        }
+    }
+
+    /// Prints a list separated by commas.
+    fn separated<T, F>(&mut self, values: &[T], mut print: F)
+        where F: FnMut(&mut Self, &T) {
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+            print(self, value);
+        }
+    }
+
+    /// Starts a new line at the current indentation.
+    fn newline(&mut self) {
+        self.output.push('\n');
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+}
+
+/// Gets how tightly an expression binds.
+fn binding(expr: &Expr) -> Binding {
+    match *expr {
+        Expr::MultipleAssignment(..) => STATEMENT,
+        Expr::Assignment(..) | Expr::ConstantAssignment(..) | Expr::KeyValue(..) => OPEN,
+        // `return a, b` takes everything up to the end of the statement.
+        Expr::Return(ref return_expr) if !return_expr.arguments.is_empty() => OPEN,
+        Expr::Ternary(..) => TERNARY,
+        Expr::Binary(ref binary) => binary_binding(binary.operator),
+        Expr::Negate(..) => NEGATE,
+        _ => PRIMARY,
+    }
+}
+
+/// Gets how tightly a binary operator binds, leaving room between each
+/// precedence level for negation.
+fn binary_binding(operator: BinaryOp) -> Binding {
+    2 + operator.precedence() * 2
+}
+
+/// Checks if an item is a definition, which is separated from the items
+/// around it by a blank line.
+fn is_definition(item: &Item) -> bool {
+    matches!(*item, Item::Module(..) | Item::Class(..) | Item::Function(..))
+}
+
+/// Checks if a hash key can be written as a label, `key: value`.
+fn is_label(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_alphabetic() || c == '_') &&
+        key.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Gets the magic comment for a pragma.
+fn pragma_comment(pragma: &Pragma) -> String {
+    match *pragma {
+        Pragma::FrozenStringLiteral(value) => format!("# frozen_string_literal: {}", value),
+        Pragma::Encoding(ref encoding) => format!("# encoding: {}", encoding),
+        Pragma::Typed(ref level) => format!("# typed: {}", level),
+        Pragma::ShareableConstantValue(ref value) => format!("# shareable_constant_value: {}", value),
+    }
+}
+
+impl fmt::Display for Program
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&Printer::new().print_program(self))
+    }
+}
+
+impl fmt::Display for Item
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&Printer::new().print_item(self))
+    }
+}
+
+impl fmt::Display for Stmt
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&Printer::new().print_stmt(self))
+    }
+}
+
+impl fmt::Display for Expr
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&Printer::new().print_expr(self))
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::{self, Parser};
+
+    fn parse(s: &str) -> Program {
+        Parser::from(s).parse().expect("failed to parse")
+    }
+
+    fn parse_expression(s: &str) -> Expr {
+        parse::parse_expression(s).expect("failed to parse")
+    }
+
+    /// Removes the parentheses which the printer adds.
+    struct StripParens;
+
+    impl Fold for StripParens
+    {
+        fn fold_paren_expr(&mut self, paren: ParenExpr) -> Expr {
+            self.fold_expr(*paren.inner)
+        }
+    }
+
+    /// Generates arbitrary expressions from a seed.
+    struct Generator(u64);
+
+    impl Generator
+    {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+
+        fn expr(&mut self, depth: u32) -> Expr {
+            let choice = if depth == 0 { self.next(2) } else { self.next(7) };
+            let mut operand = || Box::new(self.expr(depth - 1));
+
+            match choice {
+                0 => {
+                    let name = ["a", "b", "c"][self.next(3) as usize];
+                    CallExpr {
+                        callee: Path { parts: vec![PathSegment {
+                            kind: PathSegmentKind::Identifier(Identifier(Symbol::intern(name))),
+                            separator: PathSeparator::Root,
+                        }] },
+                        arguments: Vec::new(),
+                        block: None,
+                        span: Default::default(),
+                    }.into()
+                },
+                1 => {
+                    // Control characters, backslashes and spaces must be escaped.
+                    let value = ['a', '?', '\'', '\n', '\t', '\\', ' ', '\0', '\u{1b}', '\u{e9}'][self.next(10) as usize];
+                    CharacterLiteral { value, span: Default::default() }.into()
+                },
+                2 | 3 => {
+                    let operator = BinaryOp::ALL[self.next(BinaryOp::ALL.len() as u64) as usize];
+                    BinaryExpr { operator, lhs: self.expr(depth - 1).into(), rhs: self.expr(depth - 1).into(), span: Default::default() }.into()
+                },
+                4 => NegateExpr { inner: operand(), span: Default::default() }.into(),
+                5 => TernaryExpr { condition: operand(), if_true: self.expr(depth - 1).into(), if_false: self.expr(depth - 1).into(), span: Default::default() }.into(),
+                _ => AssignmentExpr {
+                    assignee: AssignTarget::Local(Identifier(Symbol::intern("x"))),
+                    value: operand(),
+                    span: Default::default(),
+                }.into(),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_programs() {
+        let sources = [
+            "# frozen_string_literal: true\nrequire 'set'\n",
+            "module Foo::Bar\n  class Baz < ::Base\n    attr_reader(:a, :b)\n  end\nend\n",
+            "def foo\n  private\nend\nclass A\n  def initialize\n  end\n  private\n  def bar\n  end\n  public def baz\n  end\n  def self.qux\n  end\nend\n",
+            "protected def foo\nend\n",
+            "a, b = 1, 2\n*c, (d, e) = f\ng, = h\n@x = $y = @@z = X::Y = 1\na.b = c[1, 2] = 3\n",
+            "x = foo.bar&.baz(1, key: 2, :empty? => 3)\nFoo::Bar.new { |a, b| a + b }\n",
+            "each do |x|\n  puts(x)\n  puts(?x)\nend\nfoo {}\n",
+            "begin\n  a\nrescue Foo, Bar => e\n  b\nelse\n  c\nensure\n  d\nend\n",
+            "for a, b in c\n  yield(a, b)\nend\n",
+            "def foo\n  super\n  super(1)\n  return 1, 2\nend\n",
+            "def foo(a, b = a + 1, c = bar(:x) ? 1 : 2)\n  a\nend\ndef self.baz(a = -1)\nend\n",
+            "alias foo bar\nalias $a $b\nundef foo, bar\nBEGIN { a }\nEND { b }\n",
+            "x = defined?(y) ? 'it is' : -z ** 2\nobj.class::Foo = 1\n",
+            "x = ?\\n\ny = ?\\t\nz = ?\\\\\nputs ?\\s, ?a\n",
+            "puts 'x'\n__END__\ndata\n",
+        ];
+
+        for source in sources.iter() {
+            let program = parse(source);
+            let printed = program.to_string();
+            assert_eq!(parse(&printed), program, "printed {:?} as {:?}", source, printed);
+        }
+    }
+
+    #[test]
+    fn parenthesizes_by_precedence() {
+        let print = |s: &str| StripParens.fold_expr(parse_expression(s)).to_string();
+
+        assert_eq!(print("(a + b) * c"), "(a + b) * c");
+        assert_eq!(print("a + (b * c)"), "a + b * c");
+        assert_eq!(print("a - (b - c)"), "a - (b - c)");
+        assert_eq!(print("(a ** b) ** c"), "(a ** b) ** c");
+        assert_eq!(print("(-a) ** b"), "(-a) ** b");
+        assert_eq!(print("-(a * b)"), "-(a * b)");
+        assert_eq!(print("a + (b = c)"), "a + b = c");
+        assert_eq!(print("(a = b) + c"), "(a = b) + c");
+        assert_eq!(print("(a ? b : c) ? d : (e ? f : g)"), "(a ? b : c) ? d : e ? f : g");
+    }
+
+    #[test]
+    fn round_trips_generated_expressions() {
+        let mut generator = Generator(0x5eed);
+
+        for _ in 0..2000 {
+            let expr = generator.expr(4);
+            let printed = expr.to_string();
+            assert_eq!(StripParens.fold_expr(parse_expression(&printed)), expr, "printed as {:?}", printed);
+        }
+    }
+}
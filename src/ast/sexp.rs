@@ -0,0 +1,642 @@
+//! Writing the AST as S-expressions, in the dialect of the `parser` gem.
+//!
+//! Tools such as RuboCop and unparser work on the `(send nil :foo (int 1))`
+//! trees printed by `ruby-parse`. The output here matches `ruby-parse` with
+//! its default, modern settings, so `a[1]` is `(index ...)`, keyword
+//! arguments are `(kwargs ...)` and a lone block parameter is
+//! `(procarg0 ...)`.
+//!
+//! Where the AST keeps less than the gem, the output differs:
+//!
+//! * Strings are written as they appear in the source, as the tokenizer has
+//!   no escapes.
+//! * Hash keys are always symbols.
+//! * Code which could not be parsed is written as `(error)`.
+
+use ast::*;
+use symbol::Symbol;
+
+use std::{fmt, slice};
+
+/// The text each level of nesting is indented by.
+const INDENT: &str = "  ";
+
+/// An S-expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sexp
+{
+    /// A node, such as `(send ...)`, with its type and children.
+    Node(&'static str, Vec<Sexp>),
+    Nil,
+    Symbol(String),
+    String(String),
+    Integer(i64),
+}
+
+/// Builds S-expressions from the AST, keeping track of local variables.
+///
+/// Like Ruby, a bare name is a local variable from where it is first
+/// assigned, and a call anywhere else.
+#[derive(Clone, Debug)]
+struct Builder
+{
+    /// The local variables in each enclosing scope, along with whether the
+    /// scope can see the variables of the one around it, as blocks can.
+    scopes: Vec<(bool, Vec<Symbol>)>,
+}
+
+impl Sexp
+{
+    pub fn from_program(program: &Program) -> Self {
+        Builder::new().items(&program.items, Visibility::Private)
+    }
+
+    pub fn from_item(item: &Item) -> Self {
+        Builder::new().items(slice::from_ref(item), Visibility::Private)
+    }
+
+    pub fn from_stmt(stmt: &Stmt) -> Self {
+        Builder::new().stmt(stmt)
+    }
+
+    pub fn from_expr(expr: &Expr) -> Self {
+        Builder::new().expr(expr)
+    }
+
+    fn node(kind: &'static str, children: Vec<Sexp>) -> Self {
+        Sexp::Node(kind, children)
+    }
+
+    fn symbol<S>(name: S) -> Self where S: Into<String> {
+        Sexp::Symbol(name.into())
+    }
+
+    /// Writes the S-expression as `ruby-parse` does, with every child node
+    /// on a line of its own.
+    fn write(&self, output: &mut String, indent: usize) {
+        match *self {
+            Sexp::Node(kind, ref children) => {
+                output.push('(');
+                // Types such as `__FILE__` are printed dasherized.
+                output.push_str(&kind.replace('_', "-"));
+
+                for child in children.iter() {
+                    if let Sexp::Node(..) = *child {
+                        output.push('\n');
+                        for _ in 0..indent + 1 {
+                            output.push_str(INDENT);
+                        }
+                    } else {
+                        output.push(' ');
+                    }
+                    child.write(output, indent + 1);
+                }
+
+                output.push(')');
+            },
+            Sexp::Nil => output.push_str("nil"),
+            Sexp::Symbol(ref name) => {
+                output.push(':');
+                output.push_str(name);
+            },
+            Sexp::String(ref value) => output.push_str(&inspect_string(value)),
+            Sexp::Integer(value) => output.push_str(&value.to_string()),
+        }
+    }
+}
+
+impl Builder
+{
+    fn new() -> Self {
+        Builder { scopes: vec![(false, Vec::new())] }
+    }
+
+    /// Builds the items of a program, class or module body.
+    fn items(&mut self, items: &[Item], default_visibility: Visibility) -> Sexp {
+        let modifiers = inline_visibility_modifiers(items, default_visibility);
+        let children = items.iter().zip(modifiers).map(|(item, modifier)| self.item(item, modifier)).collect();
+
+        body(children)
+    }
+
+    fn item(&mut self, item: &Item, modifier: Option<Visibility>) -> Sexp {
+        match *item {
+            Item::Module(ref module) => {
+                let name = self.constant_path(&module.name);
+                let body = self.scope(false, |builder| builder.items(&module.items, Visibility::Public));
+
+                Sexp::node("module", vec![name, body])
+            },
+            Item::Class(ref class) => {
+                let name = self.constant_path(&class.name);
+                let superclass = class.superclass.as_ref().map_or(Sexp::Nil, |superclass| self.expr(superclass));
+                let body = self.scope(false, |builder| builder.items(&class.items, Visibility::Public));
+
+                Sexp::node("class", vec![name, superclass, body])
+            },
+            Item::Function(ref function) => {
+                let definition = self.function(function);
+
+                // `private def foo` passes the definition to `private`.
+                match modifier {
+                    Some(visibility) => Sexp::node("send", vec![
+                        Sexp::Nil,
+                        Sexp::symbol(visibility.modifier()),
+                        definition,
+                    ]),
+                    None => definition,
+                }
+            },
+            Item::Stmt(ref stmt) => self.stmt(stmt),
+            Item::BeginBlock(ref block) => Sexp::node("preexe", vec![self.statements(&block.statements)]),
+            Item::EndBlock(ref block) => Sexp::node("postexe", vec![self.statements(&block.statements)]),
+        }
+    }
+
+    fn function(&mut self, function: &Function) -> Sexp {
+        let (parameters, statements) = self.scope(false, |builder| {
+            let parameters = function.parameters.iter().map(|parameter| builder.parameter(parameter)).collect();
+            (Sexp::node("args", parameters), builder.statements(&function.statements))
+        });
+        let name = Sexp::symbol(function.name.as_str());
+
+        if function.singleton {
+            Sexp::node("defs", vec![Sexp::node("self", Vec::new()), name, parameters, statements])
+        } else {
+            Sexp::node("def", vec![name, parameters, statements])
+        }
+    }
+
+    /// Builds a function parameter, which is a local variable from where it
+    /// is named, so even its own default can use it.
+    fn parameter(&mut self, parameter: &Parameter) -> Sexp {
+        self.scopes.last_mut().unwrap().1.push(parameter.name);
+
+        let name = Sexp::symbol(parameter.name.as_str());
+        match parameter.default {
+            Some(ref default) => Sexp::node("optarg", vec![name, self.expr(default)]),
+            None => Sexp::node("arg", vec![name]),
+        }
+    }
+
+    /// Builds a list of statements, wrapped in `(begin ...)` if there is more
+    /// than one.
+    fn statements(&mut self, statements: &[Stmt]) -> Sexp {
+        body(statements.iter().map(|stmt| self.stmt(stmt)).collect())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Sexp {
+        match *stmt {
+            Stmt::Expr(ref expr) => self.expr(expr),
+            Stmt::Alias(ref alias) => {
+                let name = |name: &str| match alias.kind {
+                    AliasKind::Method => Sexp::node("sym", vec![Sexp::symbol(name)]),
+                    AliasKind::GlobalVariable => Sexp::node("gvar", vec![Sexp::symbol(format!("${}", name))]),
+                };
+
+                Sexp::node("alias", vec![name(alias.new_name.as_str()), name(alias.old_name.as_str())])
+            },
+            Stmt::Undef(ref undef) => {
+                Sexp::node("undef", undef.names.iter().map(|name| Sexp::node("sym", vec![Sexp::symbol(name.as_str())])).collect())
+            },
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Sexp {
+        match *expr {
+            Expr::Assignment(ref assignment) => {
+                // The variable exists from the `=`, so `a = a` sets `a` to nil.
+                self.declare(&assignment.assignee);
+                let value = self.expr(&assignment.value);
+                self.assign_target(&assignment.assignee, Some(value))
+            },
+            Expr::ConstantAssignment(ref assignment) => {
+                let value = self.expr(&assignment.value);
+                self.constant_assignment(&assignment.constant, Some(value))
+            },
+            Expr::Constant(ref constant) => self.constant_path(&constant.path),
+            Expr::Paren(ref paren) => Sexp::node("begin", vec![self.expr(&paren.inner)]),
+            Expr::Call(ref call) => self.call(call),
+            Expr::StringLiteral(ref literal) => Sexp::node("str", vec![Sexp::String(literal.value.clone())]),
+            Expr::IntegerLiteral(ref literal) => Sexp::node("int", vec![Sexp::Integer(literal.value)]),
+            Expr::Symbol(ref symbol) => Sexp::node("sym", vec![Sexp::symbol(symbol.name.as_str())]),
+            Expr::KeyValue(ref key_value) => self.pair(key_value),
+            Expr::Negate(ref negate) => match *negate.inner {
+                // The minus is part of a negative number.
+                Expr::IntegerLiteral(ref literal) => Sexp::node("int", vec![Sexp::Integer(-literal.value)]),
+                ref inner => Sexp::node("send", vec![self.expr(inner), Sexp::symbol("-@")]),
+            },
+            Expr::Defined(ref defined) => Sexp::node("defined?", vec![self.expr(&defined.inner)]),
+            Expr::Super(ref super_expr) => Sexp::node("super", self.arguments(&super_expr.arguments, "kwargs")),
+            Expr::ZSuper(..) => Sexp::node("zsuper", Vec::new()),
+            Expr::Yield(ref yield_expr) => Sexp::node("yield", self.arguments(&yield_expr.arguments, "kwargs")),
+            Expr::Return(ref return_expr) => Sexp::node("return", self.arguments(&return_expr.arguments, "hash")),
+            Expr::Binary(ref binary) => {
+                let lhs = self.expr(&binary.lhs);
+                let rhs = self.expr(&binary.rhs);
+
+                match binary.operator {
+                    BinaryOp::LogicalAnd => Sexp::node("and", vec![lhs, rhs]),
+                    BinaryOp::LogicalOr => Sexp::node("or", vec![lhs, rhs]),
+                    operator => Sexp::node("send", vec![lhs, Sexp::symbol(operator.symbol()), rhs]),
+                }
+            },
+            Expr::CharacterLiteral(ref literal) => Sexp::node("str", vec![Sexp::String(literal.value.to_string())]),
+            Expr::Ternary(ref ternary) => Sexp::node("if", vec![
+                self.expr(&ternary.condition),
+                self.expr(&ternary.if_true),
+                self.expr(&ternary.if_false),
+            ]),
+            Expr::MultipleAssignment(ref assignment) => {
+                for target in assignment.targets.iter() {
+                    self.declare(target);
+                }
+
+                let targets = self.assign_targets(&assignment.targets);
+                let values = match *assignment.values {
+                    [ref value] => self.expr(value),
+                    ref values => Sexp::node("array", values.iter().map(|value| self.expr(value)).collect()),
+                };
+
+                Sexp::node("masgn", vec![targets, values])
+            },
+            Expr::Index(ref index) => {
+                let mut children = vec![self.expr(&index.receiver)];
+                children.extend(self.arguments(&index.arguments, "hash"));
+                Sexp::node("index", children)
+            },
+            Expr::For(ref for_expr) => {
+                for target in for_expr.targets.iter() {
+                    self.declare(target);
+                }
+
+                let targets = match *for_expr.targets {
+                    [ref target] => self.assign_target(target, None),
+                    ref targets => self.assign_targets(targets),
+                };
+                let iterable = self.expr(&for_expr.iterable);
+                let statements = self.statements(&for_expr.statements);
+
+                Sexp::node("for", vec![targets, iterable, statements])
+            },
+            Expr::Begin(ref begin) => self.begin(begin),
+            Expr::Error(..) | Expr::Missing(..) => Sexp::node("error", Vec::new()),
+        }
+    }
+
+    fn begin(&mut self, begin: &BeginExpr) -> Sexp {
+        if begin.rescue_clauses.is_empty() && begin.else_statements.is_none() && begin.ensure_statements.is_none() {
+            return Sexp::node("kwbegin", begin.statements.iter().map(|stmt| self.stmt(stmt)).collect());
+        }
+
+        let mut inner = self.statements(&begin.statements);
+
+        if !begin.rescue_clauses.is_empty() || begin.else_statements.is_some() {
+            let mut children = vec![inner];
+
+            for clause in begin.rescue_clauses.iter() {
+                let exceptions = match clause.exceptions.is_empty() {
+                    true => Sexp::Nil,
+                    false => Sexp::node("array", clause.exceptions.iter().map(|exception| self.expr(exception)).collect()),
+                };
+                let target = match clause.target {
+                    Some(ref target) => {
+                        self.declare(target);
+                        self.assign_target(target, None)
+                    },
+                    None => Sexp::Nil,
+                };
+                let statements = self.statements(&clause.statements);
+
+                children.push(Sexp::node("resbody", vec![exceptions, target, statements]));
+            }
+
+            children.push(match begin.else_statements {
+                Some(ref statements) => self.statements(statements),
+                None => Sexp::Nil,
+            });
+
+            inner = Sexp::node("rescue", children);
+        }
+
+        if let Some(ref statements) = begin.ensure_statements {
+            inner = Sexp::node("ensure", vec![inner, self.statements(statements)]);
+        }
+
+        Sexp::node("kwbegin", vec![inner])
+    }
+
+    fn call(&mut self, call: &CallExpr) -> Sexp {
+        let mut receiver = Sexp::Nil;
+        let last = call.callee.parts.len() - 1;
+
+        for (index, segment) in call.callee.parts.iter().enumerate() {
+            let name = segment.kind.name();
+            let has_arguments = index == last && (!call.arguments.is_empty() || call.block.is_some());
+            let is_constant = matches!(segment.kind, PathSegmentKind::Constant(..)) && !has_arguments &&
+                matches!(segment.separator, PathSeparator::Root | PathSeparator::DoubleColon);
+            let is_bare = matches!(segment.kind, PathSegmentKind::Identifier(..)) && !has_arguments &&
+                segment.separator == PathSeparator::Root;
+
+            receiver = if is_constant {
+                Sexp::node("const", vec![receiver, Sexp::symbol(name.as_str())])
+            } else if is_bare && self.is_local(name) {
+                Sexp::node("lvar", vec![Sexp::symbol(name.as_str())])
+            } else if let (true, Some(keyword)) = (is_bare, keyword(name.as_str())) {
+                Sexp::node(keyword, Vec::new())
+            } else {
+                let kind = if segment.separator == PathSeparator::SafeNavigation { "csend" } else { "send" };
+                let mut children = vec![receiver, Sexp::symbol(name.as_str())];
+                if index == last {
+                    children.extend(self.arguments(&call.arguments, "kwargs"));
+                }
+
+                Sexp::node(kind, children)
+            };
+        }
+
+        match call.block {
+            Some(ref block) => {
+                self.scope(true, |builder| {
+                    for parameter in block.parameters.iter() {
+                        builder.declare(parameter);
+                    }
+
+                    let parameters = match *block.parameters {
+                        // A lone parameter is spread over an array passed to the block.
+                        [AssignTarget::Local(..)] | [AssignTarget::Group(..)] => {
+                            vec![block_parameter(&block.parameters[0], "procarg0")]
+                        },
+                        ref parameters => parameters.iter().map(|parameter| block_parameter(parameter, "mlhs")).collect(),
+                    };
+                    let statements = builder.statements(&block.statements);
+
+                    Sexp::node("block", vec![receiver, Sexp::node("args", parameters), statements])
+                })
+            },
+            None => receiver,
+        }
+    }
+
+    /// Builds a list of arguments, with the key-value pairs at the end
+    /// collected into a hash of the given kind.
+    fn arguments(&mut self, arguments: &[Argument], hash_kind: &'static str) -> Vec<Sexp> {
+        let pairs = arguments.iter().rev()
+            .take_while(|argument| matches!(**argument, Argument::Positional(Expr::KeyValue(..))))
+            .count();
+        let (positional, pairs) = arguments.split_at(arguments.len() - pairs);
+
+        let mut children: Vec<_> = positional.iter().map(|argument| match *argument {
+            Argument::Positional(Expr::KeyValue(ref key_value)) => Sexp::node("hash", vec![self.pair(key_value)]),
+            Argument::Positional(ref expr) => self.expr(expr),
+        }).collect();
+
+        if !pairs.is_empty() {
+            children.push(Sexp::node(hash_kind, pairs.iter().map(|argument| match *argument {
+                Argument::Positional(ref expr) => self.expr(expr),
+            }).collect()));
+        }
+
+        children
+    }
+
+    fn pair(&mut self, key_value: &KeyValueExpr) -> Sexp {
+        let key = Sexp::node("sym", vec![Sexp::symbol(key_value.key.as_str())]);
+        Sexp::node("pair", vec![key, self.expr(&key_value.value)])
+    }
+
+    fn assign_targets(&mut self, targets: &[AssignTarget]) -> Sexp {
+        Sexp::node("mlhs", targets.iter().map(|target| self.assign_target(target, None)).collect())
+    }
+
+    /// Builds an assignment to a target, which has no value when it is part
+    /// of a multiple assignment.
+    fn assign_target(&mut self, target: &AssignTarget, value: Option<Sexp>) -> Sexp {
+        match *target {
+            AssignTarget::Local(ref name) => variable("lvasgn", name.0.as_str().to_owned(), value),
+            AssignTarget::InstanceVariable(ref name) => variable("ivasgn", format!("@{}", name.0), value),
+            AssignTarget::GlobalVariable(ref name) => variable("gvasgn", format!("${}", name.0), value),
+            AssignTarget::ClassVariable(ref name) => variable("cvasgn", format!("@@{}", name.0), value),
+            AssignTarget::Constant(ref path) => self.constant_assignment(path, value),
+            AssignTarget::Attribute(ref attribute) => {
+                let mut children = vec![self.expr(&attribute.receiver), Sexp::symbol(format!("{}=", attribute.name))];
+                children.extend(value);
+                Sexp::node("send", children)
+            },
+            AssignTarget::Index(ref index) => {
+                let mut children = vec![self.expr(&index.receiver)];
+                children.extend(self.arguments(&index.arguments, "hash"));
+                children.extend(value);
+                Sexp::node("indexasgn", children)
+            },
+            AssignTarget::Splat(ref target) => {
+                Sexp::node("splat", target.iter().map(|target| self.assign_target(target, None)).collect())
+            },
+            AssignTarget::Group(ref targets) => self.assign_targets(targets),
+        }
+    }
+
+    fn constant_assignment(&mut self, path: &ConstantPath, value: Option<Sexp>) -> Sexp {
+        let (name, scope) = path.parts.split_last().expect("constant paths are never empty");
+        let scope = self.constant_scope(&path.scope, scope);
+
+        let mut children = vec![scope, Sexp::symbol(name.0.as_str())];
+        children.extend(value);
+        Sexp::node("casgn", children)
+    }
+
+    fn constant_path(&mut self, path: &ConstantPath) -> Sexp {
+        self.constant_scope(&path.scope, &path.parts)
+    }
+
+    /// Builds the lookup of a list of constants, or the scope they are looked
+    /// up in if there are none.
+    fn constant_scope(&mut self, scope: &ConstantScope, parts: &[Constant]) -> Sexp {
+        let mut sexp = match *scope {
+            ConstantScope::Lexical => Sexp::Nil,
+            ConstantScope::TopLevel => Sexp::node("cbase", Vec::new()),
+            ConstantScope::Expr(ref scope) => self.expr(scope),
+        };
+
+        for part in parts.iter() {
+            sexp = Sexp::node("const", vec![sexp, Sexp::symbol(part.0.as_str())]);
+        }
+
+        sexp
+    }
+
+    /// Adds the local variables a target assigns to the current scope.
+    fn declare(&mut self, target: &AssignTarget) {
+        match *target {
+            AssignTarget::Local(name) if !self.is_local(name.0) => {
+                self.scopes.last_mut().unwrap().1.push(name.0);
+            },
+            AssignTarget::Splat(Some(ref target)) => self.declare(target),
+            AssignTarget::Group(ref targets) => {
+                for target in targets.iter() {
+                    self.declare(target);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Checks if a name refers to a local variable at this point.
+    fn is_local(&self, name: Symbol) -> bool {
+        for &(inherits, ref locals) in self.scopes.iter().rev() {
+            if locals.contains(&name) {
+                return true;
+            }
+            if !inherits {
+                break;
+            }
+        }
+
+        false
+    }
+
+    /// Builds something in a new scope, which can see the local variables
+    /// around it if it `inherits`.
+    fn scope<T, F>(&mut self, inherits: bool, build: F) -> T
+        where F: FnOnce(&mut Self) -> T {
+        self.scopes.push((inherits, Vec::new()));
+        let built = build(self);
+        self.scopes.pop();
+
+        built
+    }
+}
+
+/// Builds a block parameter, using `kind` for a nested group.
+fn block_parameter(parameter: &AssignTarget, kind: &'static str) -> Sexp {
+    match *parameter {
+        AssignTarget::Local(..) if kind == "procarg0" => {
+            Sexp::node("procarg0", vec![block_parameter(parameter, "mlhs")])
+        },
+        AssignTarget::Local(ref name) => Sexp::node("arg", vec![Sexp::symbol(name.0.as_str())]),
+        AssignTarget::Splat(ref target) => Sexp::node("restarg", match target.as_deref() {
+            Some(AssignTarget::Local(ref name)) => vec![Sexp::symbol(name.0.as_str())],
+            _ => Vec::new(),
+        }),
+        AssignTarget::Group(ref targets) => {
+            Sexp::node(kind, targets.iter().map(|target| block_parameter(target, "mlhs")).collect())
+        },
+        _ => Sexp::node("error", Vec::new()),
+    }
+}
+
+/// Builds an assignment to a variable.
+fn variable(kind: &'static str, name: String, value: Option<Sexp>) -> Sexp {
+    let mut children = vec![Sexp::Symbol(name)];
+    children.extend(value);
+    Sexp::node(kind, children)
+}
+
+/// Wraps a body in `(begin ...)` if it has more than one child.
+fn body(mut children: Vec<Sexp>) -> Sexp {
+    match children.len() {
+        0 => Sexp::Nil,
+        1 => children.pop().unwrap(),
+        _ => Sexp::node("begin", children),
+    }
+}
+
+/// Gets the node for a keyword which is parsed as a name, such as `self`.
+fn keyword(name: &str) -> Option<&'static str> {
+    match name {
+        "self" => Some("self"),
+        "nil" => Some("nil"),
+        "true" => Some("true"),
+        "false" => Some("false"),
+        "__FILE__" => Some("__FILE__"),
+        "__LINE__" => Some("__LINE__"),
+        "__ENCODING__" => Some("__ENCODING__"),
+        _ => None,
+    }
+}
+
+/// Quotes a string as Ruby's `String#inspect` does.
+fn inspect_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\x0c' => quoted.push_str("\\f"),
+            '\x0b' => quoted.push_str("\\v"),
+            '\x08' => quoted.push_str("\\b"),
+            '\x07' => quoted.push_str("\\a"),
+            '\x1b' => quoted.push_str("\\e"),
+            // `#` is escaped where it would start interpolation.
+            '#' if matches!(chars.peek(), Some('{') | Some('$') | Some('@')) => quoted.push_str("\\#"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+impl fmt::Display for Sexp
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut output = String::new();
+        self.write(&mut output, 0);
+        fmt.write_str(&output)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::{self, Parser};
+
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn matches_ruby_parse() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sexp");
+        let mut sources: Vec<_> = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "rb"))
+            .collect();
+        sources.sort();
+        assert!(!sources.is_empty(), "no sources in {}", directory.display());
+
+        let mut mismatches = Vec::new();
+        for source in sources.iter() {
+            let program = Parser::from(&fs::read_to_string(source).unwrap()[..]).parse().expect("failed to parse");
+            let expected = fs::read_to_string(source.with_extension("sexp")).unwrap();
+            let actual = format!("{}\n", Sexp::from_program(&program));
+
+            if actual != expected {
+                mismatches.push(format!("{}:\n{}", source.display(), ::format::unified_diff(&expected, &actual, "ruby-parse", "rubic")));
+            }
+        }
+
+        assert!(mismatches.is_empty(), "{} of {} files differ from ruby-parse\n{}",
+                mismatches.len(), sources.len(), mismatches.join("\n"));
+    }
+
+    #[test]
+    fn writes_single_expressions() {
+        let expr = parse::parse_expression("foo(1, 'a\\b')").unwrap();
+        assert_eq!(Sexp::from_expr(&expr).to_string(), "(send nil :foo\n  (int 1)\n  (str \"a\\\\b\"))");
+        assert_eq!(Sexp::from_program(&Program::new()).to_string(), "nil");
+    }
+
+    #[test]
+    fn writes_function_parameters_as_locals() {
+        let program = Parser::from("def foo(a, b = a)\n  b\nend\n").parse().unwrap();
+        assert_eq!(Sexp::from_program(&program).to_string(),
+                   "(def :foo\n  (args\n    (arg :a)\n    (optarg :b\n      (lvar :a)))\n  (lvar :b))");
+    }
+}
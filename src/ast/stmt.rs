@@ -1,8 +1,73 @@
 use ast;
+use symbol::Symbol;
+use span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 
 /// A statement.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Stmt
 {
     Expr(ast::Expr),
+    Alias(Alias),
+    Undef(Undef),
+}
+
+/// An `alias new old` statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Alias
+{
+    pub kind: AliasKind,
+    /// The name being introduced, without any sigil.
+    pub new_name: Symbol,
+    /// The name being aliased, without any sigil.
+    pub old_name: Symbol,
+    /// Where the statement is in the source.
+    pub span: ast::NodeSpan,
+}
+
+/// The kind of name an `alias` statement refers to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AliasKind
+{
+    /// `alias new old`.
+    Method,
+    /// `alias $new $old`.
+    GlobalVariable,
+}
+
+/// An `undef a, b` statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Undef
+{
+    /// The names of the undefined methods.
+    pub names: Vec<Symbol>,
+    /// Where the statement is in the source.
+    pub span: ast::NodeSpan,
+}
+
+impl Stmt
+{
+    /// Gets where the statement is in the source, if it was parsed.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Stmt::Expr(ref expr) => expr.span(),
+            Stmt::Alias(ref alias) => alias.span.get(),
+            Stmt::Undef(ref undef) => undef.span.get(),
+        }
+    }
+
+    /// Records where the statement is in the source.
+    pub fn set_span(&mut self, span: Span) {
+        match *self {
+            Stmt::Expr(ref mut expr) => expr.set_span(span),
+            Stmt::Alias(ref mut alias) => alias.span = ast::NodeSpan::new(span),
+            Stmt::Undef(ref mut undef) => undef.span = ast::NodeSpan::new(span),
+        }
+    }
 }
@@ -0,0 +1,478 @@
+//! Walking over the AST.
+//!
+//! Implement `Visitor` and override the methods for the nodes of interest.
+//! An overriding method should call the matching `walk_*` function if it
+//! wants the children of the node to be visited too.
+//!
+//! ```
+//! use rubic::ast::{self, visit, Visitor};
+//!
+//! /// Counts the method calls in a program.
+//! struct CallCounter(usize);
+//!
+//! impl<'ast> Visitor<'ast> for CallCounter {
+//!     fn visit_call_expr(&mut self, call: &'ast ast::CallExpr) {
+//!         self.0 += 1;
+//!         visit::walk_call_expr(self, call);
+//!     }
+//! }
+//! ```
+
+use ast::*;
+
+/// Something which visits each node of the AST in turn.
+///
+/// Every method walks the children of its node by default.
+pub trait Visitor<'ast>
+{
+    fn visit_program(&mut self, program: &'ast Program) { walk_program(self, program) }
+    fn visit_item(&mut self, item: &'ast Item) { walk_item(self, item) }
+    fn visit_module(&mut self, module: &'ast Module) { walk_module(self, module) }
+    fn visit_class(&mut self, class: &'ast Class) { walk_class(self, class) }
+    fn visit_function(&mut self, function: &'ast Function) { walk_function(self, function) }
+    fn visit_begin_block(&mut self, block: &'ast BeginBlock) { walk_begin_block(self, block) }
+    fn visit_end_block(&mut self, block: &'ast EndBlock) { walk_end_block(self, block) }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) { walk_stmt(self, stmt) }
+    fn visit_alias(&mut self, alias: &'ast Alias) { walk_alias(self, alias) }
+    fn visit_undef(&mut self, undef: &'ast Undef) { walk_undef(self, undef) }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) { walk_expr(self, expr) }
+    fn visit_assignment_expr(&mut self, expr: &'ast AssignmentExpr) { walk_assignment_expr(self, expr) }
+    fn visit_constant_assignment_expr(&mut self, expr: &'ast ConstantAssignmentExpr) { walk_constant_assignment_expr(self, expr) }
+    fn visit_constant_expr(&mut self, expr: &'ast ConstantExpr) { walk_constant_expr(self, expr) }
+    fn visit_paren_expr(&mut self, expr: &'ast ParenExpr) { walk_paren_expr(self, expr) }
+    fn visit_call_expr(&mut self, expr: &'ast CallExpr) { walk_call_expr(self, expr) }
+    fn visit_string_literal(&mut self, literal: &'ast StringLiteral) { walk_string_literal(self, literal) }
+    fn visit_integer_literal(&mut self, literal: &'ast IntegerLiteral) { walk_integer_literal(self, literal) }
+    fn visit_symbol_expr(&mut self, expr: &'ast SymbolExpr) { walk_symbol_expr(self, expr) }
+    fn visit_key_value_expr(&mut self, expr: &'ast KeyValueExpr) { walk_key_value_expr(self, expr) }
+    fn visit_negate_expr(&mut self, expr: &'ast NegateExpr) { walk_negate_expr(self, expr) }
+    fn visit_defined_expr(&mut self, expr: &'ast DefinedExpr) { walk_defined_expr(self, expr) }
+    fn visit_super_expr(&mut self, expr: &'ast SuperExpr) { walk_super_expr(self, expr) }
+    fn visit_zsuper_expr(&mut self, expr: &'ast ZSuperExpr) { walk_zsuper_expr(self, expr) }
+    fn visit_yield_expr(&mut self, expr: &'ast YieldExpr) { walk_yield_expr(self, expr) }
+    fn visit_return_expr(&mut self, expr: &'ast ReturnExpr) { walk_return_expr(self, expr) }
+    fn visit_binary_expr(&mut self, expr: &'ast BinaryExpr) { walk_binary_expr(self, expr) }
+    fn visit_character_literal(&mut self, literal: &'ast CharacterLiteral) { walk_character_literal(self, literal) }
+    fn visit_ternary_expr(&mut self, expr: &'ast TernaryExpr) { walk_ternary_expr(self, expr) }
+    fn visit_multiple_assignment_expr(&mut self, expr: &'ast MultipleAssignmentExpr) { walk_multiple_assignment_expr(self, expr) }
+    fn visit_index_expr(&mut self, expr: &'ast IndexExpr) { walk_index_expr(self, expr) }
+    fn visit_for_expr(&mut self, expr: &'ast ForExpr) { walk_for_expr(self, expr) }
+    fn visit_begin_expr(&mut self, expr: &'ast BeginExpr) { walk_begin_expr(self, expr) }
+    fn visit_rescue_clause(&mut self, clause: &'ast RescueClause) { walk_rescue_clause(self, clause) }
+    fn visit_error_expr(&mut self, expr: &'ast ErrorExpr) { walk_error_expr(self, expr) }
+    fn visit_missing_expr(&mut self, expr: &'ast MissingExpr) { walk_missing_expr(self, expr) }
+
+    fn visit_block(&mut self, block: &'ast Block) { walk_block(self, block) }
+    fn visit_assign_target(&mut self, target: &'ast AssignTarget) { walk_assign_target(self, target) }
+    fn visit_attribute_target(&mut self, target: &'ast AttributeTarget) { walk_attribute_target(self, target) }
+    fn visit_constant_path(&mut self, path: &'ast ConstantPath) { walk_constant_path(self, path) }
+    fn visit_path(&mut self, path: &'ast Path) { walk_path(self, path) }
+    fn visit_path_segment(&mut self, segment: &'ast PathSegment) { walk_path_segment(self, segment) }
+    fn visit_argument(&mut self, argument: &'ast Argument) { walk_argument(self, argument) }
+    fn visit_parameter(&mut self, parameter: &'ast Parameter) { walk_parameter(self, parameter) }
+}
+
+pub fn walk_program<'ast, V>(visitor: &mut V, program: &'ast Program)
+    where V: Visitor<'ast> + ?Sized {
+    walk_items(visitor, &program.items);
+}
+
+pub fn walk_item<'ast, V>(visitor: &mut V, item: &'ast Item)
+    where V: Visitor<'ast> + ?Sized {
+    match *item {
+        Item::Module(ref module) => visitor.visit_module(module),
+        Item::Class(ref class) => visitor.visit_class(class),
+        Item::Function(ref function) => visitor.visit_function(function),
+        Item::Stmt(ref stmt) => visitor.visit_stmt(stmt),
+        Item::BeginBlock(ref block) => visitor.visit_begin_block(block),
+        Item::EndBlock(ref block) => visitor.visit_end_block(block),
+    }
+}
+
+pub fn walk_module<'ast, V>(visitor: &mut V, module: &'ast Module)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_constant_path(&module.name);
+    walk_items(visitor, &module.items);
+}
+
+pub fn walk_class<'ast, V>(visitor: &mut V, class: &'ast Class)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_constant_path(&class.name);
+    if let Some(ref superclass) = class.superclass {
+        visitor.visit_expr(superclass);
+    }
+    walk_items(visitor, &class.items);
+}
+
+pub fn walk_function<'ast, V>(visitor: &mut V, function: &'ast Function)
+    where V: Visitor<'ast> + ?Sized {
+    for parameter in function.parameters.iter() {
+        visitor.visit_parameter(parameter);
+    }
+    walk_stmts(visitor, &function.statements);
+}
+
+pub fn walk_begin_block<'ast, V>(visitor: &mut V, block: &'ast BeginBlock)
+    where V: Visitor<'ast> + ?Sized {
+    walk_stmts(visitor, &block.statements);
+}
+
+pub fn walk_end_block<'ast, V>(visitor: &mut V, block: &'ast EndBlock)
+    where V: Visitor<'ast> + ?Sized {
+    walk_stmts(visitor, &block.statements);
+}
+
+pub fn walk_stmt<'ast, V>(visitor: &mut V, stmt: &'ast Stmt)
+    where V: Visitor<'ast> + ?Sized {
+    match *stmt {
+        Stmt::Expr(ref expr) => visitor.visit_expr(expr),
+        Stmt::Alias(ref alias) => visitor.visit_alias(alias),
+        Stmt::Undef(ref undef) => visitor.visit_undef(undef),
+    }
+}
+
+pub fn walk_alias<'ast, V>(_visitor: &mut V, _alias: &'ast Alias)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_undef<'ast, V>(_visitor: &mut V, _undef: &'ast Undef)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_expr<'ast, V>(visitor: &mut V, expr: &'ast Expr)
+    where V: Visitor<'ast> + ?Sized {
+    match *expr {
+        Expr::Assignment(ref expr) => visitor.visit_assignment_expr(expr),
+        Expr::ConstantAssignment(ref expr) => visitor.visit_constant_assignment_expr(expr),
+        Expr::Constant(ref expr) => visitor.visit_constant_expr(expr),
+        Expr::Paren(ref expr) => visitor.visit_paren_expr(expr),
+        Expr::Call(ref expr) => visitor.visit_call_expr(expr),
+        Expr::StringLiteral(ref literal) => visitor.visit_string_literal(literal),
+        Expr::IntegerLiteral(ref literal) => visitor.visit_integer_literal(literal),
+        Expr::Symbol(ref expr) => visitor.visit_symbol_expr(expr),
+        Expr::KeyValue(ref expr) => visitor.visit_key_value_expr(expr),
+        Expr::Negate(ref expr) => visitor.visit_negate_expr(expr),
+        Expr::Defined(ref expr) => visitor.visit_defined_expr(expr),
+        Expr::Super(ref expr) => visitor.visit_super_expr(expr),
+        Expr::ZSuper(ref expr) => visitor.visit_zsuper_expr(expr),
+        Expr::Yield(ref expr) => visitor.visit_yield_expr(expr),
+        Expr::Return(ref expr) => visitor.visit_return_expr(expr),
+        Expr::Binary(ref expr) => visitor.visit_binary_expr(expr),
+        Expr::CharacterLiteral(ref literal) => visitor.visit_character_literal(literal),
+        Expr::Ternary(ref expr) => visitor.visit_ternary_expr(expr),
+        Expr::MultipleAssignment(ref expr) => visitor.visit_multiple_assignment_expr(expr),
+        Expr::Index(ref expr) => visitor.visit_index_expr(expr),
+        Expr::For(ref expr) => visitor.visit_for_expr(expr),
+        Expr::Begin(ref expr) => visitor.visit_begin_expr(expr),
+        Expr::Error(ref expr) => visitor.visit_error_expr(expr),
+        Expr::Missing(ref expr) => visitor.visit_missing_expr(expr),
+    }
+}
+
+pub fn walk_assignment_expr<'ast, V>(visitor: &mut V, expr: &'ast AssignmentExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_assign_target(&expr.assignee);
+    visitor.visit_expr(&expr.value);
+}
+
+pub fn walk_constant_assignment_expr<'ast, V>(visitor: &mut V, expr: &'ast ConstantAssignmentExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_constant_path(&expr.constant);
+    visitor.visit_expr(&expr.value);
+}
+
+pub fn walk_constant_expr<'ast, V>(visitor: &mut V, expr: &'ast ConstantExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_constant_path(&expr.path);
+}
+
+pub fn walk_paren_expr<'ast, V>(visitor: &mut V, expr: &'ast ParenExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.inner);
+}
+
+pub fn walk_call_expr<'ast, V>(visitor: &mut V, expr: &'ast CallExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_path(&expr.callee);
+    walk_arguments(visitor, &expr.arguments);
+    if let Some(ref block) = expr.block {
+        visitor.visit_block(block);
+    }
+}
+
+pub fn walk_string_literal<'ast, V>(_visitor: &mut V, _literal: &'ast StringLiteral)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_integer_literal<'ast, V>(_visitor: &mut V, _literal: &'ast IntegerLiteral)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_symbol_expr<'ast, V>(_visitor: &mut V, _expr: &'ast SymbolExpr)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_key_value_expr<'ast, V>(visitor: &mut V, expr: &'ast KeyValueExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.value);
+}
+
+pub fn walk_negate_expr<'ast, V>(visitor: &mut V, expr: &'ast NegateExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.inner);
+}
+
+pub fn walk_defined_expr<'ast, V>(visitor: &mut V, expr: &'ast DefinedExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.inner);
+}
+
+pub fn walk_super_expr<'ast, V>(visitor: &mut V, expr: &'ast SuperExpr)
+    where V: Visitor<'ast> + ?Sized {
+    walk_arguments(visitor, &expr.arguments);
+}
+
+pub fn walk_zsuper_expr<'ast, V>(_visitor: &mut V, _expr: &'ast ZSuperExpr)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_yield_expr<'ast, V>(visitor: &mut V, expr: &'ast YieldExpr)
+    where V: Visitor<'ast> + ?Sized {
+    walk_arguments(visitor, &expr.arguments);
+}
+
+pub fn walk_return_expr<'ast, V>(visitor: &mut V, expr: &'ast ReturnExpr)
+    where V: Visitor<'ast> + ?Sized {
+    walk_arguments(visitor, &expr.arguments);
+}
+
+pub fn walk_binary_expr<'ast, V>(visitor: &mut V, expr: &'ast BinaryExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.lhs);
+    visitor.visit_expr(&expr.rhs);
+}
+
+pub fn walk_character_literal<'ast, V>(_visitor: &mut V, _literal: &'ast CharacterLiteral)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_ternary_expr<'ast, V>(visitor: &mut V, expr: &'ast TernaryExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.condition);
+    visitor.visit_expr(&expr.if_true);
+    visitor.visit_expr(&expr.if_false);
+}
+
+pub fn walk_multiple_assignment_expr<'ast, V>(visitor: &mut V, expr: &'ast MultipleAssignmentExpr)
+    where V: Visitor<'ast> + ?Sized {
+    for target in expr.targets.iter() {
+        visitor.visit_assign_target(target);
+    }
+    for value in expr.values.iter() {
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_index_expr<'ast, V>(visitor: &mut V, expr: &'ast IndexExpr)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&expr.receiver);
+    walk_arguments(visitor, &expr.arguments);
+}
+
+pub fn walk_for_expr<'ast, V>(visitor: &mut V, expr: &'ast ForExpr)
+    where V: Visitor<'ast> + ?Sized {
+    for target in expr.targets.iter() {
+        visitor.visit_assign_target(target);
+    }
+    visitor.visit_expr(&expr.iterable);
+    walk_stmts(visitor, &expr.statements);
+}
+
+pub fn walk_begin_expr<'ast, V>(visitor: &mut V, expr: &'ast BeginExpr)
+    where V: Visitor<'ast> + ?Sized {
+    walk_stmts(visitor, &expr.statements);
+    for clause in expr.rescue_clauses.iter() {
+        visitor.visit_rescue_clause(clause);
+    }
+    if let Some(ref statements) = expr.else_statements {
+        walk_stmts(visitor, statements);
+    }
+    if let Some(ref statements) = expr.ensure_statements {
+        walk_stmts(visitor, statements);
+    }
+}
+
+pub fn walk_rescue_clause<'ast, V>(visitor: &mut V, clause: &'ast RescueClause)
+    where V: Visitor<'ast> + ?Sized {
+    for exception in clause.exceptions.iter() {
+        visitor.visit_expr(exception);
+    }
+    if let Some(ref target) = clause.target {
+        visitor.visit_assign_target(target);
+    }
+    walk_stmts(visitor, &clause.statements);
+}
+
+pub fn walk_error_expr<'ast, V>(_visitor: &mut V, _expr: &'ast ErrorExpr)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_missing_expr<'ast, V>(_visitor: &mut V, _expr: &'ast MissingExpr)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_block<'ast, V>(visitor: &mut V, block: &'ast Block)
+    where V: Visitor<'ast> + ?Sized {
+    for parameter in block.parameters.iter() {
+        visitor.visit_assign_target(parameter);
+    }
+    walk_stmts(visitor, &block.statements);
+}
+
+pub fn walk_assign_target<'ast, V>(visitor: &mut V, target: &'ast AssignTarget)
+    where V: Visitor<'ast> + ?Sized {
+    match *target {
+        AssignTarget::Local(..) | AssignTarget::InstanceVariable(..) |
+            AssignTarget::GlobalVariable(..) | AssignTarget::ClassVariable(..) => (),
+        AssignTarget::Constant(ref path) => visitor.visit_constant_path(path),
+        AssignTarget::Attribute(ref target) => visitor.visit_attribute_target(target),
+        AssignTarget::Index(ref expr) => visitor.visit_index_expr(expr),
+        AssignTarget::Splat(ref target) => if let Some(ref target) = *target {
+            visitor.visit_assign_target(target);
+        },
+        AssignTarget::Group(ref targets) => for target in targets.iter() {
+            visitor.visit_assign_target(target);
+        },
+    }
+}
+
+pub fn walk_attribute_target<'ast, V>(visitor: &mut V, target: &'ast AttributeTarget)
+    where V: Visitor<'ast> + ?Sized {
+    visitor.visit_expr(&target.receiver);
+}
+
+pub fn walk_constant_path<'ast, V>(visitor: &mut V, path: &'ast ConstantPath)
+    where V: Visitor<'ast> + ?Sized {
+    if let ConstantScope::Expr(ref scope) = path.scope {
+        visitor.visit_expr(scope);
+    }
+}
+
+pub fn walk_path<'ast, V>(visitor: &mut V, path: &'ast Path)
+    where V: Visitor<'ast> + ?Sized {
+    for segment in path.parts.iter() {
+        visitor.visit_path_segment(segment);
+    }
+}
+
+pub fn walk_path_segment<'ast, V>(_visitor: &mut V, _segment: &'ast PathSegment)
+    where V: Visitor<'ast> + ?Sized {
+}
+
+pub fn walk_argument<'ast, V>(visitor: &mut V, argument: &'ast Argument)
+    where V: Visitor<'ast> + ?Sized {
+    match *argument {
+        Argument::Positional(ref expr) => visitor.visit_expr(expr),
+    }
+}
+
+pub fn walk_parameter<'ast, V>(visitor: &mut V, parameter: &'ast Parameter)
+    where V: Visitor<'ast> + ?Sized {
+    if let Some(ref default) = parameter.default {
+        visitor.visit_expr(default);
+    }
+}
+
+fn walk_items<'ast, V>(visitor: &mut V, items: &'ast [Item])
+    where V: Visitor<'ast> + ?Sized {
+    for item in items.iter() {
+        visitor.visit_item(item);
+    }
+}
+
+fn walk_stmts<'ast, V>(visitor: &mut V, statements: &'ast [Stmt])
+    where V: Visitor<'ast> + ?Sized {
+    for stmt in statements.iter() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+fn walk_arguments<'ast, V>(visitor: &mut V, arguments: &'ast [Argument])
+    where V: Visitor<'ast> + ?Sized {
+    for argument in arguments.iter() {
+        visitor.visit_argument(argument);
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::Parser;
+
+    /// Collects the names of every call, in the order they are visited.
+    #[derive(Default)]
+    struct CallNames<'ast>(Vec<&'ast str>);
+
+    impl<'ast> Visitor<'ast> for CallNames<'ast>
+    {
+        fn visit_path_segment(&mut self, segment: &'ast PathSegment) {
+            if let PathSegmentKind::Identifier(Identifier(name)) = segment.kind {
+                self.0.push(name.as_str());
+            }
+        }
+    }
+
+    #[test]
+    fn visits_every_nested_node() {
+        let program = Parser::from("class Foo < bar\n  def baz\n    a[b] = c ? d(e) : -f\n    begin\n      g { |h| i }\n    rescue j => @k\n    end\n  end\nend\n").parse().unwrap();
+
+        let mut names = CallNames::default();
+        names.visit_program(&program);
+
+        assert_eq!(names.0, ["bar", "a", "b", "c", "d", "e", "f", "g", "i", "j"]);
+    }
+
+    #[test]
+    fn overriding_methods_can_skip_children() {
+        struct SkipBlocks<'ast>(CallNames<'ast>);
+
+        impl<'ast> Visitor<'ast> for SkipBlocks<'ast>
+        {
+            fn visit_block(&mut self, _: &'ast Block) {}
+            fn visit_path_segment(&mut self, segment: &'ast PathSegment) { self.0.visit_path_segment(segment) }
+        }
+
+        let program = Parser::from("a(b) { c }\n").parse().unwrap();
+        let mut visitor = SkipBlocks(CallNames::default());
+        visitor.visit_program(&program);
+
+        assert_eq!((visitor.0).0, ["a", "b"]);
+    }
+
+    #[test]
+    fn visits_function_parameters() {
+        /// Counts parameters, as well as collecting calls.
+        #[derive(Default)]
+        struct Parameters<'ast>(usize, CallNames<'ast>);
+
+        impl<'ast> Visitor<'ast> for Parameters<'ast>
+        {
+            fn visit_parameter(&mut self, parameter: &'ast Parameter) {
+                self.0 += 1;
+                walk_parameter(self, parameter);
+            }
+
+            fn visit_path_segment(&mut self, segment: &'ast PathSegment) { self.1.visit_path_segment(segment) }
+        }
+
+        let program = Parser::from("def f(a = g); end\n").parse().unwrap();
+        let mut visitor = Parameters::default();
+        visitor.visit_program(&program);
+
+        assert_eq!(visitor.0, 1);
+        assert_eq!((visitor.1).0, ["g"]);
+    }
+}
@@ -0,0 +1,443 @@
+//! Walking over the AST, changing it in place.
+//!
+//! This mirrors `visit`, but each method is given a mutable reference so
+//! that nodes can be rewritten or replaced.
+//!
+//! ```
+//! use rubic::ast::{self, visit_mut, VisitorMut};
+//!
+//! /// Replaces every integer with zero.
+//! struct Zero;
+//!
+//! impl VisitorMut for Zero {
+//!     fn visit_expr(&mut self, expr: &mut ast::Expr) {
+//!         if let ast::Expr::IntegerLiteral(ref mut literal) = *expr {
+//!             literal.value = 0;
+//!         }
+//!         visit_mut::walk_expr(self, expr);
+//!     }
+//! }
+//! ```
+
+use ast::*;
+
+/// Something which visits each node of the AST in turn, and may change
+/// them as it goes.
+///
+/// Every method walks the children of its node by default.
+pub trait VisitorMut
+{
+    fn visit_program(&mut self, program: &mut Program) { walk_program(self, program) }
+    fn visit_item(&mut self, item: &mut Item) { walk_item(self, item) }
+    fn visit_module(&mut self, module: &mut Module) { walk_module(self, module) }
+    fn visit_class(&mut self, class: &mut Class) { walk_class(self, class) }
+    fn visit_function(&mut self, function: &mut Function) { walk_function(self, function) }
+    fn visit_begin_block(&mut self, block: &mut BeginBlock) { walk_begin_block(self, block) }
+    fn visit_end_block(&mut self, block: &mut EndBlock) { walk_end_block(self, block) }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) { walk_stmt(self, stmt) }
+    fn visit_alias(&mut self, alias: &mut Alias) { walk_alias(self, alias) }
+    fn visit_undef(&mut self, undef: &mut Undef) { walk_undef(self, undef) }
+
+    fn visit_expr(&mut self, expr: &mut Expr) { walk_expr(self, expr) }
+    fn visit_assignment_expr(&mut self, expr: &mut AssignmentExpr) { walk_assignment_expr(self, expr) }
+    fn visit_constant_assignment_expr(&mut self, expr: &mut ConstantAssignmentExpr) { walk_constant_assignment_expr(self, expr) }
+    fn visit_constant_expr(&mut self, expr: &mut ConstantExpr) { walk_constant_expr(self, expr) }
+    fn visit_paren_expr(&mut self, expr: &mut ParenExpr) { walk_paren_expr(self, expr) }
+    fn visit_call_expr(&mut self, expr: &mut CallExpr) { walk_call_expr(self, expr) }
+    fn visit_string_literal(&mut self, literal: &mut StringLiteral) { walk_string_literal(self, literal) }
+    fn visit_integer_literal(&mut self, literal: &mut IntegerLiteral) { walk_integer_literal(self, literal) }
+    fn visit_symbol_expr(&mut self, expr: &mut SymbolExpr) { walk_symbol_expr(self, expr) }
+    fn visit_key_value_expr(&mut self, expr: &mut KeyValueExpr) { walk_key_value_expr(self, expr) }
+    fn visit_negate_expr(&mut self, expr: &mut NegateExpr) { walk_negate_expr(self, expr) }
+    fn visit_defined_expr(&mut self, expr: &mut DefinedExpr) { walk_defined_expr(self, expr) }
+    fn visit_super_expr(&mut self, expr: &mut SuperExpr) { walk_super_expr(self, expr) }
+    fn visit_zsuper_expr(&mut self, expr: &mut ZSuperExpr) { walk_zsuper_expr(self, expr) }
+    fn visit_yield_expr(&mut self, expr: &mut YieldExpr) { walk_yield_expr(self, expr) }
+    fn visit_return_expr(&mut self, expr: &mut ReturnExpr) { walk_return_expr(self, expr) }
+    fn visit_binary_expr(&mut self, expr: &mut BinaryExpr) { walk_binary_expr(self, expr) }
+    fn visit_character_literal(&mut self, literal: &mut CharacterLiteral) { walk_character_literal(self, literal) }
+    fn visit_ternary_expr(&mut self, expr: &mut TernaryExpr) { walk_ternary_expr(self, expr) }
+    fn visit_multiple_assignment_expr(&mut self, expr: &mut MultipleAssignmentExpr) { walk_multiple_assignment_expr(self, expr) }
+    fn visit_index_expr(&mut self, expr: &mut IndexExpr) { walk_index_expr(self, expr) }
+    fn visit_for_expr(&mut self, expr: &mut ForExpr) { walk_for_expr(self, expr) }
+    fn visit_begin_expr(&mut self, expr: &mut BeginExpr) { walk_begin_expr(self, expr) }
+    fn visit_rescue_clause(&mut self, clause: &mut RescueClause) { walk_rescue_clause(self, clause) }
+    fn visit_error_expr(&mut self, expr: &mut ErrorExpr) { walk_error_expr(self, expr) }
+    fn visit_missing_expr(&mut self, expr: &mut MissingExpr) { walk_missing_expr(self, expr) }
+
+    fn visit_block(&mut self, block: &mut Block) { walk_block(self, block) }
+    fn visit_assign_target(&mut self, target: &mut AssignTarget) { walk_assign_target(self, target) }
+    fn visit_attribute_target(&mut self, target: &mut AttributeTarget) { walk_attribute_target(self, target) }
+    fn visit_constant_path(&mut self, path: &mut ConstantPath) { walk_constant_path(self, path) }
+    fn visit_path(&mut self, path: &mut Path) { walk_path(self, path) }
+    fn visit_path_segment(&mut self, segment: &mut PathSegment) { walk_path_segment(self, segment) }
+    fn visit_argument(&mut self, argument: &mut Argument) { walk_argument(self, argument) }
+    fn visit_parameter(&mut self, parameter: &mut Parameter) { walk_parameter(self, parameter) }
+}
+
+pub fn walk_program<V>(visitor: &mut V, program: &mut Program)
+    where V: VisitorMut + ?Sized {
+    walk_items(visitor, &mut program.items);
+}
+
+pub fn walk_item<V>(visitor: &mut V, item: &mut Item)
+    where V: VisitorMut + ?Sized {
+    match *item {
+        Item::Module(ref mut module) => visitor.visit_module(module),
+        Item::Class(ref mut class) => visitor.visit_class(class),
+        Item::Function(ref mut function) => visitor.visit_function(function),
+        Item::Stmt(ref mut stmt) => visitor.visit_stmt(stmt),
+        Item::BeginBlock(ref mut block) => visitor.visit_begin_block(block),
+        Item::EndBlock(ref mut block) => visitor.visit_end_block(block),
+    }
+}
+
+pub fn walk_module<V>(visitor: &mut V, module: &mut Module)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_constant_path(&mut module.name);
+    walk_items(visitor, &mut module.items);
+}
+
+pub fn walk_class<V>(visitor: &mut V, class: &mut Class)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_constant_path(&mut class.name);
+    if let Some(ref mut superclass) = class.superclass {
+        visitor.visit_expr(superclass);
+    }
+    walk_items(visitor, &mut class.items);
+}
+
+pub fn walk_function<V>(visitor: &mut V, function: &mut Function)
+    where V: VisitorMut + ?Sized {
+    for parameter in function.parameters.iter_mut() {
+        visitor.visit_parameter(parameter);
+    }
+    walk_stmts(visitor, &mut function.statements);
+}
+
+pub fn walk_begin_block<V>(visitor: &mut V, block: &mut BeginBlock)
+    where V: VisitorMut + ?Sized {
+    walk_stmts(visitor, &mut block.statements);
+}
+
+pub fn walk_end_block<V>(visitor: &mut V, block: &mut EndBlock)
+    where V: VisitorMut + ?Sized {
+    walk_stmts(visitor, &mut block.statements);
+}
+
+pub fn walk_stmt<V>(visitor: &mut V, stmt: &mut Stmt)
+    where V: VisitorMut + ?Sized {
+    match *stmt {
+        Stmt::Expr(ref mut expr) => visitor.visit_expr(expr),
+        Stmt::Alias(ref mut alias) => visitor.visit_alias(alias),
+        Stmt::Undef(ref mut undef) => visitor.visit_undef(undef),
+    }
+}
+
+pub fn walk_alias<V>(_visitor: &mut V, _alias: &mut Alias)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_undef<V>(_visitor: &mut V, _undef: &mut Undef)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_expr<V>(visitor: &mut V, expr: &mut Expr)
+    where V: VisitorMut + ?Sized {
+    match *expr {
+        Expr::Assignment(ref mut expr) => visitor.visit_assignment_expr(expr),
+        Expr::ConstantAssignment(ref mut expr) => visitor.visit_constant_assignment_expr(expr),
+        Expr::Constant(ref mut expr) => visitor.visit_constant_expr(expr),
+        Expr::Paren(ref mut expr) => visitor.visit_paren_expr(expr),
+        Expr::Call(ref mut expr) => visitor.visit_call_expr(expr),
+        Expr::StringLiteral(ref mut literal) => visitor.visit_string_literal(literal),
+        Expr::IntegerLiteral(ref mut literal) => visitor.visit_integer_literal(literal),
+        Expr::Symbol(ref mut expr) => visitor.visit_symbol_expr(expr),
+        Expr::KeyValue(ref mut expr) => visitor.visit_key_value_expr(expr),
+        Expr::Negate(ref mut expr) => visitor.visit_negate_expr(expr),
+        Expr::Defined(ref mut expr) => visitor.visit_defined_expr(expr),
+        Expr::Super(ref mut expr) => visitor.visit_super_expr(expr),
+        Expr::ZSuper(ref mut expr) => visitor.visit_zsuper_expr(expr),
+        Expr::Yield(ref mut expr) => visitor.visit_yield_expr(expr),
+        Expr::Return(ref mut expr) => visitor.visit_return_expr(expr),
+        Expr::Binary(ref mut expr) => visitor.visit_binary_expr(expr),
+        Expr::CharacterLiteral(ref mut literal) => visitor.visit_character_literal(literal),
+        Expr::Ternary(ref mut expr) => visitor.visit_ternary_expr(expr),
+        Expr::MultipleAssignment(ref mut expr) => visitor.visit_multiple_assignment_expr(expr),
+        Expr::Index(ref mut expr) => visitor.visit_index_expr(expr),
+        Expr::For(ref mut expr) => visitor.visit_for_expr(expr),
+        Expr::Begin(ref mut expr) => visitor.visit_begin_expr(expr),
+        Expr::Error(ref mut expr) => visitor.visit_error_expr(expr),
+        Expr::Missing(ref mut expr) => visitor.visit_missing_expr(expr),
+    }
+}
+
+pub fn walk_assignment_expr<V>(visitor: &mut V, expr: &mut AssignmentExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_assign_target(&mut expr.assignee);
+    visitor.visit_expr(&mut expr.value);
+}
+
+pub fn walk_constant_assignment_expr<V>(visitor: &mut V, expr: &mut ConstantAssignmentExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_constant_path(&mut expr.constant);
+    visitor.visit_expr(&mut expr.value);
+}
+
+pub fn walk_constant_expr<V>(visitor: &mut V, expr: &mut ConstantExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_constant_path(&mut expr.path);
+}
+
+pub fn walk_paren_expr<V>(visitor: &mut V, expr: &mut ParenExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.inner);
+}
+
+pub fn walk_call_expr<V>(visitor: &mut V, expr: &mut CallExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_path(&mut expr.callee);
+    walk_arguments(visitor, &mut expr.arguments);
+    if let Some(ref mut block) = expr.block {
+        visitor.visit_block(block);
+    }
+}
+
+pub fn walk_string_literal<V>(_visitor: &mut V, _literal: &mut StringLiteral)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_integer_literal<V>(_visitor: &mut V, _literal: &mut IntegerLiteral)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_symbol_expr<V>(_visitor: &mut V, _expr: &mut SymbolExpr)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_key_value_expr<V>(visitor: &mut V, expr: &mut KeyValueExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.value);
+}
+
+pub fn walk_negate_expr<V>(visitor: &mut V, expr: &mut NegateExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.inner);
+}
+
+pub fn walk_defined_expr<V>(visitor: &mut V, expr: &mut DefinedExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.inner);
+}
+
+pub fn walk_super_expr<V>(visitor: &mut V, expr: &mut SuperExpr)
+    where V: VisitorMut + ?Sized {
+    walk_arguments(visitor, &mut expr.arguments);
+}
+
+pub fn walk_zsuper_expr<V>(_visitor: &mut V, _expr: &mut ZSuperExpr)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_yield_expr<V>(visitor: &mut V, expr: &mut YieldExpr)
+    where V: VisitorMut + ?Sized {
+    walk_arguments(visitor, &mut expr.arguments);
+}
+
+pub fn walk_return_expr<V>(visitor: &mut V, expr: &mut ReturnExpr)
+    where V: VisitorMut + ?Sized {
+    walk_arguments(visitor, &mut expr.arguments);
+}
+
+pub fn walk_binary_expr<V>(visitor: &mut V, expr: &mut BinaryExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.lhs);
+    visitor.visit_expr(&mut expr.rhs);
+}
+
+pub fn walk_character_literal<V>(_visitor: &mut V, _literal: &mut CharacterLiteral)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_ternary_expr<V>(visitor: &mut V, expr: &mut TernaryExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.condition);
+    visitor.visit_expr(&mut expr.if_true);
+    visitor.visit_expr(&mut expr.if_false);
+}
+
+pub fn walk_multiple_assignment_expr<V>(visitor: &mut V, expr: &mut MultipleAssignmentExpr)
+    where V: VisitorMut + ?Sized {
+    for target in expr.targets.iter_mut() {
+        visitor.visit_assign_target(target);
+    }
+    for value in expr.values.iter_mut() {
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_index_expr<V>(visitor: &mut V, expr: &mut IndexExpr)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut expr.receiver);
+    walk_arguments(visitor, &mut expr.arguments);
+}
+
+pub fn walk_for_expr<V>(visitor: &mut V, expr: &mut ForExpr)
+    where V: VisitorMut + ?Sized {
+    for target in expr.targets.iter_mut() {
+        visitor.visit_assign_target(target);
+    }
+    visitor.visit_expr(&mut expr.iterable);
+    walk_stmts(visitor, &mut expr.statements);
+}
+
+pub fn walk_begin_expr<V>(visitor: &mut V, expr: &mut BeginExpr)
+    where V: VisitorMut + ?Sized {
+    walk_stmts(visitor, &mut expr.statements);
+    for clause in expr.rescue_clauses.iter_mut() {
+        visitor.visit_rescue_clause(clause);
+    }
+    if let Some(ref mut statements) = expr.else_statements {
+        walk_stmts(visitor, statements);
+    }
+    if let Some(ref mut statements) = expr.ensure_statements {
+        walk_stmts(visitor, statements);
+    }
+}
+
+pub fn walk_rescue_clause<V>(visitor: &mut V, clause: &mut RescueClause)
+    where V: VisitorMut + ?Sized {
+    for exception in clause.exceptions.iter_mut() {
+        visitor.visit_expr(exception);
+    }
+    if let Some(ref mut target) = clause.target {
+        visitor.visit_assign_target(target);
+    }
+    walk_stmts(visitor, &mut clause.statements);
+}
+
+pub fn walk_error_expr<V>(_visitor: &mut V, _expr: &mut ErrorExpr)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_missing_expr<V>(_visitor: &mut V, _expr: &mut MissingExpr)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_block<V>(visitor: &mut V, block: &mut Block)
+    where V: VisitorMut + ?Sized {
+    for parameter in block.parameters.iter_mut() {
+        visitor.visit_assign_target(parameter);
+    }
+    walk_stmts(visitor, &mut block.statements);
+}
+
+pub fn walk_assign_target<V>(visitor: &mut V, target: &mut AssignTarget)
+    where V: VisitorMut + ?Sized {
+    match *target {
+        AssignTarget::Local(..) | AssignTarget::InstanceVariable(..) |
+            AssignTarget::GlobalVariable(..) | AssignTarget::ClassVariable(..) => (),
+        AssignTarget::Constant(ref mut path) => visitor.visit_constant_path(path),
+        AssignTarget::Attribute(ref mut target) => visitor.visit_attribute_target(target),
+        AssignTarget::Index(ref mut expr) => visitor.visit_index_expr(expr),
+        AssignTarget::Splat(ref mut target) => if let Some(ref mut target) = *target {
+            visitor.visit_assign_target(target);
+        },
+        AssignTarget::Group(ref mut targets) => for target in targets.iter_mut() {
+            visitor.visit_assign_target(target);
+        },
+    }
+}
+
+pub fn walk_attribute_target<V>(visitor: &mut V, target: &mut AttributeTarget)
+    where V: VisitorMut + ?Sized {
+    visitor.visit_expr(&mut target.receiver);
+}
+
+pub fn walk_constant_path<V>(visitor: &mut V, path: &mut ConstantPath)
+    where V: VisitorMut + ?Sized {
+    if let ConstantScope::Expr(ref mut scope) = path.scope {
+        visitor.visit_expr(scope);
+    }
+}
+
+pub fn walk_path<V>(visitor: &mut V, path: &mut Path)
+    where V: VisitorMut + ?Sized {
+    for segment in path.parts.iter_mut() {
+        visitor.visit_path_segment(segment);
+    }
+}
+
+pub fn walk_path_segment<V>(_visitor: &mut V, _segment: &mut PathSegment)
+    where V: VisitorMut + ?Sized {
+}
+
+pub fn walk_argument<V>(visitor: &mut V, argument: &mut Argument)
+    where V: VisitorMut + ?Sized {
+    match *argument {
+        Argument::Positional(ref mut expr) => visitor.visit_expr(expr),
+    }
+}
+
+pub fn walk_parameter<V>(visitor: &mut V, parameter: &mut Parameter)
+    where V: VisitorMut + ?Sized {
+    if let Some(ref mut default) = parameter.default {
+        visitor.visit_expr(default);
+    }
+}
+
+fn walk_items<V>(visitor: &mut V, items: &mut [Item])
+    where V: VisitorMut + ?Sized {
+    for item in items.iter_mut() {
+        visitor.visit_item(item);
+    }
+}
+
+fn walk_stmts<V>(visitor: &mut V, statements: &mut [Stmt])
+    where V: VisitorMut + ?Sized {
+    for stmt in statements.iter_mut() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+fn walk_arguments<V>(visitor: &mut V, arguments: &mut [Argument])
+    where V: VisitorMut + ?Sized {
+    for argument in arguments.iter_mut() {
+        visitor.visit_argument(argument);
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::Parser;
+
+    /// Renames every local variable.
+    struct Rename<'a>(&'a str, &'a str);
+
+    impl<'a> VisitorMut for Rename<'a>
+    {
+        fn visit_assign_target(&mut self, target: &mut AssignTarget) {
+            if let AssignTarget::Local(ref mut identifier) = *target {
+                if identifier.0 == self.0 { identifier.0 = Symbol::intern(self.1) }
+            }
+            walk_assign_target(self, target);
+        }
+
+        fn visit_path_segment(&mut self, segment: &mut PathSegment) {
+            if let PathSegmentKind::Identifier(ref mut identifier) = segment.kind {
+                if identifier.0 == self.0 { identifier.0 = Symbol::intern(self.1) }
+            }
+        }
+    }
+
+    #[test]
+    fn changes_nodes_in_place() {
+        let mut program = Parser::from("a = 1\nfoo(a) { |a| a + b }\n").parse().unwrap();
+        Rename("a", "x").visit_program(&mut program);
+
+        assert_eq!(program, Parser::from("x = 1\nfoo(x) { |x| x + b }\n").parse().unwrap());
+    }
+}
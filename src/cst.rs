@@ -0,0 +1,262 @@
+//! A lossless concrete syntax tree.
+//!
+//! Every character of the source, including whitespace, comments and the
+//! `__END__` data section, belongs to exactly one token, so printing the
+//! tree gives back the original text.
+
+use parse::Token;
+use span::Span;
+use ast;
+
+use std::fmt;
+
+/// The kind of a syntax node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxKind
+{
+    Program,
+    Class,
+    Module,
+    Function,
+    Parameters,
+    BeginBlock,
+    EndBlock,
+    Statement,
+    Alias,
+    Undef,
+    MultipleAssignment,
+    Assignment,
+    Ternary,
+    Binary,
+    Index,
+    Constant,
+    Call,
+    Arguments,
+    Block,
+    Literal,
+    Paren,
+    Symbol,
+    KeyValue,
+    Negate,
+    Defined,
+    Super,
+    Yield,
+    Return,
+    For,
+    Begin,
+    Rescue,
+    /// Tokens which could not be parsed.
+    Error,
+    /// An expression which was expected but not written. It has no tokens.
+    Missing,
+}
+
+/// A token along with its exact text and the trivia before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxToken
+{
+    pub token: Token,
+    /// The whitespace, comments and line continuations before the token.
+    pub leading_trivia: String,
+    /// The text of the token itself.
+    pub text: String,
+    /// Where the token is in the source, excluding its trivia.
+    pub span: Span,
+    /// The line the token starts on, starting from one.
+    pub line: usize,
+    /// The indentation of that line, in characters.
+    pub indent: usize,
+}
+
+/// A node or a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyntaxElement
+{
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A node in the syntax tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxNode
+{
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+/// A lossless syntax tree, along with the AST parsed from the same tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxTree
+{
+    pub root: SyntaxNode,
+    pub program: ast::Program,
+}
+
+impl SyntaxNode
+{
+    /// Gets every token in the node, in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    /// Gets the nodes directly beneath this one.
+    pub fn child_nodes(&self) -> Vec<&SyntaxNode> {
+        self.children.iter().filter_map(|child| match *child {
+            SyntaxElement::Node(ref node) => Some(node),
+            SyntaxElement::Token(..) => None,
+        }).collect()
+    }
+
+    /// Gets the exact source text of the node.
+    pub fn text(&self) -> String {
+        self.to_string()
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in self.children.iter() {
+            match *child {
+                SyntaxElement::Node(ref node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(ref token) => tokens.push(token),
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxToken
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.leading_trivia)?;
+        fmt.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for SyntaxNode
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for child in self.children.iter() {
+            match *child {
+                SyntaxElement::Node(ref node) => node.fmt(fmt)?,
+                SyntaxElement::Token(ref token) => token.fmt(fmt)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a syntax tree from a stream of tokens and node boundaries.
+#[derive(Debug, Default)]
+pub struct Builder
+{
+    /// The nodes which have been started but not finished, along with the
+    /// index of their first child in `children`.
+    open_nodes: Vec<(SyntaxKind, usize)>,
+    /// The children of every open node, outermost first.
+    children: Vec<SyntaxElement>,
+}
+
+/// A position in the tree that a node can later be started at.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+impl Builder
+{
+    pub fn new() -> Self { Builder::default() }
+
+    /// Adds a token to the current node.
+    pub fn token(&mut self, token: SyntaxToken) {
+        self.children.push(SyntaxElement::Token(token));
+    }
+
+    /// Starts a new node containing everything added until it is finished.
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.open_nodes.push((kind, self.children.len()));
+    }
+
+    /// Gets the current position, so that a node can be started there once
+    /// we know what it is, as in the left hand side of a binary operator.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Starts a node containing everything since a checkpoint.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        assert!(checkpoint.0 <= self.children.len(), "checkpoint is no longer valid");
+        self.open_nodes.push((kind, checkpoint.0));
+    }
+
+    /// Finishes the most recently started node.
+    pub fn finish_node(&mut self) {
+        let (kind, first_child) = self.open_nodes.pop().expect("no node to finish");
+        let children = self.children.split_off(first_child);
+
+        self.children.push(SyntaxElement::Node(SyntaxNode { kind, children }));
+    }
+
+    /// Wraps everything that has been added into a node.
+    pub fn finish(mut self, kind: SyntaxKind) -> SyntaxNode {
+        // Close any nodes left open by an error.
+        while !self.open_nodes.is_empty() {
+            self.finish_node();
+        }
+
+        SyntaxNode { kind, children: self.children }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use parse::Parser;
+
+    fn parse(src: &str) -> SyntaxTree {
+        Parser::new(src.chars()).parse_lossless().expect("failed to parse")
+    }
+
+    fn assert_round_trips(src: &str) {
+        assert_eq!(parse(src).root.to_string(), src);
+    }
+
+    #[test]
+    fn round_trips_whitespace_and_comments() {
+        assert_round_trips("# frozen_string_literal: true\n\nclass Foo < Bar # the class\n\n  def  baz(a,  b)\n    a   +\n      b\n  end\nend\n");
+        assert_round_trips("  puts  'hi' ;puts(\"a\\tb\")");
+        assert_round_trips("a = 1 \\\n  + 2\n\n\n");
+        assert_round_trips("x # trailing comment without a new line");
+    }
+
+    #[test]
+    fn round_trips_block_comments_and_data() {
+        assert_round_trips("=begin\nsome docs\n=end\nfoo\n__END__\nraw data\n");
+    }
+
+    #[test]
+    fn round_trips_expressions() {
+        assert_round_trips("foo.bar(:a => 1, b: ?c) { |x, (y, *z)| x ? y : -z }\n");
+        assert_round_trips("begin\n  a, *b = c[1], ::D::E\nrescue Foo => e\n  f\nensure\n  g\nend\n");
+        assert_round_trips("for i in list do\n  puts i ** 2\nend\n");
+    }
+
+    #[test]
+    fn nests_nodes() {
+        let tree = parse("a + b * c\n");
+        let statement = tree.root.child_nodes()[0];
+        let binary = statement.child_nodes()[0];
+
+        assert_eq!(statement.kind, SyntaxKind::Statement);
+        assert_eq!(binary.kind, SyntaxKind::Binary);
+        assert_eq!(binary.child_nodes().iter().map(|node| node.kind).collect::<Vec<_>>(),
+                   vec![SyntaxKind::Call, SyntaxKind::Binary]);
+        assert_eq!(binary.child_nodes()[1].text(), " b * c");
+    }
+
+    #[test]
+    fn derives_the_same_ast() {
+        let src = "class A\n  def b\n    c(1)\n  end\nend\n";
+
+        assert_eq!(parse(src).program, Parser::new(src.chars()).parse().unwrap());
+    }
+}
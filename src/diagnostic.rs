@@ -0,0 +1,353 @@
+//! Diagnostics, which describe a problem in a program along with where it
+//! is in the source.
+
+use span::{Span, LineColumn};
+
+use std::{fmt, iter};
+
+/// How serious a diagnostic is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity
+{
+    Note,
+    Warning,
+    Error,
+}
+
+/// Whether a label marks the problem itself or something related to it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LabelStyle
+{
+    /// The problem, underlined with `^`.
+    Primary,
+    /// Something which explains the problem, underlined with `-`.
+    Secondary,
+}
+
+/// A message attached to a span of source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label
+{
+    pub span: Span,
+    pub message: String,
+    pub style: LabelStyle,
+}
+
+/// A problem in a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic
+{
+    pub severity: Severity,
+    /// A code which identifies the kind of problem, such as `E0001`.
+    ///
+    /// Codes never change meaning, so they can be searched for and ignored.
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// The labelled spans, which are shown in source order.
+    pub labels: Vec<Label>,
+    /// Suggestions for how to fix the problem.
+    pub help: Vec<String>,
+}
+
+/// Renders diagnostics along with the lines of source they refer to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Renderer
+{
+    /// Whether to colour the output with ANSI escape codes.
+    ansi: bool,
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+impl Severity
+{
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    fn colour(self) -> &'static str {
+        match self {
+            Severity::Note => GREEN,
+            Severity::Warning => YELLOW,
+            Severity::Error => RED,
+        }
+    }
+}
+
+impl Diagnostic
+{
+    pub fn new<S>(severity: Severity, message: S) -> Self where S: Into<String> {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error<S>(message: S) -> Self where S: Into<String> {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning<S>(message: S) -> Self where S: Into<String> {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Marks where the problem is.
+    pub fn with_primary_label<S>(self, span: Span, message: S) -> Self where S: Into<String> {
+        self.with_label(span, message, LabelStyle::Primary)
+    }
+
+    /// Marks something related to the problem, such as where a block that
+    /// was never closed was opened.
+    pub fn with_secondary_label<S>(self, span: Span, message: S) -> Self where S: Into<String> {
+        self.with_label(span, message, LabelStyle::Secondary)
+    }
+
+    pub fn with_help<S>(mut self, help: S) -> Self where S: Into<String> {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Gets the span of the first primary label.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|label| label.style == LabelStyle::Primary).map(|label| label.span)
+    }
+
+    fn with_label<S>(mut self, span: Span, message: S, style: LabelStyle) -> Self where S: Into<String> {
+        self.labels.push(Label { span, message: message.into(), style });
+        self
+    }
+}
+
+impl Renderer
+{
+    /// Creates a renderer which outputs plain text.
+    pub fn plain() -> Self { Renderer { ansi: false } }
+
+    /// Creates a renderer which colours its output for a terminal.
+    pub fn ansi() -> Self { Renderer { ansi: true } }
+
+    /// Renders a diagnostic about a file.
+    ///
+    /// ```text
+    /// error[E0001]: unexpected `)`
+    ///  --> test.rb:1:7
+    ///   |
+    /// 1 | foo 1 )
+    ///   |       ^ expected a terminator
+    /// ```
+    pub fn render(&self, diagnostic: &Diagnostic, file_name: &str, source: &str) -> String {
+        let mut output = String::new();
+
+        let colour = diagnostic.severity.colour();
+        output.push_str(&self.paint(colour, diagnostic.severity.as_str()));
+        if let Some(code) = diagnostic.code {
+            output.push_str(&self.paint(colour, &format!("[{}]", code)));
+        }
+        output.push_str(&self.paint(BOLD, &format!(": {}", diagnostic.message)));
+        output.push('\n');
+
+        let mut labels: Vec<_> = diagnostic.labels.iter().map(|label| {
+            (SourceLine::containing(clamp(label.span.start, source), source), label)
+        }).collect();
+        labels.sort_by_key(|(line, label)| (line.number, label.span.start));
+
+        let width = labels.iter().map(|(line, _)| line.number.to_string().len()).max().unwrap_or(0);
+        let padding = " ".repeat(width);
+        let gutter = self.paint(BLUE, &format!("{} |", padding));
+
+        if let Some(span) = diagnostic.primary_span().or_else(|| labels.first().map(|(_, label)| label.span)) {
+            let position = LineColumn::of(clamp(span.start, source), source);
+            output.push_str(&format!("{}{} {}:{}:{}\n", padding, self.paint(BLUE, "-->"),
+                                     file_name, position.line, position.column));
+            output.push_str(&gutter);
+            output.push('\n');
+        }
+
+        let mut last_line = None;
+        for &(ref line, label) in labels.iter() {
+            if last_line != Some(line.number) {
+                if last_line.is_some_and(|last| line.number > last + 1) {
+                    output.push_str(&self.paint(BLUE, "..."));
+                    output.push('\n');
+                }
+
+                output.push_str(&self.paint(BLUE, &format!("{:>width$} |", line.number, width = width)));
+                output.push_str(&format!(" {}\n", line.text));
+                last_line = Some(line.number);
+            }
+
+            let (marker, style) = match label.style {
+                LabelStyle::Primary => ('^', colour),
+                LabelStyle::Secondary => ('-', BLUE),
+            };
+
+            // Spans over several lines are only underlined on the first.
+            let start = clamp(label.span.start, source) - line.start;
+            let end = label.span.end.min(line.start + line.text.len()).saturating_sub(line.start).max(start);
+
+            // Keep tabs so that the markers line up with the source.
+            let indent: String = line.text[..start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let length = line.text[start..end].chars().count().max(1);
+
+            let markers: String = iter::repeat_n(marker, length).collect();
+            output.push_str(&format!("{} {}{}", gutter, indent, self.paint(style, &markers)));
+            if !label.message.is_empty() {
+                output.push_str(&format!(" {}", self.paint(style, &label.message)));
+            }
+            output.push('\n');
+        }
+
+        if !diagnostic.help.is_empty() && !labels.is_empty() {
+            output.push_str(&gutter);
+            output.push('\n');
+        }
+
+        for help in diagnostic.help.iter() {
+            output.push_str(&format!("{} {} {}\n", padding, self.paint(BLUE, "="),
+                                     self.paint(BOLD, &format!("help: {}", help))));
+        }
+
+        output
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.ansi {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+impl fmt::Display for Severity
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt(fmt)
+    }
+}
+
+impl fmt::Display for Diagnostic
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(fmt, "{}[{}]: {}", self.severity, code, self.message),
+            None => write!(fmt, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// A line of source.
+struct SourceLine<'a>
+{
+    /// The line number, starting from one.
+    number: usize,
+    /// The offset of the start of the line.
+    start: usize,
+    /// The text of the line, without its line ending.
+    text: &'a str,
+}
+
+impl<'a> SourceLine<'a>
+{
+    fn containing(offset: usize, source: &'a str) -> Self {
+        let start = source[..offset].rfind('\n').map(|index| index + 1).unwrap_or(0);
+        let end = source[offset..].find('\n').map(|index| offset + index).unwrap_or(source.len());
+        let text = &source[start..end];
+
+        SourceLine {
+            number: LineColumn::of(offset, source).line,
+            start,
+            text: text.strip_suffix('\r').unwrap_or(text),
+        }
+    }
+}
+
+/// Moves an offset at the very end of a file back onto its last line, so
+/// that a missing `end` is shown after the last line rather than on an
+/// empty one.
+fn clamp(offset: usize, source: &str) -> usize {
+    let offset = offset.min(source.len());
+
+    if offset == source.len() && source.ends_with('\n') {
+        source.trim_end_matches(['\n', '\r']).len()
+    } else {
+        offset
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn renders_primary_and_secondary_labels() {
+        let source = "class Foo\n  def bar\n    baz )\n  end\n";
+        let diagnostic = Diagnostic::error("unexpected `)`")
+            .with_code("E0001")
+            .with_primary_label(Span::new(28, 29), "expected a terminator")
+            .with_secondary_label(Span::new(12, 19), "in this method")
+            .with_help("remove the `)`");
+
+        assert_eq!(Renderer::plain().render(&diagnostic, "test.rb", source), "\
+error[E0001]: unexpected `)`
+ --> test.rb:3:9
+  |
+2 |   def bar
+  |   ------- in this method
+3 |     baz )
+  |         ^ expected a terminator
+  |
+  = help: remove the `)`
+");
+    }
+
+    #[test]
+    fn renders_empty_spans_at_the_end_of_the_file() {
+        let source = "foo(1,\n";
+        let diagnostic = Diagnostic::error("unexpected end-of-file")
+            .with_primary_label(Span::at(source.len()), "");
+
+        assert_eq!(Renderer::plain().render(&diagnostic, "test.rb", source), "\
+error: unexpected end-of-file
+ --> test.rb:1:7
+  |
+1 | foo(1,
+  |       ^
+");
+    }
+
+    #[test]
+    fn underlines_only_the_first_line_of_multi_line_spans() {
+        let source = "\tfoo(\n  1)\n";
+        let diagnostic = Diagnostic::warning("a warning").with_primary_label(Span::new(1, 10), "here");
+
+        assert!(Renderer::plain().render(&diagnostic, "test.rb", source).contains("1 | \tfoo(\n  | \t^^^^ here\n"));
+    }
+
+    #[test]
+    fn colours_output_for_terminals() {
+        let diagnostic = Diagnostic::error("oops").with_primary_label(Span::new(0, 1), "");
+        let output = Renderer::ansi().render(&diagnostic, "test.rb", "a\n");
+
+        assert!(output.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(output.contains("\x1b[1;31m^\x1b[0m"));
+    }
+}
@@ -8,4 +8,13 @@ error_chain! {
     foreign_links {
         Io(::std::io::Error);
     }
+
+    errors {
+        /// Formatting would have changed what the code means, which is a
+        /// bug in the formatter.
+        FormattingChangedMeaning {
+            description("formatting changed the meaning of the code")
+            display("formatting changed the meaning of the code")
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Showing how formatting changes a file, as a unified diff.
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// What happens to a line going from the old text to the new.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Edit
+{
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Gets a unified diff between two texts, or nothing if they are the same.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = new.split_inclusive('\n').collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    let mut output = String::new();
+    if edits.iter().all(|&edit| edit == Edit::Keep) {
+        return output;
+    }
+
+    output.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+
+    let mut index = 0;
+    while let Some(first_change) = edits[index..].iter().position(|&edit| edit != Edit::Keep) {
+        let start = (index + first_change).saturating_sub(CONTEXT);
+
+        // Carry on until the unchanged lines could not be context for both
+        // this change and the next.
+        let mut end = index + first_change;
+        let mut unchanged = 0;
+        while end < edits.len() && unchanged <= CONTEXT * 2 {
+            unchanged = if edits[end] == Edit::Keep { unchanged + 1 } else { 0 };
+            end += 1;
+        }
+        let end = end - unchanged.saturating_sub(CONTEXT);
+
+        hunk(&mut output, &edits, start, end, &old_lines, &new_lines);
+        index = end;
+    }
+
+    output
+}
+
+/// Writes the hunk covering the edits in a range.
+fn hunk(output: &mut String, edits: &[Edit], start: usize, end: usize, old_lines: &[&str], new_lines: &[&str]) {
+    let count = |edits: &[Edit], skip: Edit| edits.iter().filter(|&&edit| edit != skip).count();
+    let old_start = count(&edits[..start], Edit::Insert);
+    let new_start = count(&edits[..start], Edit::Delete);
+    let old_count = count(&edits[start..end], Edit::Insert);
+    let new_count = count(&edits[start..end], Edit::Delete);
+
+    // Empty ranges are numbered by the line before them.
+    let line_number = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+    output.push_str(&format!("@@ -{},{} +{},{} @@\n",
+                             line_number(old_start, old_count), old_count,
+                             line_number(new_start, new_count), new_count));
+
+    let (mut old_index, mut new_index) = (old_start, new_start);
+    for &edit in edits[start..end].iter() {
+        let (prefix, line) = match edit {
+            Edit::Keep => { old_index += 1; new_index += 1; (' ', old_lines[old_index - 1]) },
+            Edit::Delete => { old_index += 1; ('-', old_lines[old_index - 1]) },
+            Edit::Insert => { new_index += 1; ('+', new_lines[new_index - 1]) },
+        };
+
+        output.push(prefix);
+        output.push_str(line);
+        if !line.ends_with('\n') {
+            output.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// Finds the shortest way to edit one list of lines into another, using
+/// Myers' algorithm.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let max = old.len() + new.len();
+    let offset = max as isize;
+    // The furthest `x` reached along each diagonal `k = x - y`.
+    let mut furthest = vec![0usize; 2 * max + 2];
+    let mut trace = Vec::new();
+
+    let from_above = |furthest: &[usize], d: isize, k: isize| {
+        k == -d || (k != d && furthest[(k - 1 + offset) as usize] < furthest[(k + 1 + offset) as usize])
+    };
+
+    'search: for d in 0..=(max as isize) {
+        trace.push(furthest.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if from_above(&furthest, d, k) {
+                furthest[(k + 1 + offset) as usize]
+            } else {
+                furthest[(k - 1 + offset) as usize] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+
+            while x < old.len() && y < new.len() && old[x] == new[y] {
+                x += 1;
+                y += 1;
+            }
+
+            furthest[(k + offset) as usize] = x;
+            if x >= old.len() && y >= new.len() {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back through the furthest points to recover the path taken.
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (old.len() as isize, new.len() as isize);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous_k = if from_above(furthest, d, k) { k + 1 } else { k - 1 };
+        let previous_x = furthest[(previous_k + offset) as usize] as isize;
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Keep);
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            edits.push(if x == previous_x { Edit::Insert } else { Edit::Delete });
+            x = previous_x;
+            y = previous_y;
+        }
+    }
+
+    edits.reverse();
+    edits
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn diffs_changed_lines_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+
+        assert_eq!(unified_diff(old, new, "old", "new"), "\
+--- old
++++ new
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -8,3 +8,4 @@
+ h
+ i
+ j
++k
+\\ No newline at end of file
+");
+        assert_eq!(unified_diff(old, old, "old", "new"), "");
+    }
+}
@@ -0,0 +1,193 @@
+//! Documents which lay themselves out to fit a width.
+//!
+//! A document is text along with places it may be broken across lines.
+//! Each group is printed on one line if it fits, and otherwise has every one
+//! of its line breaks taken.
+
+use std::mem;
+
+/// The text each level of indentation is made of.
+const INDENT: &str = "  ";
+
+/// A part of a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Doc
+{
+    Text(String),
+    /// Text printed from the start of a line whatever the indentation, as
+    /// `=begin` comments must be.
+    Raw(String),
+    /// A space, or a new line if the group is broken.
+    Line,
+    /// Nothing, or a new line if the group is broken.
+    SoftLine,
+    /// A new line, which breaks every group around it.
+    HardLine,
+    /// A new line followed by an empty line.
+    BlankLine,
+    /// Text printed only if the group is broken, such as a trailing comma.
+    IfBreak(&'static str),
+    /// Text put off until the end of the line, for trailing comments. It
+    /// breaks every group around it.
+    LineSuffix(String),
+    Indent(Vec<Doc>),
+    Group(Vec<Doc>),
+}
+
+/// Prints documents.
+#[derive(Debug)]
+pub struct Renderer
+{
+    width: usize,
+    output: String,
+    /// The column the next character goes in.
+    column: usize,
+    /// The indentation of the current line, if nothing has been written on
+    /// it yet.
+    pending_indent: Option<usize>,
+    line_suffixes: Vec<String>,
+}
+
+impl Doc
+{
+    /// Checks if the document can only be printed across multiple lines.
+    fn must_break(&self) -> bool {
+        match *self {
+            Doc::HardLine | Doc::BlankLine | Doc::Raw(..) | Doc::LineSuffix(..) => true,
+            Doc::Indent(ref docs) | Doc::Group(ref docs) => docs.iter().any(Doc::must_break),
+            Doc::Text(..) | Doc::Line | Doc::SoftLine | Doc::IfBreak(..) => false,
+        }
+    }
+
+    /// Gets the width of the document printed on one line.
+    fn flat_width(&self) -> usize {
+        match *self {
+            Doc::Text(ref text) => text.chars().count(),
+            Doc::Line => 1,
+            Doc::Indent(ref docs) | Doc::Group(ref docs) => docs.iter().map(Doc::flat_width).sum(),
+            _ => 0,
+        }
+    }
+}
+
+impl Renderer
+{
+    pub fn new(width: usize) -> Self {
+        Renderer { width, output: String::new(), column: 0, pending_indent: Some(0), line_suffixes: Vec::new() }
+    }
+
+    /// Prints documents, finishing any line they leave open.
+    pub fn render(mut self, docs: &[Doc]) -> String {
+        self.docs(docs, 0, false);
+        self.flush_line_suffixes();
+        self.output
+    }
+
+    fn docs(&mut self, docs: &[Doc], indent: usize, flat: bool) {
+        for doc in docs.iter() {
+            self.doc(doc, indent, flat);
+        }
+    }
+
+    fn doc(&mut self, doc: &Doc, indent: usize, flat: bool) {
+        match *doc {
+            Doc::Text(ref text) => self.text(text),
+            Doc::Raw(ref text) => {
+                self.pending_indent = None;
+                self.output.push_str(text);
+                self.column = text.rsplit('\n').next().unwrap_or("").chars().count();
+            },
+            Doc::Line if flat => self.text(" "),
+            Doc::SoftLine if flat => (),
+            Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(indent),
+            Doc::BlankLine => {
+                self.newline(indent);
+                if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+                    self.output.push('\n');
+                }
+            },
+            Doc::IfBreak(text) => if !flat { self.text(text) },
+            Doc::LineSuffix(ref text) => self.line_suffixes.push(text.clone()),
+            Doc::Indent(ref docs) => self.docs(docs, indent + 1, flat),
+            Doc::Group(ref docs) => {
+                let fits = !docs.iter().any(Doc::must_break) &&
+                    self.column() + docs.iter().map(Doc::flat_width).sum::<usize>() <= self.width;
+                self.docs(docs, indent, flat || fits);
+            },
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some(indent) = self.pending_indent.take() {
+            for _ in 0..indent {
+                self.output.push_str(INDENT);
+            }
+            self.column = indent * INDENT.len();
+        }
+
+        self.output.push_str(text);
+        self.column += text.chars().count();
+    }
+
+    /// Starts a new line, unless the current one is still empty.
+    fn newline(&mut self, indent: usize) {
+        self.flush_line_suffixes();
+
+        match self.pending_indent {
+            Some(ref mut pending) => *pending = indent,
+            None => {
+                self.output.push('\n');
+                self.column = 0;
+                self.pending_indent = Some(indent);
+            },
+        }
+    }
+
+    fn flush_line_suffixes(&mut self) {
+        for suffix in mem::take(&mut self.line_suffixes) {
+            self.text(&suffix);
+        }
+    }
+
+    fn column(&self) -> usize {
+        match self.pending_indent {
+            Some(indent) => indent * INDENT.len(),
+            None => self.column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn text(text: &str) -> Doc { Doc::Text(text.to_owned()) }
+
+    /// `foo(a, b)`, broken with a trailing comma if it does not fit.
+    fn call(arguments: &[&str]) -> Doc {
+        let mut inner = vec![Doc::SoftLine];
+        for (index, argument) in arguments.iter().enumerate() {
+            if index > 0 {
+                inner.extend(vec![text(","), Doc::Line]);
+            }
+            inner.push(text(argument));
+        }
+        inner.push(Doc::IfBreak(","));
+
+        Doc::Group(vec![text("foo("), Doc::Indent(inner), Doc::SoftLine, text(")")])
+    }
+
+    #[test]
+    fn breaks_groups_which_do_not_fit() {
+        assert_eq!(Renderer::new(20).render(&[call(&["a", "b"])]), "foo(a, b)");
+        assert_eq!(Renderer::new(10).render(&[call(&["aaa", "bbb"])]), "foo(\n  aaa,\n  bbb,\n)");
+    }
+
+    #[test]
+    fn keeps_trailing_comments_at_the_end_of_the_line() {
+        let docs = [text("a"), Doc::LineSuffix(" # a".to_owned()), text(" + b"), Doc::HardLine,
+                    Doc::HardLine, Doc::BlankLine, text("c")];
+        assert_eq!(Renderer::new(80).render(&docs), "a + b # a\n\nc");
+    }
+}
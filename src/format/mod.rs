@@ -0,0 +1,648 @@
+//! Formatting Ruby source in a canonical style.
+//!
+//! Formatting works on the lossless syntax tree, so that comments and the
+//! blank lines separating groups of statements are kept. Everything else
+//! about the layout is decided by the formatter:
+//!
+//! * Bodies are indented by two spaces, with one statement per line.
+//! * Binary operators, `=`, `=>` and the parts of a ternary have a space
+//!   either side, and commas have a space after them.
+//! * Argument and parameter lists which do not fit in the width are put one
+//!   per line, with a trailing comma after the last argument.
+//! * Strings use single quotes unless they need double quotes.
+//! * Runs of blank lines become one, and blank lines at the start or end of
+//!   a body are removed.
+
+pub use self::doc::{Doc, Renderer};
+pub use self::diff::unified_diff;
+
+pub mod doc;
+pub mod diff;
+
+use ast;
+use cst::{SyntaxTree, SyntaxNode, SyntaxElement, SyntaxToken, SyntaxKind};
+use parse::{Parser, Token, Keyword};
+use errors::{Error, ErrorKind};
+
+/// The width lines are kept within where possible.
+pub const DEFAULT_WIDTH: usize = 100;
+
+/// Formats Ruby source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Formatter
+{
+    width: usize,
+}
+
+/// How a construct lays out its body.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Style
+{
+    /// The top level of the program, which is not indented.
+    Program,
+    /// A body on its own lines, as in `def foo ... end`.
+    Lines,
+    /// A body in braces which stays on one line if it fits, as in
+    /// `foo { |x| x }`.
+    Braces,
+}
+
+/// Part of the trivia before a token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Trivia<'a>
+{
+    Newline,
+    /// A `#` comment, without its new line.
+    Comment(&'a str),
+    /// An `=begin` comment, from `=begin` to `=end`.
+    BlockComment(&'a str),
+    /// The `__END__` line and the data after it.
+    Data(&'a str),
+}
+
+/// Turns a syntax tree into a document.
+#[derive(Debug)]
+struct Converter
+{
+    /// The documents of every open group or indented body, outermost first.
+    stack: Vec<Vec<Doc>>,
+    /// Whether the last thing written lets the next token follow it without
+    /// a space.
+    glue: bool,
+    /// The number of new lines since the last thing written.
+    pending_newlines: usize,
+    /// Whether we are between the statements of a body, rather than inside
+    /// a statement.
+    statement_level: bool,
+    /// The style of the innermost body.
+    style: Style,
+    /// The token whose trivia has already been written, ahead of the token.
+    written_trivia: Option<*const SyntaxToken>,
+    /// The `__END__` section.
+    data: Option<String>,
+}
+
+impl Formatter
+{
+    pub fn new() -> Self {
+        Formatter { width: DEFAULT_WIDTH }
+    }
+
+    /// Sets the width lines are kept within where possible.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Formats source code.
+    ///
+    /// Fails if the source does not parse, or if the formatted code would
+    /// not mean the same thing as the original.
+    pub fn format(&self, source: &str) -> Result<String, Error> {
+        let tree = Parser::from(source).parse_lossless()?;
+        self.format_tree(&tree)
+    }
+
+    /// Formats a syntax tree.
+    ///
+    /// Fails if the formatted code would not mean the same thing as the
+    /// tree.
+    pub fn format_tree(&self, tree: &SyntaxTree) -> Result<String, Error> {
+        let formatted = self.layout(tree);
+
+        match Parser::from(&formatted[..]).parse() {
+            Ok(ref program) if same_code(program, &tree.program) => Ok(formatted),
+            _ => Err(ErrorKind::FormattingChangedMeaning.into()),
+        }
+    }
+
+    fn layout(&self, tree: &SyntaxTree) -> String {
+        let mut converter = Converter {
+            stack: vec![Vec::new()],
+            glue: true,
+            pending_newlines: 0,
+            statement_level: true,
+            style: Style::Program,
+            written_trivia: None,
+            data: None,
+        };
+        converter.body(&tree.root);
+
+        let mut docs = converter.stack.pop().unwrap();
+        if !docs.is_empty() {
+            docs.push(Doc::HardLine);
+        }
+        if let Some(data) = converter.data {
+            docs.push(Doc::Raw(data));
+        }
+
+        Renderer::new(self.width).render(&docs)
+    }
+}
+
+impl Default for Formatter
+{
+    fn default() -> Self { Formatter::new() }
+}
+
+impl Converter
+{
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind {
+            SyntaxKind::Program | SyntaxKind::Class | SyntaxKind::Module | SyntaxKind::Function |
+                SyntaxKind::BeginBlock | SyntaxKind::EndBlock | SyntaxKind::Block |
+                SyntaxKind::For | SyntaxKind::Begin | SyntaxKind::Rescue => self.body(node),
+            SyntaxKind::Arguments => self.list(node, true),
+            SyntaxKind::Parameters => self.list(node, false),
+            _ => {
+                for (index, child) in node.children.iter().enumerate() {
+                    self.element(node, index, child);
+                }
+            },
+        }
+    }
+
+    fn element(&mut self, parent: &SyntaxNode, index: usize, element: &SyntaxElement) {
+        match *element {
+            SyntaxElement::Node(ref node) => self.node(node),
+            // New lines within an expression are only there for layout.
+            SyntaxElement::Token(ref token) if token.token.is_terminator() => {
+                self.trivia(token);
+                self.pending_newlines += 1;
+            },
+            SyntaxElement::Token(ref token) => {
+                let (glue_before, glue_after) = spacing(parent, index);
+                self.token(token, glue_before, glue_after);
+            },
+        }
+    }
+
+    /// Lays out something with a header followed by a body of statements,
+    /// such as a class, or a block with its parameters.
+    fn body(&mut self, node: &SyntaxNode) {
+        let style = match node.kind {
+            SyntaxKind::Program => Style::Program,
+            SyntaxKind::BeginBlock | SyntaxKind::EndBlock => Style::Braces,
+            SyntaxKind::Block if first_token(node).map(|token| token.token == Token::left_brace()) == Some(true) => Style::Braces,
+            _ => Style::Lines,
+        };
+        let closer = match node.children.last() {
+            Some(SyntaxElement::Token(token)) if style != Style::Program &&
+                (token.token == Token::end() || token.token == Token::right_brace()) => Some(node.children.len() - 1),
+            _ => None,
+        };
+
+        let saved = (self.statement_level, self.style);
+        self.style = style;
+
+        if style == Style::Braces {
+            self.stack.push(Vec::new());
+        }
+
+        // The program has no header, and is not indented.
+        let mut in_body = style == Style::Program;
+        // Whether the header is finished, even if a clause has started since.
+        let mut started = in_body;
+        let mut indented = false;
+        let mut pipes = 0;
+        self.statement_level = in_body;
+
+        for (index, child) in node.children.iter().enumerate() {
+            started |= in_body;
+
+            match *child {
+                SyntaxElement::Token(ref token) if Some(index) == closer => {
+                    self.statement_level = in_body;
+                    self.trivia_ahead(token);
+                    self.close_body(&mut indented, pipes > 0);
+                    self.token(token, false, false);
+                },
+                SyntaxElement::Token(ref token) if token.token.is_terminator() => {
+                    self.trivia(token);
+                    if !in_body {
+                        self.open_body(&mut in_body, &mut indented);
+                    }
+                    self.pending_newlines += 1;
+                },
+                SyntaxElement::Token(ref token) if started &&
+                    (token.token == Token::Keyword(Keyword::Else) || token.token == Token::Keyword(Keyword::Ensure)) => {
+                    self.trivia_ahead(token);
+                    self.close_clause(&mut in_body, &mut indented);
+                    self.token(token, false, false);
+                },
+                SyntaxElement::Node(ref rescue) if rescue.kind == SyntaxKind::Rescue => {
+                    self.close_clause(&mut in_body, &mut indented);
+                    self.node(rescue);
+                    self.statement_level = false;
+                },
+                SyntaxElement::Node(ref statement) if is_statement(statement.kind) => {
+                    if !in_body {
+                        self.open_body(&mut in_body, &mut indented);
+                    }
+
+                    self.statement_break();
+                    self.statement_level = false;
+                    self.node(statement);
+                    self.statement_level = true;
+                },
+                // Block parameters, as in `{ |a, b| ... }`.
+                SyntaxElement::Token(ref token) if !in_body && node.kind == SyntaxKind::Block && token.token == Token::pipe() => {
+                    pipes += 1;
+                    self.token(token, pipes > 1, pipes == 1);
+                },
+                ref element => self.element(node, index, element),
+            }
+        }
+
+        // A `rescue` clause ends where the next clause starts.
+        if indented {
+            self.close_indent();
+        }
+
+        if style == Style::Braces {
+            let group = self.stack.pop().unwrap();
+            self.push(Doc::Group(group));
+        }
+
+        self.statement_level = saved.0;
+        self.style = saved.1;
+    }
+
+    /// Starts the indented body after a header.
+    fn open_body(&mut self, in_body: &mut bool, indented: &mut bool) {
+        *in_body = true;
+        self.statement_level = true;
+        self.pending_newlines = 0;
+
+        if self.style != Style::Program {
+            self.stack.push(Vec::new());
+            *indented = true;
+        }
+    }
+
+    /// Finishes a body, ready for its closing `end` or `}`.
+    fn close_body(&mut self, indented: &mut bool, has_parameters: bool) {
+        let empty = !*indented || self.stack.last().unwrap().is_empty();
+        if *indented {
+            self.close_indent();
+            *indented = false;
+        }
+
+        match self.style {
+            Style::Braces if empty && !has_parameters => self.glue = true,
+            Style::Braces => self.line(Doc::Line),
+            Style::Lines | Style::Program => self.line(Doc::HardLine),
+        }
+        self.statement_level = false;
+    }
+
+    /// Finishes a body before a clause such as `rescue` or `else`, which
+    /// has its own header.
+    fn close_clause(&mut self, in_body: &mut bool, indented: &mut bool) {
+        if *indented {
+            self.close_indent();
+            *indented = false;
+        }
+
+        self.line(Doc::HardLine);
+        *in_body = false;
+        self.statement_level = false;
+    }
+
+    fn close_indent(&mut self) {
+        let body = self.stack.pop().unwrap();
+        if !body.is_empty() {
+            self.push(Doc::Indent(body));
+        }
+        self.pending_newlines = 0;
+    }
+
+    /// Separates a statement from whatever came before it in its body,
+    /// keeping a blank line if there was at least one.
+    fn statement_break(&mut self) {
+        let first = self.stack.last().unwrap().is_empty();
+
+        match (first, self.style) {
+            (true, Style::Program) => (),
+            (true, Style::Braces) => self.line(Doc::Line),
+            (true, Style::Lines) => self.line(Doc::HardLine),
+            (false, _) if self.pending_newlines > 1 => self.line(Doc::BlankLine),
+            (false, _) => self.line(Doc::HardLine),
+        }
+        self.pending_newlines = 0;
+    }
+
+    /// Lays out a parenthesized argument or parameter list, or the
+    /// arguments of a call without parentheses, so that it is put one per
+    /// line if it does not fit.
+    fn list(&mut self, node: &SyntaxNode, is_arguments: bool) {
+        let parenthesized = first_child_is(node, &Token::left_paren());
+        let (open, close) = if parenthesized {
+            (Some(0), Some(node.children.len() - 1))
+        } else {
+            (None, None)
+        };
+
+        // Split the items at the commas, leaving out a trailing comma.
+        let mut items: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut commas = Vec::new();
+        for index in 0..node.children.len() {
+            if Some(index) == open || Some(index) == close {
+                continue;
+            }
+
+            match node.children[index] {
+                SyntaxElement::Token(ref token) if token.token == Token::comma() => {
+                    commas.push(index);
+                    items.push(Vec::new());
+                },
+                _ => items.last_mut().unwrap().push(index),
+            }
+        }
+        if items.last().unwrap().is_empty() {
+            items.pop();
+        }
+
+        self.stack.push(Vec::new());
+        if let Some(open) = open {
+            self.element(node, open, &node.children[open]);
+            self.stack.push(Vec::new());
+        }
+
+        for (number, item) in items.iter().enumerate() {
+            if number > 0 {
+                let comma = commas[number - 1];
+                self.element(node, comma, &node.children[comma]);
+
+                // Comments after the comma stay with the item before it.
+                if let Some(token) = item.first().and_then(|&index| first_token_of(&node.children[index])) {
+                    self.trivia_ahead(token);
+                }
+                self.line(Doc::Line);
+            } else if parenthesized {
+                self.line(Doc::SoftLine);
+            } else {
+                // The rest of the arguments are indented if they are broken.
+                self.stack.push(Vec::new());
+            }
+
+            for &index in item.iter() {
+                self.element(node, index, &node.children[index]);
+            }
+        }
+
+        // A trailing comma only has comments to keep.
+        if let Some(&comma) = commas.get(items.len().saturating_sub(1)) {
+            if let SyntaxElement::Token(ref token) = node.children[comma] {
+                self.trivia(token);
+            }
+        }
+
+        if let Some(close) = close {
+            if let SyntaxElement::Token(ref token) = node.children[close] {
+                self.trivia_ahead(token);
+            }
+
+            if !items.is_empty() && is_arguments {
+                self.push(Doc::IfBreak(","));
+            }
+            self.close_indent();
+            if !items.is_empty() {
+                self.line(Doc::SoftLine);
+            }
+            self.element(node, close, &node.children[close]);
+        } else if !items.is_empty() {
+            self.close_indent();
+        }
+
+        let group = self.stack.pop().unwrap();
+        self.push(Doc::Group(group));
+    }
+
+    /// Writes a token along with the comments before it.
+    fn token(&mut self, token: &SyntaxToken, glue_before: bool, glue_after: bool) {
+        self.trivia(token);
+
+        if token.text.is_empty() {
+            return;
+        }
+
+        let text = match token.token {
+            Token::String(ref value) if token.text.starts_with('"') && !value.contains(['\\', '#']) => {
+                format!("'{}'", value)
+            },
+            _ => token.text.clone(),
+        };
+
+        if !self.glue && !glue_before {
+            self.push(Doc::Text(" ".to_owned()));
+        }
+        self.push(Doc::Text(text));
+        self.glue = glue_after;
+        self.pending_newlines = 0;
+    }
+
+    /// Writes the comments in the trivia before a token.
+    fn trivia(&mut self, token: &SyntaxToken) {
+        if self.written_trivia.take() == Some(token as *const _) {
+            return;
+        }
+
+        for trivia in split_trivia(&token.leading_trivia) {
+            match trivia {
+                Trivia::Newline => self.pending_newlines += 1,
+                Trivia::Comment(text) => {
+                    let own_line = self.pending_newlines > 0 ||
+                        (self.statement_level && self.stack.last().unwrap().is_empty());
+                    self.comment(Doc::Text(text.to_owned()), own_line);
+                },
+                Trivia::BlockComment(text) => self.comment(Doc::Raw(text.to_owned()), true),
+                Trivia::Data(text) => self.data = Some(text.to_owned()),
+            }
+        }
+    }
+
+    /// Writes the comments before a token ahead of the token itself, so
+    /// that they come before a line break or the end of a body.
+    fn trivia_ahead(&mut self, token: &SyntaxToken) {
+        self.trivia(token);
+        self.written_trivia = Some(token as *const _);
+    }
+
+    fn comment(&mut self, comment: Doc, own_line: bool) {
+        if !own_line {
+            if let Doc::Text(text) = comment {
+                self.push(Doc::LineSuffix(format!(" {}", text)));
+            }
+        } else if self.statement_level {
+            self.statement_break();
+            self.push(comment);
+        } else {
+            self.line(Doc::HardLine);
+            self.push(comment);
+            self.line(Doc::HardLine);
+        }
+    }
+
+    /// Writes a possible line break, which nothing needs a space after.
+    fn line(&mut self, line: Doc) {
+        self.push(line);
+        self.glue = true;
+    }
+
+    fn push(&mut self, doc: Doc) {
+        self.stack.last_mut().unwrap().push(doc);
+    }
+}
+
+/// Works out whether a token is written without a space before it, and
+/// whether the next is written without a space after it.
+fn spacing(parent: &SyntaxNode, index: usize) -> (bool, bool) {
+    let token = match parent.children[index] {
+        SyntaxElement::Token(ref token) => &token.token,
+        SyntaxElement::Node(..) => return (false, false),
+    };
+    let first = index == 0;
+    let next_is_node = matches!(parent.children.get(index + 1), Some(&SyntaxElement::Node(..)));
+
+    match *token {
+        Token::Symbol(")") | Token::Symbol("]") | Token::Symbol(",") | Token::Symbol(";") => (true, false),
+        Token::Symbol("(") => {
+            let follows_name = match parent.kind {
+                SyntaxKind::Arguments | SyntaxKind::Parameters => first,
+                SyntaxKind::Defined => true,
+                _ => false,
+            };
+            (follows_name, true)
+        },
+        Token::Symbol("[") => (parent.kind == SyntaxKind::Index, true),
+        Token::Symbol(".") | Token::Symbol("&.") => (true, true),
+        // `::Foo` starts from the top level.
+        Token::Symbol("::") => (!first, true),
+        Token::Symbol("-") if parent.kind == SyntaxKind::Negate => (false, true),
+        // A splat, unless it stands alone as in `a, * = b`.
+        Token::Symbol("*") if parent.kind != SyntaxKind::Binary => (false, next_is_node),
+        // A symbol, as in `:foo` or `:foo => 1`.
+        Token::Symbol(":") if parent.kind != SyntaxKind::Ternary => (false, true),
+        _ => (false, false),
+    }
+}
+
+/// Checks if a node is one of the statements in a body.
+fn is_statement(kind: SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::Statement | SyntaxKind::Function | SyntaxKind::Class | SyntaxKind::Module |
+             SyntaxKind::BeginBlock | SyntaxKind::EndBlock)
+}
+
+fn first_child_is(node: &SyntaxNode, token: &Token) -> bool {
+    match node.children.first() {
+        Some(SyntaxElement::Token(first)) => first.token == *token,
+        _ => false,
+    }
+}
+
+fn first_token(node: &SyntaxNode) -> Option<&SyntaxToken> {
+    node.children.iter().filter_map(first_token_of).next()
+}
+
+fn first_token_of(element: &SyntaxElement) -> Option<&SyntaxToken> {
+    match *element {
+        SyntaxElement::Node(ref node) => first_token(node),
+        SyntaxElement::Token(ref token) => Some(token),
+    }
+}
+
+/// Splits trivia into new lines and comments, dropping other whitespace and
+/// line continuations.
+fn split_trivia<'a>(text: &'a str) -> Vec<Trivia<'a>> {
+    let mut trivia = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let at_line_start = text.len() == rest.len() || text[..text.len() - rest.len()].ends_with('\n');
+
+        if at_line_start && rest.starts_with("__END__") {
+            trivia.push(Trivia::Data(rest));
+            break;
+        } else if at_line_start && rest.starts_with("=begin") {
+            let end = rest.find("\n=end").map(|end| end + "\n=end".len()).unwrap_or(rest.len());
+            let end = end + rest[end..].find('\n').unwrap_or(rest.len() - end);
+            trivia.push(Trivia::BlockComment(&rest[..end]));
+            rest = &rest[end..];
+        } else if c == '#' {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            trivia.push(Trivia::Comment(rest[..end].trim_end()));
+            rest = &rest[end..];
+        } else if c == '\\' {
+            // Line continuations are joined.
+            rest = rest[1..].trim_start_matches('\r');
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+        } else {
+            if c == '\n' {
+                trivia.push(Trivia::Newline);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    trivia
+}
+
+/// Checks if two programs have the same code, ignoring where their
+/// comments are.
+fn same_code(a: &ast::Program, b: &ast::Program) -> bool {
+    a.items == b.items && a.data == b.data && a.pragmas == b.pragmas
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn format(source: &str) -> String {
+        format_with_width(source, DEFAULT_WIDTH)
+    }
+
+    fn format_with_width(source: &str, width: usize) -> String {
+        let formatter = Formatter::new().with_width(width);
+        let formatted = formatter.format(source).expect("failed to format");
+
+        assert_eq!(formatter.format(&formatted).unwrap(), formatted, "formatting is not idempotent");
+        formatted
+    }
+
+    #[test]
+    fn normalizes_layout() {
+        assert_eq!(format("class Foo<Bar;def  baz(a,b=1)\n a+b*-c\n   end;end"),
+                   "class Foo < Bar\n  def baz(a, b = 1)\n    a + b * -c\n  end\nend\n");
+        assert_eq!(format("x=foo.bar( 1,:a=>2 ,b:3, )\ny = c [0]\n"),
+                   "x = foo.bar(1, :a => 2, b: 3)\ny = c[0]\n");
+        assert_eq!(format("each do |a,(b,*c)| d end\nmap {|x|x}\ntap { }\n"),
+                   "each do |a, (b, *c)|\n  d\nend\nmap { |x| x }\ntap {}\n");
+        assert_eq!(format("begin\na\nrescue  Foo=>e\nb\nensure\nc\nend\n"),
+                   "begin\n  a\nrescue Foo => e\n  b\nensure\n  c\nend\n");
+    }
+
+    #[test]
+    fn prefers_single_quotes() {
+        assert_eq!(format("a(\"b\", \"c\\n\", \"#{d}\")\n"), "a('b', \"c\\n\", \"#{d}\")\n");
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_line_groups() {
+        assert_eq!(format("# frozen_string_literal: true\n\n\n\nclass Foo # a class\n\n  # Does a thing.\n  def bar\n    a # first\n\n\n    b\n\n  end\nend\n=begin\ndocs\n=end\n__END__\n  data\n"),
+                   "# frozen_string_literal: true\n\nclass Foo # a class\n  # Does a thing.\n  def bar\n    a # first\n\n    b\n  end\nend\n=begin\ndocs\n=end\n__END__\n  data\n");
+        assert_eq!(format("foo(a, # first\n    # the second\n    b)\n"),
+                   "foo(\n  a, # first\n  # the second\n  b,\n)\n");
+    }
+
+    #[test]
+    fn breaks_lists_which_do_not_fit() {
+        assert_eq!(format_with_width("foo(first_argument, second_argument)\n", 30),
+                   "foo(\n  first_argument,\n  second_argument,\n)\n");
+        assert_eq!(format_with_width("puts first_argument, second_argument\n", 30),
+                   "puts first_argument,\n  second_argument\n");
+        assert_eq!(format_with_width("foo(first_argument, bar(a, b, c))\n", 30),
+                   "foo(\n  first_argument,\n  bar(a, b, c),\n)\n");
+    }
+}
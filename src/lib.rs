@@ -3,7 +3,16 @@ pub use self::errors::{Error, ErrorKind};
 pub mod errors;
 
 pub mod ast;
+pub mod cst;
+pub mod diagnostic;
+pub mod format;
 pub mod parse;
+pub mod span;
+pub mod symbol;
 
 #[macro_use]
 extern crate error_chain;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
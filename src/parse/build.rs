@@ -0,0 +1,494 @@
+//! Building trees from what the parser reads.
+//!
+//! The parser does not build any particular tree itself. It calls a `Build`
+//! as it finishes each node, children first and in source order. The boxed
+//! `ast::Program` is built by `AstBuilder`, and `ast::arena::Arena` builds
+//! its nodes in place, so parsing into an arena never builds the boxed tree.
+
+use ast::{self, AliasKind, BinaryOp, PathSeparator, Visibility};
+use cst::SyntaxKind;
+use span::Span;
+use symbol::Symbol;
+
+/// Something the parser builds a tree in.
+pub trait Build
+{
+    type Program;
+    type Item;
+    type Stmt;
+    type Expr;
+    /// Something that can be assigned to.
+    type Target;
+    type ConstantPath;
+    type Block;
+    type RescueClause;
+    type Parameter;
+    /// How much has been built.
+    type Mark: Copy;
+
+    /// Gets how much has been built, so that anything built after can be
+    /// thrown away.
+    fn mark(&self) -> Self::Mark;
+    /// Throws away everything built since a mark, such as the parts of a
+    /// statement which failed to parse.
+    fn discard(&mut self, mark: Self::Mark);
+
+    fn program(&mut self, items: Vec<Self::Item>, data: Option<&str>, pragmas: &[ast::Pragma]) -> Self::Program;
+    fn module(&mut self, name: Self::ConstantPath, items: Vec<Self::Item>) -> Self::Item;
+    fn class(&mut self, name: Self::ConstantPath, superclass: Option<Self::Expr>, items: Vec<Self::Item>) -> Self::Item;
+    fn function(&mut self, name: Symbol, singleton: bool, visibility: Visibility, parameters: Vec<Self::Parameter>,
+                statements: Vec<Self::Stmt>) -> Self::Item;
+    fn parameter(&mut self, name: Symbol, default: Option<Self::Expr>) -> Self::Parameter;
+    fn begin_block(&mut self, statements: Vec<Self::Stmt>) -> Self::Item;
+    fn end_block(&mut self, statements: Vec<Self::Stmt>) -> Self::Item;
+    fn stmt_item(&mut self, stmt: Self::Stmt) -> Self::Item;
+
+    /// Checks if an item is a call to a visibility modifier such as `private`.
+    fn visibility_modifier(&self, item: &Self::Item) -> Option<VisibilityModifier>;
+    /// Applies a visibility modifier to an item before it, if it is one of
+    /// the functions the modifier names.
+    fn apply_visibility_modifier(&mut self, item: &mut Self::Item, modifier: &VisibilityModifier);
+
+    fn expr_stmt(&mut self, expr: Self::Expr) -> Self::Stmt;
+    fn alias(&mut self, kind: AliasKind, new_name: Symbol, old_name: Symbol) -> Self::Stmt;
+    fn undef(&mut self, names: Vec<Symbol>) -> Self::Stmt;
+
+    /// Turns an expression on the left of an `=` into what it assigns to,
+    /// giving it back if it cannot be assigned to.
+    ///
+    /// This is only given the expression built last.
+    fn target(&mut self, expr: Self::Expr) -> Result<Self::Target, Self::Expr>;
+    fn splat_target(&mut self, target: Option<Self::Target>) -> Self::Target;
+    fn group_target(&mut self, targets: Vec<Self::Target>) -> Self::Target;
+
+    fn assignment(&mut self, target: Self::Target, value: Self::Expr) -> Self::Expr;
+    fn multiple_assignment(&mut self, targets: Vec<Self::Target>, values: Vec<Self::Expr>) -> Self::Expr;
+    fn ternary(&mut self, condition: Self::Expr, if_true: Self::Expr, if_false: Self::Expr) -> Self::Expr;
+    fn binary(&mut self, operator: BinaryOp, lhs: Self::Expr, rhs: Self::Expr) -> Self::Expr;
+    fn index(&mut self, receiver: Self::Expr, arguments: Vec<Self::Expr>) -> Self::Expr;
+
+    /// Builds the path of constants after `::` or at the start of a `class`
+    /// or `module`, looked up at the top level or lexically.
+    fn constant_path(&mut self, top_level: bool, parts: Vec<ast::Constant>) -> Self::ConstantPath;
+    fn constant(&mut self, path: Self::ConstantPath) -> Self::Expr;
+    /// Looks up a constant in the value of an expression, `a(b)::C`.
+    fn scoped_constant(&mut self, scope: Self::Expr, name: ast::Constant) -> Self::Expr;
+
+    fn call(&mut self, callee: ast::Path, arguments: Vec<Self::Expr>, block: Option<Self::Block>) -> Self::Expr;
+    fn block(&mut self, parameters: Vec<Self::Target>, statements: Vec<Self::Stmt>) -> Self::Block;
+    fn for_loop(&mut self, targets: Vec<Self::Target>, iterable: Self::Expr, statements: Vec<Self::Stmt>) -> Self::Expr;
+    fn begin(&mut self, statements: Vec<Self::Stmt>, rescue_clauses: Vec<Self::RescueClause>,
+             else_statements: Option<Vec<Self::Stmt>>, ensure_statements: Option<Vec<Self::Stmt>>) -> Self::Expr;
+    fn rescue_clause(&mut self, exceptions: Vec<Self::Expr>, target: Option<Self::Target>,
+                     statements: Vec<Self::Stmt>) -> Self::RescueClause;
+
+    fn string(&mut self, value: String) -> Self::Expr;
+    fn integer(&mut self, value: i64) -> Self::Expr;
+    fn character(&mut self, value: char) -> Self::Expr;
+    fn symbol(&mut self, name: Symbol) -> Self::Expr;
+    fn key_value(&mut self, key: Symbol, value: Self::Expr) -> Self::Expr;
+    fn paren(&mut self, inner: Self::Expr) -> Self::Expr;
+    fn negate(&mut self, inner: Self::Expr) -> Self::Expr;
+    fn defined(&mut self, inner: Self::Expr) -> Self::Expr;
+    fn super_call(&mut self, arguments: Vec<Self::Expr>) -> Self::Expr;
+    /// Builds a `super` without arguments, which passes on the arguments of
+    /// the method it is in.
+    fn zsuper(&mut self) -> Self::Expr;
+    fn yield_expr(&mut self, arguments: Vec<Self::Expr>) -> Self::Expr;
+    fn return_expr(&mut self, arguments: Vec<Self::Expr>) -> Self::Expr;
+    /// Builds the placeholder for something which could not be parsed.
+    fn error(&mut self) -> Self::Expr;
+    /// Builds the placeholder for an expression which is missing.
+    fn missing(&mut self) -> Self::Expr;
+
+    /// Records where an item is in the source, once it has been parsed.
+    fn locate_item(&mut self, item: &mut Self::Item, span: Span);
+    /// Records where a statement is in the source.
+    fn locate_stmt(&mut self, stmt: &mut Self::Stmt, span: Span);
+    /// Records where an expression is in the source.
+    fn locate_expr(&mut self, expr: &mut Self::Expr, span: Span);
+
+    /// Describes an expression for an error message, as in "a method call".
+    fn describe(&self, expr: &Self::Expr) -> &'static str;
+    /// Gets the kind of syntax node an expression is parsed from.
+    fn syntax_kind(&self, expr: &Self::Expr) -> SyntaxKind;
+}
+
+/// A call to a visibility modifier such as `private`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisibilityModifier
+{
+    /// Whether it applies to singleton functions, as `private_class_method`
+    /// does.
+    pub singleton: bool,
+    pub visibility: Visibility,
+    /// The functions it names, or nothing if it has no arguments and so
+    /// changes the visibility of the functions after it.
+    pub names: Option<Vec<Symbol>>,
+}
+
+/// Builds the boxed AST.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AstBuilder;
+
+impl Build for AstBuilder
+{
+    type Program = ast::Program;
+    type Item = ast::Item;
+    type Stmt = ast::Stmt;
+    type Expr = ast::Expr;
+    type Target = ast::AssignTarget;
+    type ConstantPath = ast::ConstantPath;
+    type Block = ast::Block;
+    type RescueClause = ast::RescueClause;
+    type Parameter = ast::Parameter;
+    // Nothing needs throwing away, as dropping the AST frees it.
+    type Mark = ();
+
+    fn mark(&self) {}
+    fn discard(&mut self, _: ()) {}
+
+    fn program(&mut self, items: Vec<ast::Item>, data: Option<&str>, pragmas: &[ast::Pragma]) -> ast::Program {
+        ast::Program {
+            items,
+            data: data.map(ToOwned::to_owned),
+            pragmas: pragmas.to_owned(),
+            comments: Vec::new(),
+        }
+    }
+
+    fn module(&mut self, name: ast::ConstantPath, items: Vec<ast::Item>) -> ast::Item {
+        ast::Module { name, items, span: Default::default() }.into()
+    }
+
+    fn class(&mut self, name: ast::ConstantPath, superclass: Option<ast::Expr>, items: Vec<ast::Item>) -> ast::Item {
+        ast::Class { name, items, superclass, span: Default::default() }.into()
+    }
+
+    fn function(&mut self, name: Symbol, singleton: bool, visibility: Visibility, parameters: Vec<ast::Parameter>,
+                statements: Vec<ast::Stmt>) -> ast::Item {
+        ast::Function { name, singleton, visibility, parameters, statements, span: Default::default() }.into()
+    }
+
+    fn parameter(&mut self, name: Symbol, default: Option<ast::Expr>) -> ast::Parameter {
+        ast::Parameter { name, default: default.map(Box::new) }
+    }
+
+    fn begin_block(&mut self, statements: Vec<ast::Stmt>) -> ast::Item {
+        ast::BeginBlock { statements, span: Default::default() }.into()
+    }
+
+    fn end_block(&mut self, statements: Vec<ast::Stmt>) -> ast::Item {
+        ast::EndBlock { statements, span: Default::default() }.into()
+    }
+
+    fn stmt_item(&mut self, stmt: ast::Stmt) -> ast::Item { stmt.into() }
+
+    fn visibility_modifier(&self, item: &ast::Item) -> Option<VisibilityModifier> {
+        let call = match *item {
+            ast::Item::Stmt(ast::Stmt::Expr(ast::Expr::Call(ref call))) => call,
+            _ => return None,
+        };
+        let (singleton, visibility) = call.visibility_modifier()?;
+
+        let names = if call.arguments.is_empty() {
+            None
+        } else {
+            Some(call.arguments.iter().filter_map(|argument| match *argument {
+                ast::Argument::Positional(ast::Expr::Symbol(ref symbol)) => Some(symbol.name),
+                ast::Argument::Positional(ast::Expr::StringLiteral(ref s)) => Some(Symbol::intern(&s.value)),
+                _ => None,
+            }).collect())
+        };
+
+        Some(VisibilityModifier { singleton, visibility, names })
+    }
+
+    fn apply_visibility_modifier(&mut self, item: &mut ast::Item, modifier: &VisibilityModifier) {
+        if let ast::Item::Function(ref mut function) = *item {
+            if function.singleton == modifier.singleton && modifier.names(function.name) {
+                function.visibility = modifier.visibility;
+            }
+        }
+    }
+
+    fn expr_stmt(&mut self, expr: ast::Expr) -> ast::Stmt { ast::Stmt::Expr(expr) }
+
+    fn alias(&mut self, kind: AliasKind, new_name: Symbol, old_name: Symbol) -> ast::Stmt {
+        ast::Stmt::Alias(ast::Alias { kind, new_name, old_name, span: Default::default() })
+    }
+
+    fn undef(&mut self, names: Vec<Symbol>) -> ast::Stmt {
+        ast::Stmt::Undef(ast::Undef { names, span: Default::default() })
+    }
+
+    fn target(&mut self, expr: ast::Expr) -> Result<ast::AssignTarget, ast::Expr> {
+        let call = match expr {
+            ast::Expr::Call(call) => call,
+            ast::Expr::Index(index) => return Ok(ast::AssignTarget::Index(index)),
+            ast::Expr::Constant(constant) => return Ok(ast::AssignTarget::Constant(constant.path)),
+            expr => return Err(expr),
+        };
+
+        if !call.arguments.is_empty() || call.block.is_some() {
+            return Err(call.into());
+        }
+
+        let target = CallTarget::of(call.callee.parts.iter().map(|part| (part.kind.name(), part.separator)));
+        let mut parts = call.callee.parts;
+
+        match target {
+            CallTarget::Constant => Ok(ast::AssignTarget::Constant(ast::ConstantPath {
+                scope: ast::ConstantScope::Lexical,
+                parts: parts.into_iter().map(|part| ast::Constant(part.kind.name())).collect(),
+            })),
+            CallTarget::Variable => Ok(variable_target(parts[0].kind.name())),
+            CallTarget::Attribute => {
+                let name = parts.pop().unwrap().kind.name();
+                let receiver = ast::CallExpr {
+                    callee: parts.into_iter().collect(),
+                    arguments: Vec::new(),
+                    block: None,
+                    span: Default::default(),
+                };
+
+                Ok(ast::AssignTarget::Attribute(ast::AttributeTarget {
+                    receiver: Box::new(receiver.into()),
+                    name,
+                }))
+            },
+        }
+    }
+
+    fn splat_target(&mut self, target: Option<ast::AssignTarget>) -> ast::AssignTarget {
+        ast::AssignTarget::Splat(target.map(Box::new))
+    }
+
+    fn group_target(&mut self, targets: Vec<ast::AssignTarget>) -> ast::AssignTarget {
+        ast::AssignTarget::Group(targets)
+    }
+
+    fn assignment(&mut self, target: ast::AssignTarget, value: ast::Expr) -> ast::Expr {
+        let value = Box::new(value);
+
+        match target {
+            ast::AssignTarget::Constant(constant) => ast::ConstantAssignmentExpr { constant, value, span: Default::default() }.into(),
+            assignee => ast::AssignmentExpr { assignee, value, span: Default::default() }.into(),
+        }
+    }
+
+    fn multiple_assignment(&mut self, targets: Vec<ast::AssignTarget>, values: Vec<ast::Expr>) -> ast::Expr {
+        ast::MultipleAssignmentExpr { targets, values, span: Default::default() }.into()
+    }
+
+    fn ternary(&mut self, condition: ast::Expr, if_true: ast::Expr, if_false: ast::Expr) -> ast::Expr {
+        ast::TernaryExpr {
+            condition: Box::new(condition),
+            if_true: Box::new(if_true),
+            if_false: Box::new(if_false),
+            span: Default::default(),
+        }.into()
+    }
+
+    fn binary(&mut self, operator: BinaryOp, lhs: ast::Expr, rhs: ast::Expr) -> ast::Expr {
+        ast::BinaryExpr { operator, lhs: Box::new(lhs), rhs: Box::new(rhs), span: Default::default() }.into()
+    }
+
+    fn index(&mut self, receiver: ast::Expr, arguments: Vec<ast::Expr>) -> ast::Expr {
+        ast::IndexExpr { receiver: Box::new(receiver), arguments: positional(arguments), span: Default::default() }.into()
+    }
+
+    fn constant_path(&mut self, top_level: bool, parts: Vec<ast::Constant>) -> ast::ConstantPath {
+        let scope = if top_level { ast::ConstantScope::TopLevel } else { ast::ConstantScope::Lexical };
+        ast::ConstantPath { scope, parts }
+    }
+
+    fn constant(&mut self, path: ast::ConstantPath) -> ast::Expr {
+        ast::ConstantExpr { path, span: Default::default() }.into()
+    }
+
+    fn scoped_constant(&mut self, scope: ast::Expr, name: ast::Constant) -> ast::Expr {
+        match scope {
+            ast::Expr::Constant(mut expr) => {
+                expr.path.parts.push(name);
+                expr.into()
+            },
+            scope => ast::ConstantExpr {
+                path: ast::ConstantPath {
+                    scope: ast::ConstantScope::Expr(Box::new(scope)),
+                    parts: vec![name],
+                },
+                span: Default::default(),
+            }.into(),
+        }
+    }
+
+    fn call(&mut self, callee: ast::Path, arguments: Vec<ast::Expr>, block: Option<ast::Block>) -> ast::Expr {
+        ast::CallExpr { callee, arguments: positional(arguments), block, span: Default::default() }.into()
+    }
+
+    fn block(&mut self, parameters: Vec<ast::AssignTarget>, statements: Vec<ast::Stmt>) -> ast::Block {
+        ast::Block { parameters, statements }
+    }
+
+    fn for_loop(&mut self, targets: Vec<ast::AssignTarget>, iterable: ast::Expr, statements: Vec<ast::Stmt>) -> ast::Expr {
+        ast::ForExpr { targets, iterable: Box::new(iterable), statements, span: Default::default() }.into()
+    }
+
+    fn begin(&mut self, statements: Vec<ast::Stmt>, rescue_clauses: Vec<ast::RescueClause>,
+             else_statements: Option<Vec<ast::Stmt>>, ensure_statements: Option<Vec<ast::Stmt>>) -> ast::Expr {
+        ast::BeginExpr { statements, rescue_clauses, else_statements, ensure_statements, span: Default::default() }.into()
+    }
+
+    fn rescue_clause(&mut self, exceptions: Vec<ast::Expr>, target: Option<ast::AssignTarget>,
+                     statements: Vec<ast::Stmt>) -> ast::RescueClause {
+        ast::RescueClause { exceptions, target, statements }
+    }
+
+    fn string(&mut self, value: String) -> ast::Expr { ast::StringLiteral { value, span: Default::default() }.into() }
+    fn integer(&mut self, value: i64) -> ast::Expr { ast::IntegerLiteral { value, span: Default::default() }.into() }
+    fn character(&mut self, value: char) -> ast::Expr { ast::CharacterLiteral { value, span: Default::default() }.into() }
+    fn symbol(&mut self, name: Symbol) -> ast::Expr { ast::SymbolExpr { name, span: Default::default() }.into() }
+
+    fn key_value(&mut self, key: Symbol, value: ast::Expr) -> ast::Expr {
+        ast::KeyValueExpr { key, value: Box::new(value), span: Default::default() }.into()
+    }
+
+    fn paren(&mut self, inner: ast::Expr) -> ast::Expr { ast::ParenExpr { inner: Box::new(inner), span: Default::default() }.into() }
+    fn negate(&mut self, inner: ast::Expr) -> ast::Expr { ast::NegateExpr { inner: Box::new(inner), span: Default::default() }.into() }
+    fn defined(&mut self, inner: ast::Expr) -> ast::Expr { ast::DefinedExpr { inner: Box::new(inner), span: Default::default() }.into() }
+
+    fn super_call(&mut self, arguments: Vec<ast::Expr>) -> ast::Expr {
+        ast::SuperExpr { arguments: positional(arguments), span: Default::default() }.into()
+    }
+
+    fn zsuper(&mut self) -> ast::Expr { ast::ZSuperExpr { span: Default::default() }.into() }
+
+    fn yield_expr(&mut self, arguments: Vec<ast::Expr>) -> ast::Expr {
+        ast::YieldExpr { arguments: positional(arguments), span: Default::default() }.into()
+    }
+
+    fn return_expr(&mut self, arguments: Vec<ast::Expr>) -> ast::Expr {
+        ast::ReturnExpr { arguments: positional(arguments), span: Default::default() }.into()
+    }
+
+    fn error(&mut self) -> ast::Expr { ast::ErrorExpr { span: Default::default() }.into() }
+    fn missing(&mut self) -> ast::Expr { ast::MissingExpr { span: Default::default() }.into() }
+
+    fn locate_item(&mut self, item: &mut ast::Item, span: Span) { item.set_span(span) }
+    fn locate_stmt(&mut self, stmt: &mut ast::Stmt, span: Span) { stmt.set_span(span) }
+    fn locate_expr(&mut self, expr: &mut ast::Expr, span: Span) { expr.set_span(span) }
+
+    fn describe(&self, expr: &ast::Expr) -> &'static str {
+        match *expr {
+            ast::Expr::Assignment(..) | ast::Expr::ConstantAssignment(..) => "an assignment",
+            ast::Expr::MultipleAssignment(..) => "a multiple assignment",
+            ast::Expr::Constant(..) => "a constant",
+            ast::Expr::Paren(..) => "a parenthesized expression",
+            ast::Expr::Call(..) => "a method call",
+            ast::Expr::StringLiteral(..) => "a string literal",
+            ast::Expr::IntegerLiteral(..) => "an integer literal",
+            ast::Expr::CharacterLiteral(..) => "a character literal",
+            ast::Expr::Symbol(..) => "a symbol",
+            ast::Expr::KeyValue(..) => "a `key: value` pair",
+            ast::Expr::Negate(..) => "a negation",
+            ast::Expr::Defined(..) => "a `defined?` expression",
+            ast::Expr::Super(..) | ast::Expr::ZSuper(..) => "a `super` call",
+            ast::Expr::Yield(..) => "a `yield`",
+            ast::Expr::Return(..) => "a `return`",
+            ast::Expr::Binary(..) => "a binary operation",
+            ast::Expr::Ternary(..) => "a ternary expression",
+            ast::Expr::Index(..) => "an index expression",
+            ast::Expr::For(..) => "a `for` loop",
+            ast::Expr::Begin(..) => "a `begin` block",
+            ast::Expr::Error(..) | ast::Expr::Missing(..) => "an invalid expression",
+        }
+    }
+
+    fn syntax_kind(&self, expr: &ast::Expr) -> SyntaxKind {
+        match *expr {
+            ast::Expr::Assignment(..) | ast::Expr::ConstantAssignment(..) => SyntaxKind::Assignment,
+            ast::Expr::Constant(..) => SyntaxKind::Constant,
+            ast::Expr::Paren(..) => SyntaxKind::Paren,
+            ast::Expr::Call(..) => SyntaxKind::Call,
+            ast::Expr::StringLiteral(..) | ast::Expr::IntegerLiteral(..) |
+                ast::Expr::CharacterLiteral(..) => SyntaxKind::Literal,
+            ast::Expr::Symbol(..) => SyntaxKind::Symbol,
+            ast::Expr::KeyValue(..) => SyntaxKind::KeyValue,
+            ast::Expr::Negate(..) => SyntaxKind::Negate,
+            ast::Expr::Defined(..) => SyntaxKind::Defined,
+            ast::Expr::Super(..) | ast::Expr::ZSuper(..) => SyntaxKind::Super,
+            ast::Expr::Yield(..) => SyntaxKind::Yield,
+            ast::Expr::Return(..) => SyntaxKind::Return,
+            ast::Expr::Binary(..) => SyntaxKind::Binary,
+            ast::Expr::Ternary(..) => SyntaxKind::Ternary,
+            ast::Expr::MultipleAssignment(..) => SyntaxKind::MultipleAssignment,
+            ast::Expr::Index(..) => SyntaxKind::Index,
+            ast::Expr::For(..) => SyntaxKind::For,
+            ast::Expr::Begin(..) => SyntaxKind::Begin,
+            ast::Expr::Error(..) => SyntaxKind::Error,
+            ast::Expr::Missing(..) => SyntaxKind::Missing,
+        }
+    }
+}
+
+impl VisibilityModifier
+{
+    /// Checks if the modifier names a function.
+    pub fn names(&self, name: Symbol) -> bool {
+        self.names.as_ref().is_some_and(|names| names.contains(&name))
+    }
+}
+
+/// What a call without arguments assigns to on the left of an `=`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum CallTarget
+{
+    /// A path of constants, `A::B`.
+    Constant,
+    /// A variable, `a` or `@a`.
+    Variable,
+    /// An attribute of the call before the last part, `a.b`.
+    Attribute,
+}
+
+impl CallTarget
+{
+    /// Works out what a call assigns to from the names and separators of
+    /// the parts of its path.
+    pub(crate) fn of<I>(segments: I) -> Self
+        where I: ExactSizeIterator<Item=(Symbol, PathSeparator)> {
+        let len = segments.len();
+        let mut is_constant_path = true;
+
+        for (name, separator) in segments {
+            is_constant_path &= name.as_str().starts_with(char::is_uppercase) &&
+                separator != PathSeparator::Dot && separator != PathSeparator::SafeNavigation;
+        }
+
+        if is_constant_path {
+            CallTarget::Constant
+        } else if len == 1 {
+            CallTarget::Variable
+        } else {
+            CallTarget::Attribute
+        }
+    }
+}
+
+/// Creates the assignment target for a variable name, based on its sigil.
+pub(crate) fn variable_target(name: Symbol) -> ast::AssignTarget {
+    if let Some(name) = name.as_str().strip_prefix("@@") {
+        ast::AssignTarget::ClassVariable(ast::Identifier(Symbol::intern(name)))
+    } else if let Some(name) = name.as_str().strip_prefix('@') {
+        ast::AssignTarget::InstanceVariable(ast::Identifier(Symbol::intern(name)))
+    } else if let Some(name) = name.as_str().strip_prefix('$') {
+        ast::AssignTarget::GlobalVariable(ast::Identifier(Symbol::intern(name)))
+    } else {
+        ast::AssignTarget::Local(ast::Identifier(name))
+    }
+}
+
+fn positional(arguments: Vec<ast::Expr>) -> Vec<ast::Argument> {
+    arguments.into_iter().map(ast::Argument::Positional).collect()
+}
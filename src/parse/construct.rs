@@ -0,0 +1,57 @@
+use parse::{Token, Keyword};
+use span::Span;
+
+/// Something which has been opened and must be closed, such as a `class`,
+/// a `do` block, a parenthesis or a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenConstruct
+{
+    /// The opening text, along with the name of what is being defined, as
+    /// in `class Foo` or `(`.
+    pub description: String,
+    /// The text which closes the construct, such as `end` or `)`.
+    pub closer: &'static str,
+    /// Where the opening token is.
+    pub span: Span,
+    /// The line the construct was opened on, starting from one.
+    pub line: usize,
+    /// The indentation of that line, in characters.
+    pub indent: usize,
+}
+
+impl OpenConstruct
+{
+    /// Gets the construct opened by a token, if it opens one.
+    ///
+    /// Keywords such as `if` only open a construct at the start of an
+    /// expression, so those are left to the caller.
+    pub fn opened_by(token: &Token) -> Option<&'static str> {
+        match *token {
+            Token::Symbol("(") => Some(")"),
+            Token::Symbol("[") => Some("]"),
+            Token::Symbol("{") => Some("}"),
+            Token::Keyword(Keyword::Class) | Token::Keyword(Keyword::Module) |
+                Token::Keyword(Keyword::Def) | Token::Keyword(Keyword::Begin) |
+                Token::Keyword(Keyword::Case) | Token::Keyword(Keyword::For) |
+                Token::Keyword(Keyword::Do) | Token::Keyword(Keyword::If) |
+                Token::Keyword(Keyword::Unless) | Token::Keyword(Keyword::While) |
+                Token::Keyword(Keyword::Until) => Some("end"),
+            _ => None,
+        }
+    }
+
+    /// Checks if a token closes a construct.
+    pub fn is_closer(token: &Token) -> bool {
+        matches!(*token, Token::Symbol(")") | Token::Symbol("]") | Token::Symbol("}") |
+                 Token::Keyword(Keyword::End))
+    }
+
+    /// Checks if a token closes this construct.
+    pub fn is_closed_by(&self, token: &Token) -> bool {
+        match *token {
+            Token::Symbol(symbol) => symbol == self.closer,
+            Token::Keyword(Keyword::End) => self.closer == "end",
+            _ => false,
+        }
+    }
+}
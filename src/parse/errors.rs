@@ -1,5 +1,8 @@
-use parse;
-use ast;
+use parse::{self, Token, TokenKind, OpenConstruct};
+use diagnostic::Diagnostic;
+use span::Span;
+
+use std::path::{Path, PathBuf};
 
 error_chain! {
     foreign_links {
@@ -7,14 +10,230 @@ error_chain! {
     }
 
     errors {
-        UnexpectedToken(got: parse::Token, expected: Vec<parse::Token>) {
+        UnexpectedToken(found: parse::Token, expected: Vec<TokenKind>, span: Span) {
             description("unexpected token")
-            display("unexpected token: got '{:?}' but expected '{:?}'",
-                    got, expected)
+            display("{}", match expected.len() {
+                0 => format!("unexpected {}", describe(found)),
+                _ => format!("{}, found {}", describe_expected(expected), describe(found)),
+            })
         }
-        UnexpectedExpr(got: ast::Expr, expected: String) {
+        /// An expression which cannot be used where it is, along with a
+        /// description of it such as "a method call".
+        UnexpectedExpr(found: &'static str, expected: String, span: Span) {
             description("unexpected expression")
-            display("unexpected expression: got '{:?}' but expected {}", got, expected)
+            display("expected {}, found {}", expected, found)
+        }
+        /// Something was never closed, as shown by finding the end of the
+        /// file or the closer of an outer construct.
+        ///
+        /// If the indentation suggests where the closer was meant to go, this
+        /// has the line it should probably come before.
+        Unclosed(construct: Box<OpenConstruct>, found: parse::Token, span: Span, probable_line: Option<usize>) {
+            description("unclosed construct")
+            display("expected `{}` to close `{}` opened on line {}, found {}",
+                    construct.closer, construct.description, construct.line, describe(found))
+        }
+        /// The source contains bytes which are not valid in its encoding,
+        /// at a byte offset and line.
+        InvalidEncoding(encoding: &'static str, offset: usize, line: usize) {
+            description("invalid bytes in source")
+            display("invalid {} on line {} (byte {})", encoding, line, offset)
+        }
+        UnsupportedEncoding(name: String) {
+            description("unsupported source encoding")
+            display("unsupported source encoding '{}'", name)
+        }
+        /// Parsing a file failed, with the cause holding why.
+        File(path: PathBuf) {
+            description("failed to parse file")
+            display("failed to parse '{}'", path.display())
+        }
+    }
+}
+
+/// The code of an unexpected token error.
+pub const UNEXPECTED_TOKEN: &str = "E0001";
+/// The code of an unexpected expression error.
+pub const UNEXPECTED_EXPR: &str = "E0002";
+/// The code of an unclosed construct error.
+pub const UNCLOSED: &str = "E0003";
+/// The code of an invalid encoding error.
+pub const INVALID_ENCODING: &str = "E0004";
+/// The code of an unsupported encoding error.
+pub const UNSUPPORTED_ENCODING: &str = "E0005";
+
+impl Error
+{
+    /// Gets the stable code identifying the kind of error, if it has one.
+    pub fn code(&self) -> Option<&'static str> {
+        match *self.in_file().kind() {
+            ErrorKind::UnexpectedToken(..) => Some(UNEXPECTED_TOKEN),
+            ErrorKind::UnexpectedExpr(..) => Some(UNEXPECTED_EXPR),
+            ErrorKind::Unclosed(..) => Some(UNCLOSED),
+            ErrorKind::InvalidEncoding(..) => Some(INVALID_ENCODING),
+            ErrorKind::UnsupportedEncoding(..) => Some(UNSUPPORTED_ENCODING),
+            _ => None,
+        }
+    }
+
+    /// Gets the file the error is in, if it came from parsing a file.
+    pub fn file(&self) -> Option<&Path> {
+        match *self.kind() {
+            ErrorKind::File(ref path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Gets the error about the contents of the file, if this says which
+    /// file an error is in, or otherwise the error itself.
+    pub fn in_file(&self) -> &Error {
+        let cause = match *self.kind() {
+            ErrorKind::File(..) => self.1.next_error.as_ref().and_then(|cause| cause.downcast_ref::<Error>()),
+            _ => None,
+        };
+
+        cause.unwrap_or(self)
+    }
+
+    /// Gets where the error is in the source, if it is about the source.
+    pub fn span(&self) -> Option<Span> {
+        match *self.in_file().kind() {
+            ErrorKind::UnexpectedToken(_, _, span) | ErrorKind::UnexpectedExpr(_, _, span) |
+                ErrorKind::Unclosed(_, _, span, _) => Some(span),
+            _ => None,
+        }
+    }
+
+    /// Describes the error for showing alongside the source.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        if let ErrorKind::File(..) = *self.kind() {
+            return self.in_file().to_diagnostic();
+        }
+
+        let diagnostic = match *self.kind() {
+            ErrorKind::UnexpectedToken(ref found, ref expected, span) => {
+                let diagnostic = Diagnostic::error(self.to_string());
+
+                let diagnostic = match expected.len() {
+                    0 => diagnostic.with_primary_label(span, "unexpected"),
+                    _ => diagnostic.with_primary_label(span, describe_expected(expected)),
+                };
+
+                match (found, &expected[..]) {
+                    (&Token::EndOfFile, [closer @ TokenKind::Keyword(..)]) |
+                        (&Token::EndOfFile, [closer @ TokenKind::Symbol(..)]) => {
+                        diagnostic.with_help(format!("add the missing {}", closer))
+                    },
+                    _ => diagnostic,
+                }
+            },
+            ErrorKind::UnexpectedExpr(_, ref expected, span) => {
+                let diagnostic = Diagnostic::error(self.to_string())
+                    .with_primary_label(span, format!("expected {}", expected));
+
+                if expected == "an assignment target" {
+                    diagnostic.with_help("only variables, constants, attributes and elements can be assigned to")
+                } else {
+                    diagnostic
+                }
+            },
+            ErrorKind::Unclosed(ref construct, _, span, probable_line) => {
+                let diagnostic = Diagnostic::error(format!("unclosed `{}`", construct.description))
+                    .with_primary_label(span, format!("expected `{}`", construct.closer));
+
+                let missing = format!("missing `{}` for `{}` opened on line {}",
+                                      construct.closer, construct.description, construct.line);
+
+                match probable_line {
+                    Some(line) => diagnostic
+                        .with_secondary_label(construct.span, format!("this is probably missing its `{}`", construct.closer))
+                        .with_help(format!("{}, probably before line {} where a less indented `{}` closes an outer construct",
+                                           missing, line, construct.closer)),
+                    None => diagnostic
+                        .with_secondary_label(construct.span, "opened here")
+                        .with_help(missing),
+                }
+            },
+            ref kind => Diagnostic::error(kind.to_string()),
+        };
+
+        match self.code() {
+            Some(code) => diagnostic.with_code(code),
+            None => diagnostic,
         }
     }
 }
+
+/// Describes a token for an error message.
+fn describe(token: &Token) -> String {
+    match *token {
+        Token::EndOfLine => "newline".to_owned(),
+        Token::EndOfFile => "end of file".to_owned(),
+        Token::Unknown(c) => format!("unknown character {:?}", c),
+        _ => format!("`{}`", token),
+    }
+}
+
+/// Describes the tokens that were expected, as in "expected one of `,` or `)`".
+fn describe_expected(expected: &[TokenKind]) -> String {
+    match *expected {
+        [] => "expected nothing".to_owned(),
+        [kind] => format!("expected {}", kind),
+        [ref rest @ .., last] => {
+            let rest: Vec<_> = rest.iter().map(ToString::to_string).collect();
+            format!("expected one of {} or {}", rest.join(", "), last)
+        },
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use parse::Parser;
+    use diagnostic::Renderer;
+
+    fn render(src: &str) -> String {
+        let error = Parser::new(src.chars()).parse().unwrap_err();
+        Renderer::plain().render(&error.to_diagnostic(), "test.rb", src)
+    }
+
+    #[test]
+    fn points_at_unexpected_tokens() {
+        assert_eq!(render("foo(1\nbar\n"), "\
+error[E0001]: expected one of `,` or `)`, found newline
+ --> test.rb:1:6
+  |
+1 | foo(1
+  |      ^ expected one of `,` or `)`
+");
+    }
+
+    #[test]
+    fn points_at_invalid_assignment_targets() {
+        assert_eq!(render("foo(1) = 2\n"), "\
+error[E0002]: expected an assignment target, found a method call
+ --> test.rb:1:1
+  |
+1 | foo(1) = 2
+  | ^^^^^^ expected an assignment target
+  |
+  = help: only variables, constants, attributes and elements can be assigned to
+");
+    }
+
+    #[test]
+    fn points_at_unclosed_constructs() {
+        assert_eq!(render("class Foo\n  def bar\n    1\n  end\n"), "\
+error[E0003]: unclosed `class Foo`
+ --> test.rb:4:6
+  |
+1 | class Foo
+  | ----- opened here
+...
+4 |   end
+  |      ^ expected `end`
+  |
+  = help: missing `end` for `class Foo` opened on line 1
+");
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+
+/// A reserved word.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Keyword
+{
+    /// `__ENCODING__`
+    Encoding,
+    /// `__LINE__`
+    Line,
+    /// `__FILE__`
+    File,
+    /// `BEGIN`
+    BeginBlock,
+    /// `END`
+    EndBlock,
+    Alias,
+    And,
+    Begin,
+    Break,
+    Case,
+    Class,
+    Def,
+    /// `defined?`
+    Defined,
+    Do,
+    Else,
+    Elsif,
+    End,
+    Ensure,
+    False,
+    For,
+    If,
+    In,
+    Module,
+    Next,
+    Nil,
+    Not,
+    Or,
+    Redo,
+    Rescue,
+    Retry,
+    Return,
+    /// `self`
+    Self_,
+    Super,
+    Then,
+    True,
+    Undef,
+    Unless,
+    Until,
+    When,
+    While,
+    Yield,
+}
+
+impl Keyword
+{
+    /// Every keyword.
+    pub const ALL: &'static [Keyword] = &[
+        Keyword::Encoding, Keyword::Line, Keyword::File, Keyword::BeginBlock,
+        Keyword::EndBlock, Keyword::Alias, Keyword::And, Keyword::Begin,
+        Keyword::Break, Keyword::Case, Keyword::Class, Keyword::Def,
+        Keyword::Defined, Keyword::Do, Keyword::Else, Keyword::Elsif,
+        Keyword::End, Keyword::Ensure, Keyword::False, Keyword::For,
+        Keyword::If, Keyword::In, Keyword::Module, Keyword::Next,
+        Keyword::Nil, Keyword::Not, Keyword::Or, Keyword::Redo,
+        Keyword::Rescue, Keyword::Retry, Keyword::Return, Keyword::Self_,
+        Keyword::Super, Keyword::Then, Keyword::True, Keyword::Undef,
+        Keyword::Unless, Keyword::Until, Keyword::When, Keyword::While,
+        Keyword::Yield,
+    ];
+
+    /// Gets the keyword spelled a certain way, if any.
+    pub fn from_word(word: &str) -> Option<Keyword> {
+        Keyword::ALL.iter().cloned().find(|keyword| keyword.as_str() == word)
+    }
+
+    /// Gets the spelling of the keyword.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Keyword::Encoding => "__ENCODING__",
+            Keyword::Line => "__LINE__",
+            Keyword::File => "__FILE__",
+            Keyword::BeginBlock => "BEGIN",
+            Keyword::EndBlock => "END",
+            Keyword::Alias => "alias",
+            Keyword::And => "and",
+            Keyword::Begin => "begin",
+            Keyword::Break => "break",
+            Keyword::Case => "case",
+            Keyword::Class => "class",
+            Keyword::Def => "def",
+            Keyword::Defined => "defined?",
+            Keyword::Do => "do",
+            Keyword::Else => "else",
+            Keyword::Elsif => "elsif",
+            Keyword::End => "end",
+            Keyword::Ensure => "ensure",
+            Keyword::False => "false",
+            Keyword::For => "for",
+            Keyword::If => "if",
+            Keyword::In => "in",
+            Keyword::Module => "module",
+            Keyword::Next => "next",
+            Keyword::Nil => "nil",
+            Keyword::Not => "not",
+            Keyword::Or => "or",
+            Keyword::Redo => "redo",
+            Keyword::Rescue => "rescue",
+            Keyword::Retry => "retry",
+            Keyword::Return => "return",
+            Keyword::Self_ => "self",
+            Keyword::Super => "super",
+            Keyword::Then => "then",
+            Keyword::True => "true",
+            Keyword::Undef => "undef",
+            Keyword::Unless => "unless",
+            Keyword::Until => "until",
+            Keyword::When => "when",
+            Keyword::While => "while",
+            Keyword::Yield => "yield",
+        }
+    }
+
+    /// Checks if the keyword is a value on its own, such as `self` or `nil`,
+    /// or a control flow statement taking no operand, such as `redo`.
+    pub fn is_value(self) -> bool {
+        matches!(self, Keyword::Encoding | Keyword::Line | Keyword::File |
+                 Keyword::Self_ | Keyword::Nil | Keyword::True | Keyword::False |
+                 Keyword::Break | Keyword::Next | Keyword::Redo | Keyword::Retry)
+    }
+
+    /// Checks if the keyword must be followed by an operand.
+    pub fn expects_operand(self) -> bool {
+        matches!(self, Keyword::And | Keyword::Or | Keyword::Not | Keyword::If |
+                 Keyword::Unless | Keyword::While | Keyword::Until | Keyword::When |
+                 Keyword::In | Keyword::Then | Keyword::Else | Keyword::Elsif |
+                 Keyword::Do | Keyword::Case | Keyword::Defined)
+    }
+
+    /// Checks if the keyword can begin a method argument, as in `puts self`.
+    pub fn can_begin_argument(self) -> bool {
+        !matches!(self, Keyword::And | Keyword::Or | Keyword::If | Keyword::Unless |
+                  Keyword::While | Keyword::Until | Keyword::Rescue | Keyword::Ensure |
+                  Keyword::Do | Keyword::Then | Keyword::Else | Keyword::Elsif |
+                  Keyword::End | Keyword::In)
+    }
+}
+
+impl fmt::Display for Keyword
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt(fmt)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn keywords_round_trip_through_their_spelling() {
+        for &keyword in Keyword::ALL {
+            assert_eq!(Keyword::from_word(keyword.as_str()), Some(keyword));
+        }
+
+        assert_eq!(Keyword::from_word("puts"), None);
+    }
+}
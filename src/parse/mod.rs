@@ -1,9 +1,17 @@
 pub use self::tokenize::Tokenizer;
-pub use self::token::Token;
-pub use self::parser::Parser;
+pub use self::token::{Token, TokenKind};
+pub use self::keyword::Keyword;
+pub use self::parser::{Parser, parse_file, parse_expression, parse_statement, parse_method_body, parse_item};
+pub use self::construct::OpenConstruct;
+pub use self::source::{Source, Encoding};
 pub use self::errors::*;
+pub use self::build::{Build, AstBuilder, VisibilityModifier};
 
 pub mod tokenize;
 pub mod token;
+pub mod keyword;
 pub mod parser;
+pub mod construct;
+pub mod source;
 pub mod errors;
+pub mod build;
@@ -1,12 +1,149 @@
-use parse::{Tokenizer, Token, Error};
-use ast;
+use parse::{Tokenizer, Token, TokenKind, Keyword, OpenConstruct, Error, ErrorKind, ResultExt};
+use parse::build::{Build, AstBuilder};
+use parse::source::{self, Source};
+use cst::{self, SyntaxKind};
+use span::Span;
+use ast::{self, arena::Arena, comment::CommentableNode};
+use symbol::Symbol;
 
-use std::iter::Peekable;
+use std::{fs, io, mem, str};
+use std::path::Path;
 
 /// A parser.
-pub struct Parser<I: Iterator<Item=char>>
+///
+/// The tree is made by a `Build`, which builds the boxed AST by default.
+pub struct Parser<I: Iterator<Item=char>, B = AstBuilder>
 {
-    tokenizer: Peekable<Tokenizer<I>>,
+    tokenizer: Tokenizer<I>,
+    /// What the tree is built in.
+    builder: B,
+    /// The next token, if it has already been peeked.
+    peeked: Option<Option<cst::SyntaxToken>>,
+    /// The default visibility of new functions in each enclosing body.
+    visibility: Vec<ast::Visibility>,
+    /// Restrictions on the expression currently being parsed.
+    restrictions: Restrictions,
+    /// The concrete syntax tree, if one is being built.
+    cst: Option<cst::Builder>,
+    /// Where the last token read is in the source.
+    last_span: Span,
+    /// The line the last token read is on.
+    last_line: usize,
+    /// Where each node that comments can be attached to is, in the order
+    /// the nodes start.
+    commentable_nodes: Vec<CommentableNode>,
+    /// The kinds of token checked for since the last token was read, for
+    /// describing what was expected if none of them are found.
+    expected: Vec<TokenKind>,
+    /// The errors recovered from so far, if errors are being recovered from.
+    errors: Option<Vec<Error>>,
+    /// The brackets and `end` blocks which have been opened but not closed.
+    open: Vec<OpenConstruct>,
+    /// The first construct closed by a closer indented less than its opener,
+    /// along with the line of the closer.
+    ///
+    /// When something is missing its `end`, the `end` of an outer construct
+    /// closes it instead, so this is the most likely culprit.
+    misindented: Option<(OpenConstruct, usize)>,
+    /// Whether the last token read opened a `class`, `module` or `def`, so
+    /// that the name being defined should be added to its description.
+    naming_construct: bool,
+    /// The number of tokens read so far.
+    tokens_read: usize,
+    /// Whether the last token read can end an expression, making a following
+    /// `if` or `while` a modifier rather than the start of a block.
+    after_expression: bool,
+    /// Whether a `for`, `while` or `until` header has been read without its
+    /// optional `do`.
+    in_loop_header: bool,
+}
+
+/// The result of parsing with error recovery.
+#[derive(Debug)]
+pub struct RecoveredParse
+{
+    /// The program, with `Error` and `Missing` expressions in place of
+    /// anything that could not be parsed.
+    pub program: ast::Program,
+    /// Every error, in the order they were found.
+    pub errors: Vec<Error>,
+}
+
+/// Parses a file, giving errors which say which file they are in.
+pub fn parse_file<P>(path: P) -> Result<ast::Program, Error>
+    where P: AsRef<Path> {
+    let path = path.as_ref();
+
+    let parse = || {
+        let file = fs::File::open(path)?;
+        Parser::from_reader(file)?.parse()
+    };
+
+    parse().chain_err(|| ErrorKind::File(path.to_owned()))
+}
+
+/// Parses a single expression, such as `foo.bar(1) + 2`, which must make up
+/// the whole input.
+pub fn parse_expression<P, I>(parser: P) -> Result<ast::Expr, Error>
+    where P: Into<Parser<I>>, I: Iterator<Item=char> {
+    parser.into().parse_fragment(Parser::parse_expression)
+}
+
+/// Parses a single statement, such as `a, b = b, a`, which must make up the
+/// whole input.
+pub fn parse_statement<P, I>(parser: P) -> Result<ast::Stmt, Error>
+    where P: Into<Parser<I>>, I: Iterator<Item=char> {
+    parser.into().parse_fragment(Parser::parse_statement)
+}
+
+/// Parses the statements making up the body of a method, without its `def`
+/// line or `end`.
+pub fn parse_method_body<P, I>(parser: P) -> Result<Vec<ast::Stmt>, Error>
+    where P: Into<Parser<I>>, I: Iterator<Item=char> {
+    parser.into().parse_fragment(|parser| parser.parse_statements_until(&[]))
+}
+
+/// Parses a single item, such as a class or a function, which must make up
+/// the whole input.
+pub fn parse_item<P, I>(parser: P) -> Result<ast::Item, Error>
+    where P: Into<Parser<I>>, I: Iterator<Item=char> {
+    parser.into().parse_fragment(Parser::parse_item_kind)
+}
+
+/// Context-dependent restrictions on what an expression may contain.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Restrictions
+{
+    /// A colon ends the expression rather than starting a symbol argument,
+    /// as in the first branch of a ternary.
+    no_symbol_arguments: bool,
+    /// A `do` belongs to an enclosing construct, such as a command call or a
+    /// `for` loop, rather than starting a block.
+    no_do_block: bool,
+}
+
+impl Parser<source::IntoChars>
+{
+    /// Creates a parser for source code in any supported encoding.
+    ///
+    /// The encoding is detected from a byte order mark or an `encoding`
+    /// magic comment, defaulting to UTF-8.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Source::from_bytes(bytes).map(Parser::new)
+    }
+
+    /// Creates a parser for source code read from a reader, such as a file.
+    pub fn from_reader<R>(reader: R) -> Result<Self, Error>
+        where R: io::Read {
+        Source::from_reader(reader).map(Parser::new)
+    }
+}
+
+impl<'a> From<&'a str> for Parser<str::Chars<'a>>
+{
+    fn from(source: &'a str) -> Self {
+        Parser::new(source.chars())
+    }
 }
 
 impl<I> Parser<I>
@@ -15,104 +152,330 @@ impl<I> Parser<I>
     /// Creates a new parser.
     pub fn new<A>(input: A) -> Self
         where A: IntoIterator<IntoIter=I, Item=char> {
-        Parser { tokenizer: Tokenizer::new(input.into_iter()).peekable() }
+        Parser {
+            tokenizer: Tokenizer::new(input.into_iter()),
+            builder: AstBuilder,
+            peeked: None,
+            // Top-level functions are private methods on `Object`.
+            visibility: vec![ast::Visibility::Private],
+            restrictions: Restrictions::default(),
+            cst: None,
+            last_span: Span::default(),
+            last_line: 1,
+            commentable_nodes: Vec::new(),
+            expected: Vec::new(),
+            errors: None,
+            open: Vec::new(),
+            misindented: None,
+            naming_construct: false,
+            tokens_read: 0,
+            after_expression: false,
+            in_loop_header: false,
+        }
     }
 
     /// Parses the program.
     pub fn parse(mut self) -> Result<ast::Program, Error> {
+        self.parse_ast_program()
+    }
+
+    /// Parses the program straight into an arena, without building the
+    /// boxed AST first.
+    ///
+    /// This gives the same arena as `Arena::from_program` does for the
+    /// program `parse` gives.
+    pub fn parse_arena(self) -> Result<Arena, Error> {
+        let mut parser = self.with_builder(Arena::new());
+        parser.parse_program()?;
+
+        Ok(parser.builder.link_parents())
+    }
+
+    /// Parses the program, keeping every character of the source in a
+    /// concrete syntax tree.
+    pub fn parse_lossless(mut self) -> Result<cst::SyntaxTree, Error> {
+        self.cst = Some(cst::Builder::new());
+
+        let program = self.parse_ast_program()?;
+        let root = self.cst.take().unwrap().finish(SyntaxKind::Program);
+
+        Ok(cst::SyntaxTree { root, program })
+    }
+
+    /// Parses the program, recovering from errors so that every error in the
+    /// file is found.
+    ///
+    /// Anything that cannot be parsed is skipped up to the end of its
+    /// statement or the `end` of its enclosing block.
+    pub fn parse_with_recovery(mut self) -> RecoveredParse {
+        self.errors = Some(Vec::new());
+
+        let program = self.parse_ast_program();
+        let mut errors = self.errors.take().unwrap();
+
+        let program = match program {
+            Ok(program) => program,
+            Err(error) => {
+                errors.push(error);
+                ast::Program::new()
+            },
+        };
+
+        RecoveredParse { program, errors }
+    }
+
+    /// Parses the program, attaching its comments to the nodes they are
+    /// about.
+    fn parse_ast_program(&mut self) -> Result<ast::Program, Error> {
+        let mut program = self.parse_program()?;
+
+        program.comments = self.tokenizer.take_comments();
+        ast::comment::attach(&mut program, &self.commentable_nodes);
+
+        Ok(program)
+    }
+
+    /// Makes the parser build its tree with a different builder.
+    fn with_builder<B>(self, builder: B) -> Parser<I, B> {
+        Parser {
+            tokenizer: self.tokenizer,
+            builder,
+            peeked: self.peeked,
+            visibility: self.visibility,
+            restrictions: self.restrictions,
+            cst: self.cst,
+            last_span: self.last_span,
+            last_line: self.last_line,
+            commentable_nodes: self.commentable_nodes,
+            expected: self.expected,
+            errors: self.errors,
+            open: self.open,
+            misindented: self.misindented,
+            naming_construct: self.naming_construct,
+            tokens_read: self.tokens_read,
+            after_expression: self.after_expression,
+            in_loop_header: self.in_loop_header,
+        }
+    }
+}
+
+impl<I, B> Parser<I, B>
+    where I: Iterator<Item=char>, B: Build
+{
+    /// Parses a fragment of a program, which must make up the whole input.
+    fn parse_fragment<T, F>(mut self, parse: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error> {
+        self.eat_terminators();
+        let fragment = parse(&mut self)?;
+
+        self.expect_end_of_input()?;
+        Ok(fragment)
+    }
+
+    fn parse_program(&mut self) -> Result<B::Program, Error> {
         let mut items = Vec::new();
 
         self.eat_whitespace();
         while !self.is_eof() {
             let item = self.parse_item()?;
-            items.push(item);
+            self.push_item(&mut items, item);
 
             self.eat_whitespace();
         }
 
-        Ok(ast::Program { items: items })
+        self.expect_end_of_input()?;
+
+        Ok(self.builder.program(items, self.tokenizer.data(), self.tokenizer.pragmas()))
     }
 
-    fn parse_item(&mut self) -> Result<ast::Item, Error> {
-        let item = match &expect::word(self.peek())?[..] {
-            "class" => self.parse_class().map(ast::Item::Class),
-            "module" => self.parse_module().map(ast::Item::Module),
-            "def" => self.parse_function().map(ast::Item::Function),
-            _ => self.parse_statement().map(ast::Item::Stmt),
-        }?;
+    fn parse_item(&mut self) -> Result<B::Item, Error> {
+        let item = self.recoverable(Self::parse_item_kind, |builder, span| {
+            let mut error = builder.error();
+            builder.locate_expr(&mut error, span);
+            let stmt = builder.expr_stmt(error);
+            builder.stmt_item(stmt)
+        })?;
 
-        println!("got {:?}", item);
-        expect::terminator(self.next())?;
+        self.end_statement()?;
+
+        Ok(item)
+    }
+
+    fn parse_item_kind(&mut self) -> Result<B::Item, Error> {
+        let start = self.peek_span();
+
+        let mut item = match self.peek() {
+            Some(Token::Keyword(Keyword::Class)) => {
+                self.commentable(|parser| parser.node(SyntaxKind::Class, Self::parse_class))
+            },
+            Some(Token::Keyword(Keyword::Module)) => {
+                self.commentable(|parser| parser.node(SyntaxKind::Module, Self::parse_module))
+            },
+            Some(Token::Keyword(Keyword::Def)) => {
+                self.commentable(|parser| parser.node(SyntaxKind::Function, |parser| parser.parse_function(None)))
+            },
+            Some(Token::Keyword(Keyword::BeginBlock)) => {
+                self.node(SyntaxKind::BeginBlock, Self::parse_begin_block)
+            },
+            Some(Token::Keyword(Keyword::EndBlock)) => {
+                self.node(SyntaxKind::EndBlock, Self::parse_end_block)
+            },
+            Some(Token::Identifier(name)) if ast::Visibility::from_modifier(name.as_str()).is_some() => {
+                self.node(SyntaxKind::Statement, Self::parse_visibility_modifier)
+            },
+            // Anything closing a block here has no block to close.
+            Some(Token::Keyword(Keyword::End)) | Some(Token::Symbol(")")) |
+                Some(Token::Symbol("]")) | Some(Token::Symbol("}")) => {
+                Err(self.unexpected())
+            },
+            _ => {
+                let stmt = self.parse_statement()?;
+                Ok(self.builder.stmt_item(stmt))
+            },
+        }?;
 
+        let span = self.span_since(start);
+        self.builder.locate_item(&mut item, span);
         Ok(item)
     }
 
     /// Parses a class definition.
-    fn parse_class(&mut self) -> Result<ast::Class, Error> {
+    fn parse_class(&mut self) -> Result<B::Item, Error> {
         self.eat_assert(&Token::class());
 
-        let name = expect::word(self.next())?;
-        let mut items = Vec::new();
+        let name = self.parse_constant_path()?;
         let mut superclass = None;
 
         // Check if we have a superclass specified.
-        if self.peek().unwrap() == Token::less_than() {
+        if self.peek() == Some(Token::less_than()) {
             self.eat_assert(&Token::less_than());
 
-            superclass = Some(self.parse_path()?);
+            superclass = Some(self.parse_expression()?);
         }
 
-        expect::terminator(self.next())?;
+        self.expect_terminator()?;
 
-        self.until_end(|parser| {
-            let item = parser.parse_item()?;
-            items.push(item);
-            Ok(())
-        })?;
+        let items = self.parse_body()?;
 
-        Ok(ast::Class { name: name, items: items, superclass: superclass })
+        Ok(self.builder.class(name, superclass, items))
     }
 
     /// Parses a module definition.
-    fn parse_module(&mut self) -> Result<ast::Module, Error> {
+    fn parse_module(&mut self) -> Result<B::Item, Error> {
         self.eat_assert(&Token::module());
 
-        let name = expect::word(self.next())?;
-        let mut items = Vec::new();
+        let name = self.parse_constant_path()?;
+
+        self.expect_terminator()?;
+
+        let items = self.parse_body()?;
+
+        Ok(self.builder.module(name, items))
+    }
 
-        expect::terminator(self.next())?;
+    /// Parses the items in a class or module body, up to and including the `end`.
+    fn parse_body(&mut self) -> Result<Vec<B::Item>, Error> {
+        let mut items = Vec::new();
 
+        self.visibility.push(ast::Visibility::Public);
         self.until_end(|parser| {
             let item = parser.parse_item()?;
-            items.push(item);
+            parser.push_item(&mut items, item);
             Ok(())
         })?;
+        self.visibility.pop();
+
+        Ok(items)
+    }
+
+    /// Adds an item to a body, applying any visibility modifier it represents.
+    fn push_item(&mut self, items: &mut Vec<B::Item>, item: B::Item) {
+        if let Some(modifier) = self.builder.visibility_modifier(&item) {
+            if modifier.names.is_none() {
+                // A bare modifier changes the default for the rest of the body.
+                if !modifier.singleton {
+                    *self.visibility.last_mut().unwrap() = modifier.visibility;
+                }
+            } else {
+                // Otherwise it changes the functions it names.
+                for other in items.iter_mut() {
+                    self.builder.apply_visibility_modifier(other, &modifier);
+                }
+            }
+        }
+
+        items.push(item);
+    }
+
+    /// Parses a statement beginning with a visibility modifier such as `private`.
+    fn parse_visibility_modifier(&mut self) -> Result<B::Item, Error> {
+        let mark = self.commentable_mark();
+        let modifier = self.expect_identifier()?;
+
+        // `private def foo` only changes the function being defined.
+        if self.check(&Token::def()) {
+            // `parse_item_kind` only gets here for names which are modifiers.
+            let (_, visibility) = ast::Visibility::from_modifier(modifier.as_str())
+                .expect("parsing a visibility modifier which is not one");
+
+            return self.commentable(|parser| {
+                parser.node(SyntaxKind::Function, |parser| parser.parse_function(Some(visibility)))
+            });
+        }
 
-        Ok(ast::Module { name: name, items: items })
+        let arguments = self.parse_arguments()?;
+        let callee = ast::Path {
+            parts: vec![ast::PathSegment {
+                kind: ast::PathSegmentKind::new(modifier),
+                separator: ast::PathSeparator::Root,
+            }],
+        };
+
+        self.finish_commentable(mark);
+
+        let call = self.builder.call(callee, arguments, None);
+        let stmt = self.builder.expr_stmt(call);
+        Ok(self.builder.stmt_item(stmt))
     }
 
-    /// Parses a function.
-    fn parse_function(&mut self) -> Result<ast::Function, Error> {
+    /// Parses a function, with the visibility a modifier before it such as
+    /// `private def` gives it, if it has one.
+    fn parse_function(&mut self, modifier: Option<ast::Visibility>) -> Result<B::Item, Error> {
         self.eat_assert(&Token::def());
 
-        let name = expect::word(self.next())?;
-        let _parameters = self.parse_function_parameters()?;
-        let mut statements = Vec::new();
+        let mut singleton = false;
+
+        if self.check(&Token::self_()) {
+            self.eat_assert(&Token::self_());
+            self.expect(&Token::dot())?;
+
+            singleton = true;
+        }
+
+        let name = self.expect_method_name()?;
+
+        let visibility = if let Some(visibility) = modifier {
+            visibility
+        } else if !singleton && ast::Function::is_implicitly_private(name.as_str()) {
+            ast::Visibility::Private
+        } else if singleton {
+            ast::Visibility::Public
+        } else {
+            *self.visibility.last().unwrap()
+        };
+
+        let parameters = self.parse_function_parameters()?;
 
         // Expect new line or semicolon after parameters.
-        expect::terminator(self.next())?;
+        self.expect_terminator()?;
 
-        self.until_end(|parser| {
-            let statement = parser.parse_statement()?;
-            statements.push(statement);
-            Ok(())
-        })?;
+        let statements = self.parse_statements_until_end()?;
 
-        Ok(ast::Function { name: name, statements: statements })
+        Ok(self.builder.function(name, singleton, visibility, parameters, statements))
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<ast::Parameter>, Error> {
-        let peeked_token = expect::something(self.peek())?;
+    fn parse_function_parameters(&mut self) -> Result<Vec<B::Parameter>, Error> {
+        let peeked_token = self.peek().unwrap_or(Token::EndOfFile);
 
         // Check if there is a parameter list.
         if peeked_token == Token::left_paren() {
@@ -123,7 +486,8 @@ impl<I> Parser<I>
         }
     }
 
-    fn parse_parameter_list(&mut self) -> Result<Vec<ast::Parameter>, Error> {
+    fn parse_parameter_list(&mut self) -> Result<Vec<B::Parameter>, Error> {
+        let checkpoint = self.checkpoint();
         self.eat_assert(&Token::left_paren());
 
         let mut parameters = Vec::new();
@@ -132,337 +496,1366 @@ impl<I> Parser<I>
             let parameter = parser.parse_parameter()?;
             parameters.push(parameter);
 
-            expect::one_of(parser.peek(), &[Token::comma(), Token::right_paren()])?;
-            parser.eat_if(|token| *token == Token::comma())?;
+            if !parser.consume(&Token::comma()) && !parser.check(&Token::right_paren()) {
+                return Err(parser.unexpected());
+            }
+
             Ok(())
         })?;
 
+        self.finish_node_at(checkpoint, SyntaxKind::Parameters);
         Ok(parameters)
     }
 
-    fn parse_parameter(&mut self) -> Result<ast::Parameter, Error> {
-        let name = expect::word(self.next())?;
+    fn parse_parameter(&mut self) -> Result<B::Parameter, Error> {
+        let name = self.expect_identifier()?;
 
-        // Check if there is a default value.
-        if Token::equals() == self.peek().unwrap() {
-            self.eat_assert(&Token::equals());
-            unimplemented!();
+        let default = if self.consume(&Token::equals()) {
+            Some(self.with_restrictions(Restrictions::default(), |parser| parser.parse_argument())?)
         } else {
-            Ok(ast::Parameter { name: name, default: None })
-        }
+            None
+        };
+
+        Ok(self.builder.parameter(name, default))
     }
 
-    /// Parses a statement.
-    fn parse_statement(&mut self) -> Result<ast::Stmt, Error> {
-        let expr = self.parse_expression()?;
-        Ok(ast::Stmt::Expr(expr))
+    /// Parses a `BEGIN { ... }` block.
+    fn parse_begin_block(&mut self) -> Result<B::Item, Error> {
+        self.eat_assert(&Token::begin_block());
+
+        let statements = self.parse_brace_statements()?;
+        Ok(self.builder.begin_block(statements))
     }
 
-    fn parse_expression(&mut self) -> Result<ast::Expr, Error> {
-        self.eat_whitespace();
+    /// Parses an `END { ... }` block.
+    fn parse_end_block(&mut self) -> Result<B::Item, Error> {
+        self.eat_assert(&Token::end_block());
 
-        match expect::something(self.peek())? {
-            Token::Word(..) => {
-                let path = self.parse_path()?;
+        let statements = self.parse_brace_statements()?;
+        Ok(self.builder.end_block(statements))
+    }
 
-                match self.peek().unwrap() {
-                    // Check for assignment.
-                    Token::Symbol("=") => {
-                        self.eat_assert(&Token::equals());
+    /// Parses a list of statements surrounded by braces.
+    fn parse_brace_statements(&mut self) -> Result<Vec<B::Stmt>, Error> {
+        self.expect(&Token::left_brace())?;
+        self.parse_brace_statements_after_brace()
+    }
 
-                        let value = self.parse_expression()?;
+    /// Parses statements up to and including a closing brace.
+    fn parse_brace_statements_after_brace(&mut self) -> Result<Vec<B::Stmt>, Error> {
+        let mut statements = Vec::new();
 
-                        Ok(ast::Expr::Assignment(ast::AssignmentExpr {
-                            assignee: path,
-                            value: Box::new(value),
-                        }))
-                    },
-                    // Regular method call.
-                    _ => {
-                        let arguments = self.parse_arguments()?;
-
-                        Ok(ast::CallExpr {
-                            callee: path,
-                            arguments: arguments,
-                        }.into())
-                    }
-                }
+        self.until_token(Token::right_brace(), |parser| {
+            let statement = parser.parse_statement()?;
+            statements.push(statement);
 
-            },
-            Token::String(..) => self.parse_string_expression().map(Into::into),
-            Token::Integer(..) => self.parse_integer_expression().map(Into::into),
-            Token::Symbol("(") => self.parse_paren_expression().map(Into::into),
-            Token::Symbol(":") => self.parse_expr_starting_with_colon().map(Into::into),
-            Token::Symbol("-") => self.parse_negate_expr().map(Into::into),
-            token => panic!("don't know how to handle: {:?}", token),
-        }
+            // The last statement does not need a terminator.
+            if !parser.check(&Token::right_brace()) {
+                parser.end_statement()?;
+            }
+            Ok(())
+        })?;
+
+        Ok(statements)
     }
 
-    fn parse_path(&mut self) -> Result<ast::Path, Error> {
-        let mut segments = Vec::new();
-        let mut last_separator = ast::PathSeparator::Root;
+    /// Parses statements up to and including the `end` keyword.
+    fn parse_statements_until_end(&mut self) -> Result<Vec<B::Stmt>, Error> {
+        let statements = self.parse_statements_until(&[Keyword::End])?;
+        self.expect(&Token::end())?;
 
-        loop {
-            // Read the next word and push it to the list.
-            let word = expect::word(self.next())?;
-            segments.push(ast::PathSegment {
-                separator: last_separator,
-                kind: ast::PathSegmentKind::new(word),
-            });
+        Ok(statements)
+    }
 
-            match self.peek() {
-                Some(Token::Symbol("::")) => {
-                    last_separator = ast::PathSeparator::DoubleColon;
-                    self.eat();
-                },
-                Some(Token::Symbol(".")) => {
-                    last_separator = ast::PathSeparator::Dot;
-                    self.eat();
-                },
-                _ => break, // We've finished parsing
+    /// Parses terminated statements up until one of the given keywords.
+    ///
+    /// The keyword itself is not consumed.
+    fn parse_statements_until(&mut self, keywords: &[Keyword]) -> Result<Vec<B::Stmt>, Error> {
+        let mut statements = Vec::new();
+
+        let is_keyword = |token: &Token| match *token {
+            Token::Keyword(keyword) => keywords.contains(&keyword),
+            _ => false,
+        };
+
+        self.eat_terminators();
+        while !self.peek().map(|token| is_keyword(&token)).unwrap_or(true) {
+            statements.push(self.parse_statement()?);
+
+            if !self.peek().map(|token| is_keyword(&token)).unwrap_or(true) {
+                self.end_statement()?;
             }
+            self.eat_terminators();
         }
 
-        Ok(segments.into_iter().collect())
+        Ok(statements)
     }
 
-    fn parse_string_expression(&mut self) -> Result<ast::StringLiteral, Error> {
-        let token = self.next().unwrap();
+    /// Parses a statement.
+    fn parse_statement(&mut self) -> Result<B::Stmt, Error> {
+        self.recoverable(|parser| parser.commentable(Self::parse_statement_kind), |builder, span| {
+            let mut error = builder.error();
+            builder.locate_expr(&mut error, span);
+            builder.expr_stmt(error)
+        })
+    }
 
-        if let Token::String(s) = token {
-            Ok(ast::StringLiteral { value: s })
-        } else {
-            unreachable!();
-        }
+    fn parse_statement_kind(&mut self) -> Result<B::Stmt, Error> {
+        let checkpoint = self.checkpoint();
+        let start = self.peek_span();
+
+        let mut statement = match self.peek() {
+            Some(Token::Keyword(Keyword::Alias)) => {
+                self.node(SyntaxKind::Alias, Self::parse_alias)?
+            },
+            Some(Token::Keyword(Keyword::Undef)) => {
+                self.node(SyntaxKind::Undef, Self::parse_undef)?
+            },
+            // `*a, b = c`
+            Some(Token::Symbol("*")) => {
+                let assignment = self.parse_multiple_assignment(Vec::new())?;
+                self.finish_node_at(checkpoint, SyntaxKind::MultipleAssignment);
+                self.builder.expr_stmt(assignment)
+            },
+            _ => {
+                let expr = self.parse_expression()?;
+
+                // `a, b = c`
+                if self.check(&Token::comma()) {
+                    let first_target = self.assign_target(expr, self.span_since(start))?;
+                    let assignment = self.parse_multiple_assignment(vec![first_target])?;
+                    self.finish_node_at(checkpoint, SyntaxKind::MultipleAssignment);
+                    self.builder.expr_stmt(assignment)
+                } else {
+                    self.builder.expr_stmt(expr)
+                }
+            },
+        };
+
+        self.finish_node_at(checkpoint, SyntaxKind::Statement);
+
+        let span = self.span_since(start);
+        self.builder.locate_stmt(&mut statement, span);
+        Ok(statement)
     }
 
-    fn parse_integer_expression(&mut self) -> Result<ast::IntegerLiteral, Error> {
-        let token = self.next().unwrap();
+    /// Parses the remainder of a multiple assignment, given the targets read so far.
+    fn parse_multiple_assignment(&mut self, mut targets: Vec<B::Target>)
+        -> Result<B::Expr, Error> {
+        if targets.is_empty() {
+            targets.push(self.parse_assign_target()?);
+        }
 
-        if let Token::Integer(i) = token {
-            Ok(ast::IntegerLiteral { value: i })
-        } else {
-            unreachable!();
+        while self.check(&Token::comma()) {
+            self.eat_assert(&Token::comma());
+
+            // A trailing comma, as in `a, = b`.
+            if self.check(&Token::equals()) { break };
+
+            targets.push(self.parse_assign_target()?);
         }
-    }
 
-    fn parse_paren_expression(&mut self) -> Result<ast::ParenExpr, Error> {
-        self.eat_assert(&Token::left_paren());
+        self.expect(&Token::equals())?;
 
-        let inner = self.parse_expression()?;
-        expect::specific(self.next(), Token::right_paren())?;
+        let mut values = vec![self.parse_expression()?];
+        while self.check(&Token::comma()) {
+            self.eat_assert(&Token::comma());
+            values.push(self.parse_expression()?);
+        }
 
-        Ok(ast::ParenExpr { inner: Box::new(inner) })
+        Ok(self.builder.multiple_assignment(targets, values))
     }
 
-    fn parse_expr_starting_with_colon(&mut self) -> Result<ast::Expr, Error> {
-        let symbol = self.parse_symbol()?;
+    /// Parses a single target in a multiple assignment, `for` loop or block
+    /// parameter list.
+    fn parse_assign_target(&mut self) -> Result<B::Target, Error> {
+        match self.peek().unwrap_or(Token::EndOfFile) {
+            Token::Symbol("*") => {
+                self.eat();
 
-        if self.peek().unwrap() == Token::rocket() {
-            self.eat_assert(&Token::rocket());
+                let inner = match self.peek() {
+                    Some(Token::Identifier(..)) | Some(Token::Constant(..)) | Some(Token::Symbol("(")) => {
+                        Some(self.parse_assign_target()?)
+                    },
+                    _ => None,
+                };
 
-            let value = self.parse_expression()?;
+                Ok(self.builder.splat_target(inner))
+            },
+            Token::Symbol("(") => {
+                self.eat();
 
-            Ok(ast::KeyValueExpr {
-                key: symbol.name,
-                value: Box::new(value),
-            }.into())
-        } else {
-            Ok(symbol.into())
+                let mut targets = vec![self.parse_assign_target()?];
+                while self.check(&Token::comma()) {
+                    self.eat_assert(&Token::comma());
+                    targets.push(self.parse_assign_target()?);
+                }
+
+                self.expect(&Token::right_paren())?;
+                Ok(self.builder.group_target(targets))
+            },
+            _ => {
+                let start = self.peek_span();
+                let expr = self.parse_postfix_expression()?;
+                self.assign_target(expr, self.span_since(start))
+            },
         }
     }
 
-    fn parse_negate_expr(&mut self) -> Result<ast::Expr, Error> {
-        self.eat_assert(&Token::negate());
+    /// Converts an expression on the left of an `=` into an assignment target.
+    fn assign_target(&mut self, expr: B::Expr, span: Span) -> Result<B::Target, Error> {
+        self.builder.target(expr).map_err(|expr| {
+            let found = self.builder.describe(&expr);
+            ErrorKind::UnexpectedExpr(found, "an assignment target".to_owned(), span).into()
+        })
+    }
+
+    /// Parses an `alias new old` statement.
+    fn parse_alias(&mut self) -> Result<B::Stmt, Error> {
+        self.eat_assert(&Token::alias());
+
+        let new_name = self.parse_method_name_or_symbol()?;
+        let old_name = self.parse_method_name_or_symbol()?;
+
+        let kind = if new_name.as_str().starts_with('$') {
+            ast::AliasKind::GlobalVariable
+        } else {
+            ast::AliasKind::Method
+        };
 
-        let inner = self.parse_expression()?;
-        Ok(ast::Expr::Negate(ast::NegateExpr { inner: Box::new(inner) }))
+        let new_name = Symbol::intern(new_name.as_str().trim_start_matches('$'));
+        let old_name = Symbol::intern(old_name.as_str().trim_start_matches('$'));
+
+        Ok(self.builder.alias(kind, new_name, old_name))
     }
 
-    fn parse_symbol(&mut self) -> Result<ast::SymbolExpr, Error> {
-        self.eat_assert(&Token::colon());
-        let name = expect::word(self.next())?;
-        Ok(ast::SymbolExpr { name: name })
+    /// Parses an `undef a, b` statement.
+    fn parse_undef(&mut self) -> Result<B::Stmt, Error> {
+        self.eat_assert(&Token::undef());
+
+        let mut names = vec![self.parse_method_name_or_symbol()?];
+
+        while self.check(&Token::comma()) {
+            self.eat_assert(&Token::comma());
+            names.push(self.parse_method_name_or_symbol()?);
+        }
+
+        Ok(self.builder.undef(names))
     }
 
-    fn parse_arguments(&mut self) -> Result<Vec<ast::Argument>, Error> {
-        if self.peek().unwrap() == Token::left_paren() {
-            self.parse_arguments_with_parens()
+    /// Parses a bare method name or a symbol naming a method.
+    fn parse_method_name_or_symbol(&mut self) -> Result<Symbol, Error> {
+        if self.check(&Token::colon()) {
+            self.parse_symbol()
         } else {
-            self.parse_arguments_without_parens()
+            self.expect_method_name()
         }
     }
 
-    fn parse_arguments_with_parens(&mut self) -> Result<Vec<ast::Argument>, Error> {
-        self.eat_assert(&Token::left_paren());
-
-        let mut arguments = Vec::new();
+    fn parse_expression(&mut self) -> Result<B::Expr, Error> {
+        self.eat_whitespace();
 
-        self.until_token(Token::right_paren(), |parser| {
-            let argument = parser.parse_argument()?;
-            arguments.push(argument);
+        let checkpoint = self.checkpoint();
+        let start = self.peek_span();
+        let condition = self.parse_binary_expression(0)?;
 
-            expect::one_of(parser.peek(), &[Token::comma(), Token::right_paren()])?;
-            parser.eat_if(|token| *token == Token::comma())?;
-            Ok(())
-        })?;
+        if self.peek() == Some(Token::question_mark()) {
+            self.eat_assert(&Token::question_mark());
 
-        Ok(arguments)
-    }
+            let restrictions = Restrictions { no_symbol_arguments: true, ..self.restrictions };
+            let if_true = self.with_restrictions(restrictions, |parser| parser.parse_expression())?;
 
-    fn parse_arguments_without_parens(&mut self) -> Result<Vec<ast::Argument>, Error> {
-        let mut arguments = Vec::new();
+            self.expect(&Token::colon())?;
+            let if_false = self.parse_expression()?;
+            self.finish_node_at(checkpoint, SyntaxKind::Ternary);
 
-        if self.peek().unwrap().is_terminator() {
-            return Ok(Vec::new());
+            let ternary = self.builder.ternary(condition, if_true, if_false);
+            Ok(self.located(ternary, start))
+        } else {
+            Ok(condition)
         }
+    }
 
-        loop {
-            let argument = self.parse_argument()?;
-            arguments.push(argument);
+    /// Parses a chain of binary operators binding at least as tightly as
+    /// `min_precedence`.
+    fn parse_binary_expression(&mut self, min_precedence: u8) -> Result<B::Expr, Error> {
+        let checkpoint = self.checkpoint();
+        let start = self.peek_span();
+        let mut lhs = self.parse_primary_expression()?;
 
-            if self.peek().unwrap() == Token::comma() {
-                self.eat_assert(&Token::comma());
-                continue;
+        while let Some(operator) = self.peek_binary_operator(min_precedence) {
+            self.eat();
+
+            let rhs_precedence = if operator.is_right_associative() {
+                operator.precedence()
             } else {
-                break;
-            }
+                operator.precedence() + 1
+            };
+            let rhs = self.parse_binary_expression(rhs_precedence)?;
+            self.finish_node_at(checkpoint, SyntaxKind::Binary);
+
+            let binary = self.builder.binary(operator, lhs, rhs);
+            lhs = self.located(binary, start);
         }
 
-        Ok(arguments)
+        Ok(lhs)
     }
 
-    fn parse_argument(&mut self) -> Result<ast::Argument, Error> {
-        let value = self.parse_expression()?;
-        Ok(ast::Argument::Positional(value))
+    /// Peeks at a binary operator binding at least as tightly as `min_precedence`.
+    fn peek_binary_operator(&mut self, min_precedence: u8) -> Option<ast::BinaryOp> {
+        match self.peek() {
+            Some(Token::Symbol(symbol)) => ast::BinaryOp::from_symbol(symbol)
+                .filter(|operator| operator.precedence() >= min_precedence),
+            _ => None,
+        }
     }
 
-    fn peek(&mut self) -> Option<Token> { self.tokenizer.peek().map(Clone::clone) }
-    fn next(&mut self) -> Option<Token> { self.tokenizer.next() }
-    fn eat(&mut self) -> Option<Token> { self.next() }
+    /// Parses an expression which does not contain binary operators.
+    fn parse_primary_expression(&mut self) -> Result<B::Expr, Error> {
+        let checkpoint = self.checkpoint();
+        let start = self.peek_span();
+        let expr = self.parse_postfix_expression()?;
 
-    fn eat_assert(&mut self, token: &Token) {
-        let read_token = self.next().expect("no more tokens");
-        assert_eq!(read_token, *token, "tokens do not match");
-    }
+        // Check for assignment.
+        if self.peek() == Some(Token::equals()) {
+            let assignee = self.assign_target(expr, self.span_since(start))?;
+            self.eat_assert(&Token::equals());
 
-    /// Checks if we've reached the end of file yet.
-    fn is_eof(&mut self) -> bool {
-        if let Some(token) = self.peek() {
-            if let Token::EndOfFile = token { true } else { false }
-        } else {
-            true
+            let value = self.parse_expression()?;
+            self.finish_node_at(checkpoint, SyntaxKind::Assignment);
+
+            let assignment = self.builder.assignment(assignee, value);
+            return Ok(self.located(assignment, start));
         }
+
+        Ok(expr)
     }
 
-    fn eat_whitespace(&mut self) {
+    /// Parses an expression followed by any number of indexes, `a[b][c]`,
+    /// and scoped constants, `a(b)::C`.
+    fn parse_postfix_expression(&mut self) -> Result<B::Expr, Error> {
+        let checkpoint = self.checkpoint();
+        let start = self.peek_span();
+        let mut expr = self.parse_simple_expression()?;
+
         loop {
             match self.peek() {
-                Some(Token::EndOfLine) => {
-                    self.eat();
+                Some(Token::Symbol("[")) => {
+                    self.eat_assert(&Token::left_bracket());
+
+                    let arguments = self.with_restrictions(Restrictions::default(), |parser| {
+                        parser.parse_delimited_arguments(Token::right_bracket())
+                    })?;
+
+                    self.finish_node_at(checkpoint, SyntaxKind::Index);
+                    let index = self.builder.index(expr, arguments);
+                    expr = self.located(index, start);
                 },
-                _ => break,
-            }
+                Some(Token::Symbol("::")) => {
+                    self.eat_assert(&Token::double_colon());
+                    let constant = self.expect_constant()?;
+                    self.finish_node_at(checkpoint, SyntaxKind::Constant);
+
+                    let constant = self.builder.scoped_constant(expr, constant);
+                    expr = self.located(constant, start);
+                },
+                _ => break,
+            }
         }
+
+        Ok(expr)
     }
 
-    fn until_token<F>(&mut self, token: Token, f: F) -> Result<(), Error>
-        where F: FnMut(&mut Self) -> Result<(), Error> {
-        self.until(|next_tok| *next_tok == token , f, true)?;
-        self.eat(); // Eat terminator.
-        Ok(())
+    /// Parses an expression which is not made up of smaller expressions
+    /// joined by operators.
+    fn parse_simple_expression(&mut self) -> Result<B::Expr, Error> {
+        let checkpoint = self.checkpoint();
+        let start = self.peek_span();
+
+        let expr = self.parse_simple_expression_kind()?;
+        let kind = self.builder.syntax_kind(&expr);
+        self.finish_node_at(checkpoint, kind);
+
+        Ok(self.located(expr, start))
     }
 
-    fn until_end<F>(&mut self, f: F) -> Result<(), Error>
-        where F: FnMut(&mut Self) -> Result<(), Error> {
-        self.until_token(Token::end(), f)
+    fn parse_simple_expression_kind(&mut self) -> Result<B::Expr, Error> {
+        self.expected.push(TokenKind::Expression);
+
+        match self.peek().unwrap_or(Token::EndOfFile) {
+            Token::Keyword(Keyword::Defined) => self.parse_defined_expr(),
+            Token::Keyword(Keyword::Super) => self.parse_super_expr(),
+            Token::Keyword(Keyword::Yield) => self.parse_yield_expr(),
+            Token::Keyword(Keyword::Return) => self.parse_return_expr(),
+            Token::Keyword(Keyword::For) => self.parse_for_expr(),
+            Token::Keyword(Keyword::Begin) => self.parse_begin_expr(),
+            // Leave keywords like `end` for the enclosing construct.
+            Token::Keyword(Keyword::End) | Token::Keyword(Keyword::Else) | Token::Keyword(Keyword::Elsif) |
+                Token::Keyword(Keyword::Rescue) | Token::Keyword(Keyword::Ensure) | Token::Keyword(Keyword::When) => {
+                self.missing_expression()
+            },
+            Token::Keyword(keyword) if !keyword.is_value() => Err(self.unexpected()),
+            Token::Label(..) => self.parse_label_expr(),
+            Token::Keyword(..) | Token::Constant(..) | Token::Identifier(..) | Token::MethodName(..) => {
+                let path = self.parse_path()?;
+                let arguments = self.parse_arguments()?;
+                let block = self.parse_block()?;
+
+                // `obj.class::CONST` looks up a constant in the value of `obj.class`.
+                if arguments.is_empty() && block.is_none() {
+                    let (scope, constants) = match split_scoped_constant(path) {
+                        Ok(split) => split,
+                        Err(path) => return Ok(self.builder.call(path, arguments, block)),
+                    };
+
+                    let scope = self.builder.call(scope, Vec::new(), None);
+                    return Ok(constants.into_iter().fold(scope, |scope, constant| {
+                        self.builder.scoped_constant(scope, constant)
+                    }));
+                }
+
+                Ok(self.builder.call(path, arguments, block))
+            },
+            Token::String(..) => self.parse_string_expression(),
+            Token::Integer(..) => self.parse_integer_expression(),
+            Token::Character(c) => {
+                self.eat();
+                Ok(self.builder.character(c))
+            },
+            Token::Symbol("(") => self.parse_paren_expression(),
+            Token::Symbol("::") => {
+                let path = self.parse_constant_path()?;
+                Ok(self.builder.constant(path))
+            },
+            Token::Symbol(":") => self.parse_expr_starting_with_colon(),
+            Token::Symbol("-") => self.parse_negate_expr(),
+            _ => self.missing_expression(),
+        }
     }
 
-    fn until<P,F>(&mut self,
-                  mut pred: P,
-                  mut f: F,
-                  eat_trailing_whitespace: bool) -> Result<(), Error>
-        where P: FnMut(&Token) -> bool, F: FnMut(&mut Self) -> Result<(), Error> {
-        // Stop immediately if the predicate is true.
-        if self.peek().map(|tok| pred(&tok)).unwrap_or(true) { return Ok(()) };
+    /// Handles an expression which is missing because we found a token that
+    /// cannot begin one.
+    ///
+    /// The token is left for the enclosing construct.
+    fn missing_expression(&mut self) -> Result<B::Expr, Error> {
+        let error = self.unexpected();
+        self.report(error)?;
+        Ok(self.builder.missing())
+    }
 
-        self.eat_whitespace();
+    /// Parses a block following a method call, if there is one.
+    fn parse_block(&mut self) -> Result<Option<B::Block>, Error> {
+        let closing_token = match self.peek() {
+            Some(Token::Symbol("{")) => Token::right_brace(),
+            Some(Token::Keyword(Keyword::Do)) if !self.restrictions.no_do_block => Token::end(),
+            _ => return Ok(None),
+        };
 
-        while self.peek().map(|tok| !pred(&tok)).unwrap_or(false) {
-            f(self)?;
+        let checkpoint = self.checkpoint();
+        self.eat();
 
-            if eat_trailing_whitespace { self.eat_whitespace(); }
+        self.with_restrictions(Restrictions::default(), |parser| {
+            let mut parameters = Vec::new();
+
+            match parser.peek() {
+                Some(Token::Symbol("||")) => { parser.eat(); },
+                Some(Token::Symbol("|")) => {
+                    parser.eat();
+
+                    while !parser.check(&Token::pipe()) {
+                        parameters.push(parser.parse_assign_target()?);
+
+                        if !parser.consume(&Token::comma()) && !parser.check(&Token::pipe()) {
+                            return Err(parser.unexpected());
+                        }
+                    }
+
+                    parser.eat_assert(&Token::pipe());
+                },
+                _ => (),
+            }
+
+            let statements = if closing_token == Token::end() {
+                parser.parse_statements_until(&[Keyword::End])?
+            } else {
+                parser.parse_brace_statements_after_brace()?
+            };
+
+            if closing_token == Token::end() {
+                parser.expect(&Token::end())?;
+            }
+
+            parser.finish_node_at(checkpoint, SyntaxKind::Block);
+            Ok(Some(parser.builder.block(parameters, statements)))
+        })
+    }
+
+    /// Parses a `for a in b ... end` loop.
+    fn parse_for_expr(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::for_());
+
+        let restrictions = Restrictions { no_do_block: true, ..Restrictions::default() };
+        let (targets, iterable) = self.with_restrictions(restrictions, |parser| {
+            let mut targets = vec![parser.parse_assign_target()?];
+            while parser.check(&Token::comma()) {
+                parser.eat_assert(&Token::comma());
+                targets.push(parser.parse_assign_target()?);
+            }
+
+            parser.expect(&Token::in_())?;
+            let iterable = parser.parse_expression()?;
+
+            Ok((targets, iterable))
+        })?;
+
+        // The body is separated by `do`, a new line or a semicolon.
+        if self.check(&Token::do_()) {
+            self.eat_assert(&Token::do_());
+        } else {
+            self.expect_terminator()?;
+        }
+
+        let statements = self.parse_statements_until_end()?;
+
+        Ok(self.builder.for_loop(targets, iterable, statements))
+    }
+
+    /// Parses a `begin ... rescue ... else ... ensure ... end` expression.
+    fn parse_begin_expr(&mut self) -> Result<B::Expr, Error> {
+        const CLAUSE_KEYWORDS: &[Keyword] = &[Keyword::Rescue, Keyword::Else, Keyword::Ensure, Keyword::End];
+
+        self.eat_assert(&Token::begin());
+
+        let statements = self.parse_statements_until(CLAUSE_KEYWORDS)?;
+        let mut rescue_clauses = Vec::new();
+        let mut else_statements = None;
+        let mut ensure_statements = None;
+
+        while self.check(&Token::rescue()) {
+            rescue_clauses.push(self.parse_rescue_clause()?);
+        }
+
+        if self.check(&Token::else_()) {
+            self.eat_assert(&Token::else_());
+            else_statements = Some(self.parse_statements_until(&[Keyword::Ensure, Keyword::End])?);
+        }
+
+        if self.check(&Token::ensure()) {
+            self.eat_assert(&Token::ensure());
+            ensure_statements = Some(self.parse_statements_until(&[Keyword::End])?);
+        }
+
+        self.expect(&Token::end())?;
+
+        Ok(self.builder.begin(statements, rescue_clauses, else_statements, ensure_statements))
+    }
+
+    /// Parses a `rescue A, B => target` clause and its statements.
+    fn parse_rescue_clause(&mut self) -> Result<B::RescueClause, Error> {
+        let checkpoint = self.checkpoint();
+        self.eat_assert(&Token::rescue());
+
+        let mut exceptions = Vec::new();
+        let mut target = None;
+
+        let next_token = self.peek().unwrap_or(Token::EndOfFile);
+        if !next_token.is_terminator() && next_token != Token::rocket() && next_token != Token::then() {
+            exceptions.push(self.parse_expression()?);
+
+            while self.check(&Token::comma()) {
+                self.eat_assert(&Token::comma());
+                exceptions.push(self.parse_expression()?);
+            }
+        }
+
+        if self.check(&Token::rocket()) {
+            self.eat_assert(&Token::rocket());
+            target = Some(self.parse_assign_target()?);
+        }
+
+        if self.check(&Token::then()) {
+            self.eat_assert(&Token::then());
+        } else {
+            self.expect_terminator()?;
+        }
+
+        let statements = self.parse_statements_until(&[Keyword::Rescue, Keyword::Else, Keyword::Ensure, Keyword::End])?;
+        self.finish_node_at(checkpoint, SyntaxKind::Rescue);
+
+        Ok(self.builder.rescue_clause(exceptions, target, statements))
+    }
+
+    fn parse_path(&mut self) -> Result<ast::Path, Error> {
+        let mut segments = Vec::new();
+        let mut last_separator = ast::PathSeparator::Root;
+
+        loop {
+            // Read the next word and push it to the list.
+            let word = self.expect_method_name()?;
+            segments.push(ast::PathSegment {
+                separator: last_separator,
+                kind: ast::PathSegmentKind::new(word),
+            });
+
+            match self.peek() {
+                Some(Token::Symbol("::")) => {
+                    last_separator = ast::PathSeparator::DoubleColon;
+                    self.eat();
+                },
+                Some(Token::Symbol(".")) => {
+                    last_separator = ast::PathSeparator::Dot;
+                    self.eat();
+                },
+                Some(Token::Symbol("&.")) => {
+                    last_separator = ast::PathSeparator::SafeNavigation;
+                    self.eat();
+                },
+                _ => break, // We've finished parsing
+            }
+        }
+
+        Ok(segments.into_iter().collect())
+    }
+
+    /// Parses a path made up only of constants, such as `Foo::Bar` or `::Baz`.
+    fn parse_constant_path(&mut self) -> Result<B::ConstantPath, Error> {
+        let mut parts = Vec::new();
+        let top_level = self.check(&Token::double_colon());
+        if top_level {
+            self.eat_assert(&Token::double_colon());
+        }
+
+        loop {
+            parts.push(self.expect_constant()?);
+
+            if self.peek() == Some(Token::double_colon()) {
+                self.eat_assert(&Token::double_colon());
+            } else {
+                break;
+            }
+        }
+
+        Ok(self.builder.constant_path(top_level, parts))
+    }
+
+    fn parse_string_expression(&mut self) -> Result<B::Expr, Error> {
+        let token = self.next().unwrap();
+
+        if let Token::String(s) = token {
+            Ok(self.builder.string(s))
+        } else {
+            unreachable!();
+        }
+    }
+
+    fn parse_integer_expression(&mut self) -> Result<B::Expr, Error> {
+        let token = self.next().unwrap();
+
+        if let Token::Integer(i) = token {
+            Ok(self.builder.integer(i))
+        } else {
+            unreachable!();
+        }
+    }
+
+    fn parse_paren_expression(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::left_paren());
+
+        let inner = self.with_restrictions(Restrictions::default(), |parser| parser.parse_expression())?;
+        self.expect(&Token::right_paren())?;
+
+        Ok(self.builder.paren(inner))
+    }
+
+    fn parse_expr_starting_with_colon(&mut self) -> Result<B::Expr, Error> {
+        let mark = self.commentable_mark();
+        let symbol = self.parse_symbol()?;
+
+        if self.peek() == Some(Token::rocket()) {
+            self.eat_assert(&Token::rocket());
+
+            let value = self.parse_expression()?;
+            self.finish_commentable(mark);
+
+            Ok(self.builder.key_value(symbol, value))
+        } else {
+            Ok(self.builder.symbol(symbol))
+        }
+    }
+
+    /// Parses a `key: value` expression.
+    fn parse_label_expr(&mut self) -> Result<B::Expr, Error> {
+        let mark = self.commentable_mark();
+        let key = match self.next() {
+            Some(Token::Label(key)) => key,
+            _ => unreachable!(),
+        };
+
+        let value = self.parse_expression()?;
+        self.finish_commentable(mark);
+
+        Ok(self.builder.key_value(key, value))
+    }
+
+    fn parse_negate_expr(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::negate());
+
+        // Negation binds more tightly than everything but `**`.
+        let inner = self.parse_binary_expression(ast::BinaryOp::Power.precedence())?;
+        Ok(self.builder.negate(inner))
+    }
+
+    fn parse_defined_expr(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::defined());
+
+        // The parentheses are not part of the inner expression.
+        let inner = if self.check(&Token::left_paren()) {
+            self.eat_assert(&Token::left_paren());
+            let inner = self.parse_expression()?;
+            self.expect(&Token::right_paren())?;
+            inner
+        } else {
+            self.parse_expression()?
+        };
+
+        Ok(self.builder.defined(inner))
+    }
+
+    /// Parses a `super` expression, with or without explicit arguments.
+    fn parse_super_expr(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::super_());
+
+        if self.check(&Token::left_paren()) {
+            let arguments = self.parse_arguments_with_parens()?;
+            return Ok(self.builder.super_call(arguments));
+        }
+
+        let arguments = self.parse_arguments_without_parens()?;
+
+        if arguments.is_empty() {
+            Ok(self.builder.zsuper())
+        } else {
+            Ok(self.builder.super_call(arguments))
+        }
+    }
+
+    fn parse_yield_expr(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::yield_());
+
+        let arguments = self.parse_arguments()?;
+        Ok(self.builder.yield_expr(arguments))
+    }
+
+    fn parse_return_expr(&mut self) -> Result<B::Expr, Error> {
+        self.eat_assert(&Token::return_());
+
+        let arguments = self.parse_arguments()?;
+        Ok(self.builder.return_expr(arguments))
+    }
+
+    /// Parses a symbol, giving back its name.
+    fn parse_symbol(&mut self) -> Result<Symbol, Error> {
+        self.eat_assert(&Token::colon());
+        self.expect_method_name()
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<B::Expr>, Error> {
+        if self.peek() == Some(Token::left_paren()) {
+            self.parse_arguments_with_parens()
+        } else {
+            self.parse_arguments_without_parens()
+        }
+    }
+
+    fn parse_arguments_with_parens(&mut self) -> Result<Vec<B::Expr>, Error> {
+        self.node(SyntaxKind::Arguments, |parser| {
+            parser.eat_assert(&Token::left_paren());
+
+            parser.with_restrictions(Restrictions::default(), |parser| {
+                parser.parse_delimited_arguments(Token::right_paren())
+            })
+        })
+    }
+
+    /// Parses comma separated arguments up to and including a closing token.
+    fn parse_delimited_arguments(&mut self, closing_token: Token) -> Result<Vec<B::Expr>, Error> {
+        let mut arguments = Vec::new();
+
+        self.until_token(closing_token.clone(), |parser| {
+            let argument = parser.parse_argument()?;
+            arguments.push(argument);
+
+            if !parser.consume(&Token::comma()) && !parser.check(&closing_token) {
+                return Err(parser.unexpected());
+            }
+
+            Ok(())
+        })?;
+
+        Ok(arguments)
+    }
+
+    fn parse_arguments_without_parens(&mut self) -> Result<Vec<B::Expr>, Error> {
+        let mut arguments = Vec::new();
+
+        let next_token = self.peek().unwrap_or(Token::EndOfFile);
+        if !next_token.can_begin_argument() ||
+            (self.restrictions.no_symbol_arguments && next_token == Token::colon()) {
+            return Ok(Vec::new());
+        }
+
+        let checkpoint = self.checkpoint();
+
+        // A `do` after the arguments belongs to this call, not the last argument.
+        let restrictions = Restrictions { no_do_block: true, ..self.restrictions };
+        self.with_restrictions(restrictions, |parser| {
+            loop {
+                let argument = parser.parse_argument()?;
+                arguments.push(argument);
+
+                if parser.peek() == Some(Token::comma()) {
+                    parser.eat_assert(&Token::comma());
+                    continue;
+                } else {
+                    break;
+                }
+            }
+
+            Ok(())
+        })?;
+
+        self.finish_node_at(checkpoint, SyntaxKind::Arguments);
+        Ok(arguments)
+    }
+
+    fn parse_argument(&mut self) -> Result<B::Expr, Error> {
+        self.parse_expression()
+    }
+
+    fn peek(&mut self) -> Option<Token> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.tokenizer.next_syntax_token());
+        }
+
+        self.peeked.as_ref().unwrap().as_ref().map(|syntax_token| syntax_token.token.clone())
+    }
+
+    /// Checks if the next token is a certain token, remembering that it
+    /// was expected in case it is not.
+    fn check(&mut self, token: &Token) -> bool {
+        self.expected.push(TokenKind::of(token));
+        self.peek().as_ref() == Some(token)
+    }
+
+    /// Checks if the next token is of a certain kind, remembering that it
+    /// was expected in case it is not.
+    fn check_kind(&mut self, kind: TokenKind) -> bool {
+        self.expected.push(kind);
+        self.peek().is_some_and(|token| kind.matches(&token))
+    }
+
+    /// Eats the next token if it is a certain token.
+    fn consume(&mut self, token: &Token) -> bool {
+        let found = self.check(token);
+        if found { self.eat(); }
+        found
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<Token, Error> {
+        if self.consume(token) {
+            Ok(token.clone())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Expects a variable name.
+    fn expect_identifier(&mut self) -> Result<Symbol, Error> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                self.eat();
+                Ok(name)
+            },
+            _ => {
+                self.expected.push(TokenKind::Identifier);
+                Err(self.unexpected())
+            },
+        }
+    }
+
+    /// Expects the name of a method, which may be a keyword as in `obj.class`.
+    fn expect_method_name(&mut self) -> Result<Symbol, Error> {
+        let name = match self.peek() {
+            Some(Token::Identifier(name)) | Some(Token::Constant(name)) | Some(Token::MethodName(name)) => name,
+            Some(Token::Keyword(keyword)) => Symbol::intern(keyword.as_str()),
+            _ => {
+                self.expected.push(TokenKind::MethodName);
+                return Err(self.unexpected());
+            },
+        };
+
+        self.eat();
+        Ok(name)
+    }
+
+    fn expect_constant(&mut self) -> Result<ast::Constant, Error> {
+        match self.peek() {
+            Some(Token::Constant(name)) => {
+                self.eat();
+                Ok(ast::Constant(name))
+            },
+            _ => {
+                self.expected.push(TokenKind::Constant);
+                Err(self.unexpected())
+            },
+        }
+    }
+
+    /// Expects a new line or semicolon, or the end of the file.
+    fn expect_terminator(&mut self) -> Result<(), Error> {
+        if self.check_kind(TokenKind::Terminator) || self.peek().is_none() {
+            self.eat();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Expects nothing but terminators to be left.
+    fn expect_end_of_input(&mut self) -> Result<(), Error> {
+        self.eat_terminators();
+
+        if !self.is_eof() {
+            self.check_kind(TokenKind::EndOfFile);
+            return Err(self.unexpected());
+        }
+
+        // A string missing its closing quote runs to the end of the file.
+        if let Some(string) = self.tokenizer.unterminated_string().cloned() {
+            let span = self.peek_span();
+            self.report(ErrorKind::Unclosed(Box::new(string), Token::EndOfFile, span, None).into())?;
         }
 
         Ok(())
     }
 
-    fn eat_if<F>(&mut self,
-                 mut f: F) -> Result<(), Error>
-        where F: FnMut(&Token) -> bool {
-        if let Some(token) = self.peek() {
-            if f(&token) {
-                self.eat(); // Eat the token.
+    /// Creates an error for the next token, which is none of the tokens
+    /// that have been checked for since the last token was read.
+    fn unexpected(&mut self) -> Error {
+        let found = self.peek().unwrap_or(Token::EndOfFile);
+        let span = self.peek_span();
+
+        if let Some((construct, probable_line)) = self.unclosed_construct(&found) {
+            self.expected.clear();
+            return ErrorKind::Unclosed(Box::new(construct), found, span, probable_line).into();
+        }
+
+        let mut expected = Vec::new();
+        for kind in self.expected.drain(..) {
+            if !expected.contains(&kind) {
+                expected.push(kind);
             }
         }
 
+        ErrorKind::UnexpectedToken(found, expected, span).into()
+    }
+
+    /// Finds the construct which was probably never closed, if the next
+    /// token shows that something was not.
+    ///
+    /// Also gives back the line its closer probably belongs before, if the
+    /// indentation shows it.
+    fn unclosed_construct(&self, found: &Token) -> Option<(OpenConstruct, Option<usize>)> {
+        if *found == Token::EndOfFile {
+            if let Some(string) = self.tokenizer.unterminated_string() {
+                return Some((string.clone(), None));
+            }
+
+            if self.open.is_empty() { return None };
+
+            return match self.misindented {
+                Some((ref construct, line)) => Some((construct.clone(), Some(line))),
+                None => self.open.last().map(|construct| (construct.clone(), None)),
+            };
+        }
+
+        // Finding the closer of an outer construct means an inner one was
+        // never closed, as in `foo(a[0)`.
+        let (innermost, outer) = self.open.split_last()?;
+        let closes_outer = !innermost.is_closed_by(found) &&
+            outer.iter().any(|construct| construct.is_closed_by(found));
+
+        if closes_outer { Some((innermost.clone(), None)) } else { None }
+    }
+
+    /// Gets where the next token is in the source.
+    fn peek_span(&mut self) -> Span {
+        self.peek();
+
+        match self.peeked {
+            Some(Some(ref syntax_token)) => syntax_token.span,
+            _ => Span::at(self.last_span.end),
+        }
+    }
+
+    /// Gets the span from the start of a token up to the last token read.
+    fn span_since(&self, start: Span) -> Span {
+        Span::new(start.start, self.last_span.end.max(start.start))
+    }
+
+    /// Records that an expression runs from the start of a token up to the
+    /// last token read.
+    fn located(&mut self, mut expr: B::Expr, start: Span) -> B::Expr {
+        let span = self.span_since(start);
+        self.builder.locate_expr(&mut expr, span);
+        expr
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let syntax_token = match self.peeked.take() {
+            Some(syntax_token) => syntax_token,
+            None => self.tokenizer.next_syntax_token(),
+        }?;
+
+        let token = syntax_token.token.clone();
+        self.last_span = syntax_token.span;
+        self.last_line = syntax_token.line;
+        self.expected.clear();
+
+        self.track_nesting(&syntax_token);
+        self.tokens_read += 1;
+
+        if let Some(ref mut builder) = self.cst {
+            builder.token(syntax_token);
+        }
+
+        Some(token)
+    }
+
+    /// Keeps track of the constructs which are open as a token is read.
+    fn track_nesting(&mut self, syntax_token: &cst::SyntaxToken) {
+        let token = &syntax_token.token;
+        let after_expression = mem::replace(&mut self.after_expression, token.can_end_expression());
+
+        let is_name = matches!(*token, Token::Identifier(..) | Token::Constant(..) | Token::MethodName(..) |
+                               Token::Keyword(Keyword::Self_) | Token::Symbol(".") | Token::Symbol("::"));
+        if self.naming_construct && is_name {
+            let construct = self.open.last_mut().unwrap();
+            if !construct.description.ends_with(['.', ':']) && !matches!(*token, Token::Symbol(..)) {
+                construct.description.push(' ');
+            }
+            construct.description.push_str(&syntax_token.text);
+            return;
+        }
+        self.naming_construct = false;
+
+        let opens = match *token {
+            // `x if y` is a modifier, but `x = if y ... end` is not.
+            Token::Keyword(Keyword::If) | Token::Keyword(Keyword::Unless) => !after_expression,
+            Token::Keyword(Keyword::While) | Token::Keyword(Keyword::Until) if !after_expression => {
+                self.in_loop_header = true;
+                true
+            },
+            Token::Keyword(Keyword::While) | Token::Keyword(Keyword::Until) => false,
+            Token::Keyword(Keyword::For) => {
+                self.in_loop_header = true;
+                true
+            },
+            // The `do` in `while x do` does not need its own `end`.
+            Token::Keyword(Keyword::Do) => !mem::replace(&mut self.in_loop_header, false),
+            Token::Keyword(Keyword::Class) | Token::Keyword(Keyword::Module) | Token::Keyword(Keyword::Def) => {
+                self.naming_construct = true;
+                true
+            },
+            ref token if token.is_terminator() => {
+                self.in_loop_header = false;
+                false
+            },
+            _ => true,
+        };
+
+        if let Some(closer) = OpenConstruct::opened_by(token).filter(|_| opens) {
+            self.open.push(OpenConstruct {
+                description: syntax_token.text.clone(),
+                closer,
+                span: syntax_token.span,
+                line: syntax_token.line,
+                indent: syntax_token.indent,
+            });
+        } else if OpenConstruct::is_closer(token) {
+            // Stray closers are ignored, and closing an outer construct
+            // closes everything inside it too.
+            if let Some(index) = self.open.iter().rposition(|construct| construct.is_closed_by(token)) {
+                let construct = self.open.drain(index..).next().unwrap();
+
+                if self.misindented.is_none() && syntax_token.line != construct.line &&
+                    syntax_token.indent < construct.indent {
+                    self.misindented = Some((construct, syntax_token.line));
+                }
+            }
+        }
+    }
+
+    /// Records an error if we are recovering from errors, or fails otherwise.
+    fn report(&mut self, error: Error) -> Result<(), Error> {
+        match self.errors {
+            Some(ref mut errors) => {
+                // Everything left open at the end of the file fails there,
+                // but only the first is worth reporting.
+                if error.span().is_none() || errors.iter().all(|other| other.span() != error.span()) {
+                    errors.push(error);
+                }
+
+                Ok(())
+            },
+            None => Err(error),
+        }
+    }
+
+    /// Parses something, or if it cannot be parsed and we are recovering from
+    /// errors, skips to the end of it and gives back a placeholder.
+    ///
+    /// Anything built before the error is thrown away.
+    fn recoverable<T, F, P>(&mut self, f: F, placeholder: P) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error>, P: FnOnce(&mut B, Span) -> T {
+        let checkpoint = self.checkpoint();
+        let mark = self.commentable_mark();
+        let built = self.builder.mark();
+        let depth = self.open.len();
+        let tokens_read = self.tokens_read;
+
+        match f(self) {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                self.report(error)?;
+                self.synchronise(depth);
+
+                // Always skip at least one token so that we make progress.
+                if self.tokens_read == tokens_read {
+                    self.eat();
+                }
+
+                self.finish_node_at(checkpoint, SyntaxKind::Error);
+
+                // The placeholder is a statement, replacing any nodes read
+                // before the error.
+                self.commentable_nodes.truncate(mark.0);
+                self.finish_commentable(mark);
+
+                self.builder.discard(built);
+                let span = self.span_since(mark.1);
+                Ok(placeholder(&mut self.builder, span))
+            },
+        }
+    }
+
+    /// Skips to the end of a statement which began at a certain depth.
+    ///
+    /// This stops before a terminator at that depth, or a closing bracket
+    /// or `end` which closes the enclosing construct.
+    fn synchronise(&mut self, depth: usize) {
+        while let Some(token) = self.peek() {
+            if self.open.len() <= depth && token.is_terminator() {
+                break;
+            }
+
+            if self.open.len() <= depth && OpenConstruct::is_closer(&token) {
+                break;
+            }
+
+            self.eat();
+        }
+    }
+
+    /// Expects the terminator at the end of a statement, or if we are
+    /// recovering from errors, skips up to and including the next one.
+    fn end_statement(&mut self) -> Result<(), Error> {
+        let checkpoint = self.checkpoint();
+        let depth = self.open.len();
+
+        if let Err(error) = self.expect_terminator() {
+            self.report(error)?;
+            self.eat();
+            self.synchronise(depth);
+            self.finish_node_at(checkpoint, SyntaxKind::Error);
+
+            self.eat_if(|token| token.is_terminator())?;
+        }
+
         Ok(())
     }
-}
 
-/// Utilities for reading tokens.
-mod expect
-{
-    use parse::{Token, Error, ErrorKind};
+    /// Parses something as a single node in the syntax tree.
+    fn node<T, F>(&mut self, kind: SyntaxKind, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error> {
+        let checkpoint = self.checkpoint();
+        let result = f(self)?;
 
-    pub fn something(token: Option<Token>) -> Result<Token, Error> {
-        if let Some(token) = token {
-            Ok(token)
-        } else {
-            panic!("expected something but got nothing");
+        self.finish_node_at(checkpoint, kind);
+        Ok(result)
+    }
+
+    /// Parses a node which comments can be attached to.
+    fn commentable<T, F>(&mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error> {
+        let mark = self.commentable_mark();
+        let result = f(self)?;
+
+        self.finish_commentable(mark);
+        Ok(result)
+    }
+
+    /// Gets where a node which comments can be attached to starts.
+    fn commentable_mark(&mut self) -> (usize, Span) {
+        (self.commentable_nodes.len(), self.peek_span())
+    }
+
+    /// Records a node which comments can be attached to, from where it
+    /// starts up to the last token read.
+    ///
+    /// The node goes before any nodes inside it, which were recorded first.
+    fn finish_commentable(&mut self, (index, start): (usize, Span)) {
+        let node = CommentableNode { span: self.span_since(start), last_line: self.last_line };
+        self.commentable_nodes.insert(index, node);
+    }
+
+    /// Gets the current position in the syntax tree.
+    fn checkpoint(&self) -> cst::Checkpoint {
+        self.cst.as_ref().map(cst::Builder::checkpoint).unwrap_or_default()
+    }
+
+    /// Wraps everything parsed since a checkpoint into a syntax node.
+    fn finish_node_at(&mut self, checkpoint: cst::Checkpoint, kind: SyntaxKind) {
+        if let Some(ref mut builder) = self.cst {
+            builder.start_node_at(checkpoint, kind);
+            builder.finish_node();
         }
     }
 
-    pub fn specific(token: Option<Token>, expected: Token) -> Result<Token, Error> {
-        let token = something(token)?;
+    fn eat(&mut self) -> Option<Token> { self.next() }
+
+    fn eat_assert(&mut self, token: &Token) {
+        let read_token = self.next().expect("no more tokens");
+        assert_eq!(read_token, *token, "tokens do not match");
+    }
+
+    /// Runs a function with different expression restrictions.
+    fn with_restrictions<T, F>(&mut self, restrictions: Restrictions, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Self) -> Result<T, Error> {
+        let old_restrictions = self.restrictions;
+        self.restrictions = restrictions;
 
-        if token == expected {
-            Ok(token)
+        let result = f(self);
+        self.restrictions = old_restrictions;
+        result
+    }
+
+    /// Eats new lines and semicolons.
+    fn eat_terminators(&mut self) {
+        while self.peek().map(|token| token.is_terminator()).unwrap_or(false) {
+            self.eat();
+        }
+    }
+
+    /// Checks if we've reached the end of file yet.
+    fn is_eof(&mut self) -> bool {
+        if let Some(token) = self.peek() {
+            if let Token::EndOfFile = token { true } else { false }
         } else {
-            Err(ErrorKind::UnexpectedToken(token, vec![expected]).into())
+            true
+        }
+    }
+
+    fn eat_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(Token::EndOfLine) => {
+                    self.eat();
+                },
+                _ => break,
+            }
         }
     }
 
-    pub fn word(token: Option<Token>) -> Result<String, Error> {
-        let token = self::something(token)?;
+    fn until_token<F>(&mut self, token: Token, f: F) -> Result<(), Error>
+        where F: FnMut(&mut Self) -> Result<(), Error> {
+        self.until(|next_tok| *next_tok == token , f, true)?;
+
+        // We stop at the end of the file if the closing token is missing.
+        if !self.consume(&token) {
+            let error = self.unexpected();
+            self.report(error)?;
+        }
+
+        Ok(())
+    }
+
+    fn until_end<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&mut Self) -> Result<(), Error> {
+        self.until_token(Token::end(), f)
+    }
+
+    fn until<P,F>(&mut self,
+                  mut pred: P,
+                  mut f: F,
+                  eat_trailing_whitespace: bool) -> Result<(), Error>
+        where P: FnMut(&Token) -> bool, F: FnMut(&mut Self) -> Result<(), Error> {
+        // Stop immediately if the predicate is true.
+        if self.peek().map(|tok| pred(&tok)).unwrap_or(true) { return Ok(()) };
+
+        self.eat_whitespace();
+
+        while self.peek().map(|tok| !pred(&tok)).unwrap_or(false) {
+            f(self)?;
+
+            if eat_trailing_whitespace { self.eat_whitespace(); }
+        }
+
+        Ok(())
+    }
 
-        if let Token::Word(word) = token {
-            Ok(word)
-        } else {
-            Err(ErrorKind::UnexpectedToken(token, vec![Token::Word("word".to_owned())]).into())
+    fn eat_if<F>(&mut self,
+                 mut f: F) -> Result<(), Error>
+        where F: FnMut(&Token) -> bool {
+        if let Some(token) = self.peek() {
+            if f(&token) {
+                self.eat(); // Eat the token.
+            }
         }
-    }
 
-    pub fn one_of(token: Option<Token>, whitelist: &[Token]) -> Result<Token, Error> {
-        let token = self::something(token)?;
+        Ok(())
+    }
+}
 
-        if whitelist.iter().any(|allowed_token| *allowed_token == token) {
-            Ok(token)
-        } else {
-            Err(ErrorKind::UnexpectedToken(token, whitelist.to_owned()).into())
-        }
+/// Splits a path ending in constants scoped by a method call, such as
+/// `obj.class::A::B`, into the path of the call and the constants.
+///
+/// Gives back the path if it is not of this form.
+fn split_scoped_constant(path: ast::Path) -> Result<(ast::Path, Vec<ast::Constant>), ast::Path> {
+    let is_scoped_constant = |segment: &&ast::PathSegment| {
+        segment.separator == ast::PathSeparator::DoubleColon &&
+            matches!(segment.kind, ast::PathSegmentKind::Constant(..))
+    };
+
+    let constant_count = path.parts.iter().rev().take_while(is_scoped_constant).count();
+    let scope_count = path.parts.len() - constant_count;
+
+    let scope_is_call = path.parts[..scope_count].iter().any(|segment| {
+        matches!(segment.kind, ast::PathSegmentKind::Identifier(..)) ||
+            matches!(segment.separator, ast::PathSeparator::Dot | ast::PathSeparator::SafeNavigation)
+    });
+
+    if constant_count == 0 || !scope_is_call {
+        return Err(path);
     }
 
-    /// A terminating 'new line' or semicolon.
-    pub fn terminator(token: Option<Token>) -> Result<(), Error> {
-        match token {
-            None |
-            Some(Token::EndOfLine) |
-                Some(Token::Symbol(";")) => Ok(()),
-            Some(token) => Err(ErrorKind::UnexpectedToken(token, vec![Token::Word("terminator".to_owned())]).into()),
+    let mut scope = path.parts;
+    let constants = scope.split_off(scope_count).into_iter().map(|segment| match segment.kind {
+        ast::PathSegmentKind::Constant(constant) => constant,
+        ast::PathSegmentKind::Identifier(..) => unreachable!(),
+    }).collect();
 
-        }
-    }
+    Ok((scope.into_iter().collect(), constants))
 }
 
 #[cfg(test)]
@@ -475,10 +1868,18 @@ mod test
         Parser::new(s.chars()).parse().expect("failed to parse")
     }
 
+    fn parse_statement(s: &str) -> ast::Stmt {
+        match parse(s).items.pop() {
+            Some(ast::Item::Stmt(stmt)) => stmt,
+            item => panic!("expected a statement but got {:?}", item),
+        }
+    }
+
     #[test]
     fn can_parse_single_empty_class() {
         assert_eq!(parse("class Abc\nend"), ast::Program {
-            items: vec![ast::Class::new("Abc").into()]
+            items: vec![ast::Class::new("Abc").into()],
+            ..ast::Program::new()
         });
     }
 
@@ -486,6 +1887,7 @@ mod test
     fn can_parse_multiple_empty_classes() {
         assert_eq!(parse("class Abc\nend\nclass Def\nend"), ast::Program {
             items: vec![ast::Class::new("Abc").into(), ast::Class::new("Def").into()],
+            ..ast::Program::new()
         });
     }
 
@@ -493,6 +1895,7 @@ mod test
     fn can_parse_classes_with_semicolons() {
         assert_eq!(parse("class Abc;end"), ast::Program {
             items: vec![ast::Class::new("Abc").into()],
+            ..ast::Program::new()
         });
     }
 
@@ -500,17 +1903,65 @@ mod test
     fn can_parse_nested_classes() {
         assert_eq!(parse("class Abc; class Def; end; end"), ast::Program {
             items: vec![ast::Class {
-                name: "Abc".to_owned(),
+                name: ast::ConstantPath::new("Abc"),
                 items: vec![ast::Class::new("Def").into()],
                 superclass: None,
-            }.into()]
+                span: Default::default(),
+            }.into()],
+            ..ast::Program::new()
+        });
+    }
+
+    #[test]
+    fn can_parse_class_with_qualified_name() {
+        assert_eq!(parse("class ::Foo::Bar\nend"), ast::Program {
+            items: vec![ast::Class {
+                name: ast::ConstantPath {
+                    scope: ast::ConstantScope::TopLevel,
+                    parts: vec![ast::Constant(Symbol::intern("Foo")), ast::Constant(Symbol::intern("Bar"))],
+                },
+                items: vec![],
+                superclass: None,
+                span: Default::default(),
+            }.into()],
+            ..ast::Program::new()
+        });
+    }
+
+    #[test]
+    fn can_parse_class_with_expression_superclass() {
+        assert_eq!(parse("class Foo < Struct.new(:a)\nend"), ast::Program {
+            items: vec![ast::Class {
+                name: ast::ConstantPath::new("Foo"),
+                items: vec![],
+                superclass: Some(ast::CallExpr {
+                    callee: ast::Path {
+                        parts: vec![
+                            ast::PathSegment {
+                                kind: ast::PathSegmentKind::Constant(ast::Constant(Symbol::intern("Struct"))),
+                                separator: ast::PathSeparator::Root,
+                            },
+                            ast::PathSegment {
+                                kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("new"))),
+                                separator: ast::PathSeparator::Dot,
+                            },
+                        ],
+                    },
+                    arguments: vec![ast::Argument::Positional(ast::SymbolExpr { name: Symbol::intern("a"), span: Default::default() }.into())],
+                    block: None,
+                    span: Default::default(),
+                }.into()),
+                span: Default::default(),
+            }.into()],
+            ..ast::Program::new()
         });
     }
 
     #[test]
     fn can_parse_single_empty_module() {
         assert_eq!(parse("module Abc\nend"), ast::Program {
-            items: vec![ast::Module::new("Abc").into()]
+            items: vec![ast::Module::new("Abc").into()],
+            ..ast::Program::new()
         });
     }
 
@@ -518,9 +1969,26 @@ mod test
     fn can_parse_nested_modules() {
         assert_eq!(parse("module Abc; module Def; end; end"), ast::Program {
             items: vec![ast::Module {
-                name: "Abc".to_owned(),
+                name: ast::ConstantPath::new("Abc"),
                 items: vec![ast::Module::new("Def").into()],
-            }.into()]
+                span: Default::default(),
+            }.into()],
+            ..ast::Program::new()
+        });
+    }
+
+    #[test]
+    fn can_parse_module_with_qualified_name() {
+        assert_eq!(parse("module Api::V1; end"), ast::Program {
+            items: vec![ast::Module {
+                name: ast::ConstantPath {
+                    scope: ast::ConstantScope::Lexical,
+                    parts: vec![ast::Constant(Symbol::intern("Api")), ast::Constant(Symbol::intern("V1"))],
+                },
+                items: vec![],
+                span: Default::default(),
+            }.into()],
+            ..ast::Program::new()
         });
     }
 
@@ -528,12 +1996,41 @@ mod test
     fn can_parse_simple_function() {
         assert_eq!(parse("def abc; end"), ast::Program {
             items: vec![ast::Function {
-                name: "abc".to_owned(),
+                name: Symbol::intern("abc"),
+                singleton: false,
+                // Top-level functions are private.
+                visibility: ast::Visibility::Private,
+                parameters: vec![],
                 statements: vec![],
-            }.into()]
+                span: Default::default(),
+            }.into()],
+            ..ast::Program::new()
         });
     }
 
+    #[test]
+    fn can_parse_function_parameters() {
+        let function = match parse("def abc(a, b = c(1), d = e ? f : 2)
+end").items.pop() {
+            Some(ast::Item::Function(function)) => function,
+            item => panic!("expected a function but got {:?}", item),
+        };
+
+        assert_eq!(function.parameters, vec![
+            ast::Parameter { name: Symbol::intern("a"), default: None },
+            ast::Parameter { name: Symbol::intern("b"), default: Some(Box::new(call("c", vec![positional(integer(1))]))) },
+            ast::Parameter {
+                name: Symbol::intern("d"),
+                default: Some(Box::new(ast::TernaryExpr {
+                    condition: Box::new(call("e", vec![])),
+                    if_true: Box::new(call("f", vec![])),
+                    if_false: Box::new(integer(2)),
+                    span: Default::default(),
+                }.into())),
+            },
+        ]);
+    }
+
     #[test]
     fn can_parse_path() {
         assert_eq!(parse("Abc::def.obt"), ast::Program {
@@ -541,21 +2038,714 @@ mod test
                 callee: ast::Path {
                     parts: vec![
                         ast::PathSegment {
-                            kind: ast::PathSegmentKind::Constant(ast::Constant("Abc".to_owned())),
+                            kind: ast::PathSegmentKind::Constant(ast::Constant(Symbol::intern("Abc"))),
                             separator: ast::PathSeparator::Root,
                         },
                         ast::PathSegment {
-                            kind: ast::PathSegmentKind::Identifier(ast::Identifier("def".to_owned())),
+                            kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("def"))),
                             separator: ast::PathSeparator::DoubleColon,
                         },
                         ast::PathSegment {
-                            kind: ast::PathSegmentKind::Identifier(ast::Identifier("obt".to_owned())),
+                            kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("obt"))),
                             separator: ast::PathSeparator::Dot,
                         },
                     ],
                 },
                 arguments: Vec::new(),
-            }.into()).into()]
+                block: None,
+                span: Default::default(),
+            }.into()).into()],
+            ..ast::Program::new()
+        });
+    }
+
+    #[test]
+    fn can_parse_method_alias() {
+        assert_eq!(parse_statement("alias new_name :old_name"), ast::Stmt::Alias(ast::Alias {
+            kind: ast::AliasKind::Method,
+            new_name: Symbol::intern("new_name"),
+            old_name: Symbol::intern("old_name"),
+            span: Default::default(),
+        }));
+    }
+
+    #[test]
+    fn can_parse_global_variable_alias() {
+        assert_eq!(parse_statement("alias $new $old"), ast::Stmt::Alias(ast::Alias {
+            kind: ast::AliasKind::GlobalVariable,
+            new_name: Symbol::intern("new"),
+            old_name: Symbol::intern("old"),
+            span: Default::default(),
+        }));
+    }
+
+    #[test]
+    fn can_parse_undef() {
+        assert_eq!(parse_statement("undef a, :b"), ast::Stmt::Undef(ast::Undef {
+            names: vec![Symbol::intern("a"), Symbol::intern("b")],
+            span: Default::default(),
+        }));
+    }
+
+    #[test]
+    fn can_parse_defined() {
+        assert_eq!(parse_statement("defined?(:a)"), ast::Stmt::Expr(ast::DefinedExpr {
+            inner: Box::new(ast::SymbolExpr { name: Symbol::intern("a"), span: Default::default() }.into()),
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_parse_begin_and_end_blocks() {
+        assert_eq!(parse("BEGIN { :a }\nEND {\n  :b\n}"), ast::Program {
+            items: vec![
+                ast::BeginBlock {
+                    statements: vec![ast::Stmt::Expr(ast::SymbolExpr { name: Symbol::intern("a"), span: Default::default() }.into())],
+                    span: Default::default(),
+                }.into(),
+                ast::EndBlock {
+                    statements: vec![ast::Stmt::Expr(ast::SymbolExpr { name: Symbol::intern("b"), span: Default::default() }.into())],
+                    span: Default::default(),
+                }.into(),
+            ],
+            ..ast::Program::new()
+        });
+    }
+
+    #[test]
+    fn exposes_data_after_end_marker() {
+        assert_eq!(parse("class Abc; end\n__END__\nclass {{ invalid\n"), ast::Program {
+            items: vec![ast::Class::new("Abc").into()],
+            data: Some("class {{ invalid\n".to_owned()),
+            ..ast::Program::new()
         });
     }
+
+    #[test]
+    fn exposes_pragmas() {
+        let program = parse("# frozen_string_literal: true\nclass Abc; end");
+
+        assert_eq!(program.items, vec![ast::Class::new("Abc").into()]);
+        assert_eq!(program.pragmas, vec![ast::Pragma::FrozenStringLiteral(true)]);
+        assert_eq!(program.comments[0].text, "# frozen_string_literal: true");
+    }
+
+    fn function_visibilities(s: &str) -> Vec<(&'static str, bool, ast::Visibility)> {
+        let items = match parse(s).items.pop() {
+            Some(ast::Item::Class(class)) => class.items,
+            Some(ast::Item::Module(module)) => module.items,
+            item => panic!("expected a class or module but got {:?}", item),
+        };
+
+        items.into_iter().filter_map(|item| match item {
+            ast::Item::Function(f) => Some((f.name.as_str(), f.singleton, f.visibility)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn applies_bare_visibility_modifiers_to_following_functions() {
+        assert_eq!(function_visibilities("class A\ndef a; end\nprivate\ndef b; end\nprotected\ndef c; end\nend"), vec![
+            ("a", false, ast::Visibility::Public),
+            ("b", false, ast::Visibility::Private),
+            ("c", false, ast::Visibility::Protected),
+        ]);
+    }
+
+    #[test]
+    fn applies_visibility_modifiers_to_named_functions() {
+        assert_eq!(function_visibilities("class A\ndef a; end\ndef b; end\nprivate :b\nprivate def c; end\ndef d; end\nend"), vec![
+            ("a", false, ast::Visibility::Public),
+            ("b", false, ast::Visibility::Private),
+            ("c", false, ast::Visibility::Private),
+            ("d", false, ast::Visibility::Public),
+        ]);
+    }
+
+    #[test]
+    fn applies_class_method_visibility_modifiers() {
+        assert_eq!(function_visibilities("class A\ndef self.a; end\ndef a; end\nprivate_class_method :a\nend"), vec![
+            ("a", true, ast::Visibility::Private),
+            ("a", false, ast::Visibility::Public),
+        ]);
+    }
+
+    #[test]
+    fn applies_module_function() {
+        assert_eq!(function_visibilities("module A\nmodule_function\ndef a; end\nend"), vec![
+            ("a", false, ast::Visibility::ModuleFunction),
+        ]);
+    }
+
+    #[test]
+    fn treats_initialize_as_private() {
+        assert_eq!(function_visibilities("class A\ndef initialize; end\nend"), vec![
+            ("initialize", false, ast::Visibility::Private),
+        ]);
+    }
+
+    #[test]
+    fn visibility_modifiers_do_not_leak_out_of_bodies() {
+        assert_eq!(function_visibilities("class A\nclass B\nprivate\nend\ndef a; end\nend"), vec![
+            ("a", false, ast::Visibility::Public),
+        ]);
+    }
+
+    #[test]
+    fn can_parse_zsuper() {
+        assert_eq!(parse_statement("super"), ast::Stmt::Expr(ast::ZSuperExpr { span: Default::default() }.into()));
+    }
+
+    #[test]
+    fn can_parse_super_with_arguments() {
+        assert_eq!(parse_statement("super()"), ast::Stmt::Expr(ast::SuperExpr { arguments: vec![], span: Default::default() }.into()));
+        assert_eq!(parse_statement("super 1, 2"), ast::Stmt::Expr(ast::SuperExpr {
+            arguments: vec![
+                ast::Argument::Positional(ast::IntegerLiteral { value: 1, span: Default::default() }.into()),
+                ast::Argument::Positional(ast::IntegerLiteral { value: 2, span: Default::default() }.into()),
+            ],
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_parse_yield_and_return() {
+        assert_eq!(parse_statement("yield(1)"), ast::Stmt::Expr(ast::YieldExpr {
+            arguments: vec![ast::Argument::Positional(ast::IntegerLiteral { value: 1, span: Default::default() }.into())],
+            span: Default::default(),
+        }.into()));
+        assert_eq!(parse_statement("return"), ast::Stmt::Expr(ast::ReturnExpr { arguments: vec![], span: Default::default() }.into()));
+    }
+
+    fn binary(operator: ast::BinaryOp, lhs: ast::Expr, rhs: ast::Expr) -> ast::Expr {
+        ast::BinaryExpr { operator, lhs: Box::new(lhs), rhs: Box::new(rhs), span: Default::default() }.into()
+    }
+
+    fn integer(value: i64) -> ast::Expr {
+        ast::IntegerLiteral { value, span: Default::default() }.into()
+    }
+
+    #[test]
+    fn respects_operator_precedence() {
+        assert_eq!(parse_statement("1 + 2 * 3 ** 4 ** 5 == 6"), ast::Stmt::Expr(binary(
+            ast::BinaryOp::Equal,
+            binary(ast::BinaryOp::Add, integer(1), binary(
+                ast::BinaryOp::Multiply,
+                integer(2),
+                binary(ast::BinaryOp::Power, integer(3), binary(ast::BinaryOp::Power, integer(4), integer(5))),
+            )),
+            integer(6),
+        )));
+    }
+
+    #[test]
+    fn negation_binds_tighter_than_multiplication() {
+        assert_eq!(parse_statement("-1 * 2"), ast::Stmt::Expr(binary(
+            ast::BinaryOp::Multiply,
+            ast::NegateExpr { inner: Box::new(integer(1)), span: Default::default() }.into(),
+            integer(2),
+        )));
+    }
+
+    #[test]
+    fn can_parse_multi_line_expressions() {
+        assert_eq!(parse_statement("1 +\n  2"), parse_statement("1 + 2"));
+        assert_eq!(parse_statement("foo(1,\n  2\n)"), parse_statement("foo(1, 2)"));
+        assert_eq!(parse_statement("x = \\\n  1"), parse_statement("x = 1"));
+        assert_eq!(parse_statement("foo\n  .bar\n  &.baz"), parse_statement("foo.bar&.baz"));
+    }
+
+    #[test]
+    fn new_lines_still_separate_statements() {
+        assert_eq!(parse("a\nb").items.len(), 2);
+    }
+
+    fn path(name: &str) -> ast::Path {
+        ast::Path {
+            parts: vec![ast::PathSegment {
+                kind: ast::PathSegmentKind::new(Symbol::intern(name)),
+                separator: ast::PathSeparator::Root,
+            }],
+        }
+    }
+
+    fn call(name: &str, arguments: Vec<ast::Argument>) -> ast::Expr {
+        ast::CallExpr {
+            callee: path(name),
+            arguments,
+            block: None,
+            span: Default::default(),
+        }.into()
+    }
+
+    #[test]
+    fn can_parse_ternary() {
+        let expected = ast::Stmt::Expr(ast::TernaryExpr {
+            condition: Box::new(call("x", vec![])),
+            if_true: Box::new(call("a", vec![])),
+            if_false: Box::new(call("b", vec![])),
+            span: Default::default(),
+        }.into());
+
+        assert_eq!(parse_statement("x ? a : b"), expected);
+        assert_eq!(parse_statement("x ?a:b"), expected);
+    }
+
+    #[test]
+    fn can_parse_character_literal_argument() {
+        assert_eq!(parse_statement("puts ?a"), ast::Stmt::Expr(call("puts", vec![
+            ast::Argument::Positional(ast::CharacterLiteral { value: 'a', span: Default::default() }.into()),
+        ])));
+    }
+
+    #[test]
+    fn can_parse_not_equal_after_method_name() {
+        assert_eq!(parse_statement("foo!=bar"), ast::Stmt::Expr(binary(
+            ast::BinaryOp::NotEqual,
+            call("foo", vec![]),
+            call("bar", vec![]),
+        )));
+    }
+
+    fn local(name: &str) -> ast::AssignTarget {
+        ast::AssignTarget::Local(ast::Identifier(Symbol::intern(name)))
+    }
+
+    fn positional(expr: ast::Expr) -> ast::Argument {
+        ast::Argument::Positional(expr)
+    }
+
+    #[test]
+    fn can_parse_variable_assignments() {
+        let target = |s: &str| match parse_statement(s) {
+            ast::Stmt::Expr(ast::Expr::Assignment(assignment)) => assignment.assignee,
+            stmt => panic!("expected an assignment but got {:?}", stmt),
+        };
+
+        assert_eq!(target("a = 1"), local("a"));
+        assert_eq!(target("@a = 1"), ast::AssignTarget::InstanceVariable(ast::Identifier(Symbol::intern("a"))));
+        assert_eq!(target("@@a = 1"), ast::AssignTarget::ClassVariable(ast::Identifier(Symbol::intern("a"))));
+        assert_eq!(target("$a = 1"), ast::AssignTarget::GlobalVariable(ast::Identifier(Symbol::intern("a"))));
+    }
+
+    fn constants(names: &[&str]) -> Vec<ast::Constant> {
+        names.iter().map(|&name| ast::Constant(Symbol::intern(name))).collect()
+    }
+
+    #[test]
+    fn can_parse_constant_assignments() {
+        let assignment = |scope, names: &[&str]| ast::Stmt::Expr(ast::ConstantAssignmentExpr {
+            constant: ast::ConstantPath { scope, parts: constants(names) },
+            value: Box::new(integer(1)),
+            span: Default::default(),
+        }.into());
+
+        assert_eq!(parse_statement("FOO = 1"), assignment(ast::ConstantScope::Lexical, &["FOO"]));
+        assert_eq!(parse_statement("Foo::BAR = 1"), assignment(ast::ConstantScope::Lexical, &["Foo", "BAR"]));
+        assert_eq!(parse_statement("::BAZ = 1"), assignment(ast::ConstantScope::TopLevel, &["BAZ"]));
+    }
+
+    #[test]
+    fn can_parse_top_level_constant() {
+        assert_eq!(parse_statement("puts(::Foo::Bar)"), ast::Stmt::Expr(call("puts", vec![
+            positional(ast::ConstantExpr {
+                path: ast::ConstantPath { scope: ast::ConstantScope::TopLevel, parts: constants(&["Foo", "Bar"]) },
+                span: Default::default(),
+            }.into()),
+        ])));
+    }
+
+    #[test]
+    fn can_parse_constant_scoped_by_expression() {
+        let class_of_obj = ast::CallExpr {
+            callee: ast::Path {
+                parts: vec![
+                    ast::PathSegment {
+                        kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("obj"))),
+                        separator: ast::PathSeparator::Root,
+                    },
+                    ast::PathSegment {
+                        kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("class"))),
+                        separator: ast::PathSeparator::Dot,
+                    },
+                ],
+            },
+            arguments: Vec::new(),
+            block: None,
+            span: Default::default(),
+        };
+
+        assert_eq!(parse_statement("obj.class::CONST"), ast::Stmt::Expr(ast::ConstantExpr {
+            path: ast::ConstantPath {
+                scope: ast::ConstantScope::Expr(Box::new(class_of_obj.into())),
+                parts: constants(&["CONST"]),
+            },
+            span: Default::default(),
+        }.into()));
+
+        assert_eq!(parse_statement("foo(1)::A::B"), ast::Stmt::Expr(ast::ConstantExpr {
+            path: ast::ConstantPath {
+                scope: ast::ConstantScope::Expr(Box::new(call("foo", vec![positional(integer(1))]))),
+                parts: constants(&["A", "B"]),
+            },
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_parse_attribute_and_index_assignments() {
+        assert_eq!(parse_statement("a.b, c[1] = d"), ast::Stmt::Expr(ast::MultipleAssignmentExpr {
+            targets: vec![
+                ast::AssignTarget::Attribute(ast::AttributeTarget {
+                    receiver: Box::new(call("a", vec![])),
+                    name: Symbol::intern("b"),
+                }),
+                ast::AssignTarget::Index(ast::IndexExpr {
+                    receiver: Box::new(call("c", vec![])),
+                    arguments: vec![positional(integer(1))],
+                    span: Default::default(),
+                }),
+            ],
+            values: vec![call("d", vec![])],
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_parse_multiple_assignment_with_splats_and_groups() {
+        assert_eq!(parse_statement("*a, (b, *), c, = 1, 2"), ast::Stmt::Expr(ast::MultipleAssignmentExpr {
+            targets: vec![
+                ast::AssignTarget::Splat(Some(Box::new(local("a")))),
+                ast::AssignTarget::Group(vec![local("b"), ast::AssignTarget::Splat(None)]),
+                local("c"),
+            ],
+            values: vec![integer(1), integer(2)],
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn cannot_assign_to_literal() {
+        assert!(Parser::new("1 = 2".chars()).parse().is_err());
+        assert!(Parser::new("a(1) = 2".chars()).parse().is_err());
+    }
+
+    #[test]
+    fn can_parse_for_loop() {
+        let expected = ast::Stmt::Expr(ast::ForExpr {
+            targets: vec![local("a"), local("b")],
+            iterable: Box::new(call("x", vec![])),
+            statements: vec![ast::Stmt::Expr(call("y", vec![]))],
+            span: Default::default(),
+        }.into());
+
+        assert_eq!(parse_statement("for a, b in x do y end"), expected);
+        assert_eq!(parse_statement("for a, b in x\n  y\nend"), expected);
+    }
+
+    #[test]
+    fn can_parse_begin_with_rescue_target() {
+        assert_eq!(parse_statement("begin\n  x\nrescue A, B => @e\n  y\nelse\n  z\nensure\n  w\nend"),
+                   ast::Stmt::Expr(ast::BeginExpr {
+            statements: vec![ast::Stmt::Expr(call("x", vec![]))],
+            rescue_clauses: vec![ast::RescueClause {
+                exceptions: vec![call("A", vec![]), call("B", vec![])],
+                target: Some(ast::AssignTarget::InstanceVariable(ast::Identifier(Symbol::intern("e")))),
+                statements: vec![ast::Stmt::Expr(call("y", vec![]))],
+            }],
+            else_statements: Some(vec![ast::Stmt::Expr(call("z", vec![]))]),
+            ensure_statements: Some(vec![ast::Stmt::Expr(call("w", vec![]))]),
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_parse_block_parameters() {
+        let expected = ast::Stmt::Expr(ast::CallExpr {
+            callee: path("each"),
+            arguments: Vec::new(),
+            block: Some(ast::Block {
+                parameters: vec![
+                    local("a"),
+                    ast::AssignTarget::Group(vec![local("b"), local("c")]),
+                    ast::AssignTarget::Splat(Some(Box::new(local("d")))),
+                ],
+                statements: vec![ast::Stmt::Expr(call("b", vec![]))],
+            }),
+            span: Default::default(),
+        }.into());
+
+        assert_eq!(parse_statement("each { |a, (b, c), *d| b }"), expected);
+        assert_eq!(parse_statement("each do |a, (b, c), *d|\n  b\nend"), expected);
+    }
+
+    #[test]
+    fn do_block_binds_to_command_call() {
+        assert_eq!(parse_statement("foo bar do end"), ast::Stmt::Expr(ast::CallExpr {
+            callee: path("foo"),
+            arguments: vec![positional(call("bar", vec![]))],
+            block: Some(ast::Block { parameters: Vec::new(), statements: Vec::new() }),
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_call_methods_named_after_keywords() {
+        assert_eq!(parse_statement("self.class"), ast::Stmt::Expr(ast::CallExpr {
+            callee: ast::Path {
+                parts: vec![
+                    ast::PathSegment {
+                        kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("self"))),
+                        separator: ast::PathSeparator::Root,
+                    },
+                    ast::PathSegment {
+                        kind: ast::PathSegmentKind::Identifier(ast::Identifier(Symbol::intern("class"))),
+                        separator: ast::PathSeparator::Dot,
+                    },
+                ],
+            },
+            arguments: Vec::new(),
+            block: None,
+            span: Default::default(),
+        }.into()));
+    }
+
+    #[test]
+    fn can_define_methods_named_after_keywords() {
+        let names: Vec<_> = parse("def end\nend\ndef self.name=(value)\nend").items.into_iter().map(|item| match item {
+            ast::Item::Function(function) => function.name,
+            item => panic!("expected a function but got {:?}", item),
+        }).collect();
+
+        assert_eq!(names, vec![Symbol::intern("end"), Symbol::intern("name=")]);
+    }
+
+    #[test]
+    fn can_parse_label_arguments() {
+        assert_eq!(parse_statement("foo(a: 1, if: b)"), ast::Stmt::Expr(call("foo", vec![
+            positional(ast::KeyValueExpr { key: Symbol::intern("a"), value: Box::new(integer(1)), span: Default::default() }.into()),
+            positional(ast::KeyValueExpr { key: Symbol::intern("if"), value: Box::new(call("b", vec![])), span: Default::default() }.into()),
+        ])));
+    }
+
+    #[test]
+    fn cannot_use_keyword_as_expression() {
+        assert!(Parser::new("a = end".chars()).parse().is_err());
+    }
+
+    fn parse_with_recovery(s: &str) -> RecoveredParse {
+        Parser::new(s.chars()).parse_with_recovery()
+    }
+
+    #[test]
+    fn recovers_from_errors_in_statements() {
+        let parse = parse_with_recovery("foo 1 2\nbar\n)\nbaz 3\n");
+
+        assert_eq!(parse.errors.len(), 2);
+        assert_eq!(parse.program.items, vec![
+            ast::Stmt::Expr(call("foo", vec![positional(integer(1))])).into(),
+            ast::Stmt::Expr(call("bar", vec![])).into(),
+            ast::Stmt::Expr(call("baz", vec![positional(integer(3))])).into(),
+        ]);
+        assert!(Parser::new("foo 1 2\nbar\n".chars()).parse().is_err());
+    }
+
+    #[test]
+    fn fills_in_missing_expressions() {
+        let parse = parse_with_recovery("a = \n");
+
+        assert_eq!(parse.errors.len(), 1);
+        assert_eq!(parse.program.items, vec![ast::Stmt::Expr(ast::AssignmentExpr {
+            assignee: local("a"),
+            value: Box::new(ast::MissingExpr { span: Default::default() }.into()),
+            span: Default::default(),
+        }.into()).into()]);
+    }
+
+    #[test]
+    fn keeps_enclosing_blocks_when_recovering() {
+        let parse = parse_with_recovery("class A\n  def b\n    1 +\n  end\n  def c(d e)\n    f\n  end\nend\n");
+
+        assert_eq!(parse.errors.len(), 2);
+        let class = match parse.program.items[0] {
+            ast::Item::Class(ref class) => class,
+            ref item => panic!("expected a class but got {:?}", item),
+        };
+        match class.items[0] {
+            ast::Item::Function(ref function) => assert_eq!(function.statements, vec![
+                ast::Stmt::Expr(binary(ast::BinaryOp::Add, integer(1), ast::MissingExpr { span: Default::default() }.into())),
+            ]),
+            ref item => panic!("expected a function but got {:?}", item),
+        }
+        assert_eq!(class.items[1], ast::Stmt::Expr(ast::ErrorExpr { span: Default::default() }.into()).into());
+        assert_eq!(class.items.len(), 2);
+    }
+
+    #[test]
+    fn recovers_from_stray_and_missing_ends() {
+        let parse = parse_with_recovery("end\nfoo\n");
+        assert_eq!(parse.errors.len(), 1);
+        assert_eq!(parse.program.items, vec![
+            ast::Stmt::Expr(ast::ErrorExpr { span: Default::default() }.into()).into(),
+            ast::Stmt::Expr(call("foo", vec![])).into(),
+        ]);
+
+        let parse = parse_with_recovery("class A\n  foo\n");
+        assert_eq!(parse.errors.len(), 1);
+        assert!(matches!(parse.program.items[..], [ast::Item::Class(..)]));
+    }
+
+    #[test]
+    fn recovers_from_half_typed_and_unsupported_code() {
+        let parse = parse_with_recovery("foo 1,");
+        assert_eq!(parse.errors.len(), 1);
+        assert_eq!(parse.program.items, vec![
+            ast::Stmt::Expr(call("foo", vec![positional(integer(1)), positional(ast::MissingExpr { span: Default::default() }.into())])).into(),
+        ]);
+
+        for source in &["def foo(*a); end", "x = `ls`", "a = 1 ; b = ~x\nc"] {
+            let parse = parse_with_recovery(source);
+            assert_eq!(parse.errors.len(), 1, "{}", source);
+        }
+    }
+
+    #[test]
+    fn records_where_nodes_are() {
+        let source = "class A < B\n  def x\n    foo(1 + -2)\n  end\nend\n";
+        let program = parse(source);
+        let text = |span: Option<Span>| span.expect("node has no span").text(source);
+
+        assert_eq!(text(program.items[0].span()), "class A < B\n  def x\n    foo(1 + -2)\n  end\nend");
+        let class = match program.items[0] {
+            ast::Item::Class(ref class) => class,
+            ref item => panic!("expected a class but got {:?}", item),
+        };
+        assert_eq!(text(class.superclass.as_ref().unwrap().span()), "B");
+        assert_eq!(text(class.items[0].span()), "def x\n    foo(1 + -2)\n  end");
+
+        let function = match class.items[0] {
+            ast::Item::Function(ref function) => function,
+            ref item => panic!("expected a function but got {:?}", item),
+        };
+        assert_eq!(text(function.statements[0].span()), "foo(1 + -2)");
+        let argument = match function.statements[0] {
+            ast::Stmt::Expr(ast::Expr::Call(ref call)) => match call.arguments[0] {
+                ast::Argument::Positional(ref argument) => argument,
+                ref argument => panic!("expected a positional argument but got {:?}", argument),
+            },
+            ref stmt => panic!("expected a call but got {:?}", stmt),
+        };
+        assert_eq!(text(argument.span()), "1 + -2");
+        match *argument {
+            ast::Expr::Binary(ref binary) => assert_eq!(text(binary.rhs.span()), "-2"),
+            ref expr => panic!("expected a binary expression but got {:?}", expr),
+        }
+
+        assert_eq!(ast::Class::new("A").span.get(), None);
+    }
+
+    fn expected(s: &str) -> (Token, Vec<TokenKind>) {
+        match *Parser::new(s.chars()).parse().unwrap_err().kind() {
+            ErrorKind::UnexpectedToken(ref found, ref expected, _) => (found.clone(), expected.clone()),
+            ref kind => panic!("expected an unexpected token error but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn reports_every_token_tried_at_an_error() {
+        assert_eq!(expected("def foo(a b)\nend"),
+                   (Token::Identifier(Symbol::intern("b")), vec![TokenKind::Symbol("="), TokenKind::Symbol(","), TokenKind::Symbol(")")]));
+        assert_eq!(expected("foo 1 2"),
+                   (Token::Integer(2), vec![TokenKind::Symbol(","), TokenKind::Terminator]));
+        assert_eq!(expected("class foo\nend"),
+                   (Token::Identifier(Symbol::intern("foo")), vec![TokenKind::Symbol("::"), TokenKind::Constant]));
+        assert_eq!(expected("a = "), (Token::EndOfFile, vec![TokenKind::Expression]));
+    }
+
+    #[test]
+    fn describes_expected_tokens() {
+        let error = Parser::new("foo(1\n".chars()).parse().unwrap_err();
+        assert_eq!(error.to_string(), "expected one of `,` or `)`, found newline");
+        assert_eq!(error.span(), Some(Span::new(5, 6)));
+    }
+
+    fn unclosed(s: &str) -> (String, usize, Option<usize>) {
+        match *Parser::new(s.chars()).parse().unwrap_err().kind() {
+            ErrorKind::Unclosed(ref construct, _, _, probable_line) => {
+                (construct.description.clone(), construct.line, probable_line)
+            },
+            ref kind => panic!("expected an unclosed construct error but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn reports_constructs_left_open_at_the_end_of_the_file() {
+        assert_eq!(unclosed("class Foo::Bar\n  def self.baz\n    1\n  end\n"), ("class Foo::Bar".to_owned(), 1, None));
+        assert_eq!(unclosed("def self.baz(a,\n"), ("(".to_owned(), 1, None));
+        assert_eq!(unclosed("puts 'hi\nfoo\n"), ("'".to_owned(), 1, None));
+    }
+
+    #[test]
+    fn uses_indentation_to_find_the_missing_end() {
+        let src = "class Foo\n  def bar\n    baz do\n      1\n  end\nend\n";
+        assert_eq!(unclosed(src), ("do".to_owned(), 3, Some(5)));
+    }
+
+    #[test]
+    fn reports_constructs_closed_by_an_outer_closer() {
+        assert_eq!(unclosed("foo(a[0)\n"), ("[".to_owned(), 1, None));
+    }
+
+    #[test]
+    fn can_parse_fragments() {
+        assert_eq!(super::parse_expression(Parser::new("1 + 2\n".chars())).unwrap(),
+                   binary(ast::BinaryOp::Add, integer(1), integer(2)));
+        assert_eq!(super::parse_statement("\nreturn").unwrap(),
+                   ast::Stmt::Expr(ast::ReturnExpr { arguments: vec![], span: Default::default() }.into()));
+        assert_eq!(parse_method_body("1\n2\n").unwrap(),
+                   vec![ast::Stmt::Expr(integer(1)), ast::Stmt::Expr(integer(2))]);
+
+        match parse_item("module Foo; end").unwrap() {
+            ast::Item::Module(module) => assert!(module.items.is_empty()),
+            item => panic!("expected a module but got {:?}", item),
+        }
+    }
+
+    #[test]
+    fn fragments_must_make_up_the_whole_input() {
+        let error = super::parse_expression("1\n2").unwrap_err();
+        match *error.kind() {
+            ErrorKind::UnexpectedToken(ref found, ref expected, _) => {
+                assert_eq!(*found, Token::Integer(2));
+                assert!(expected.contains(&TokenKind::EndOfFile));
+            },
+            ref kind => panic!("expected an unexpected token error but got {:?}", kind),
+        }
+
+        assert!(super::parse_statement("foo; bar").is_err());
+        assert!(parse_item("class Foo; end; 1").is_err());
+    }
+
+    #[test]
+    fn can_parse_strings_bytes_and_readers() {
+        let expected = binary(ast::BinaryOp::Add, integer(1), integer(2));
+
+        assert_eq!(super::parse_expression("1 + 2").unwrap(), expected);
+        assert_eq!(super::parse_expression(Parser::from_bytes(b"\xEF\xBB\xBF1 + 2").unwrap()).unwrap(), expected);
+        assert_eq!(super::parse_expression(Parser::from_reader(&b"1 + 2"[..]).unwrap()).unwrap(), expected);
+        assert!(Parser::from_bytes(b"'\xFF'").is_err());
+    }
+
+    #[test]
+    fn errors_from_files_say_which_file_they_are_in() {
+        let path = ::std::env::temp_dir().join("rubic-errors-from-files.rb");
+        fs::write(&path, "foo(1\n").unwrap();
+
+        let error = parse_file(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.file(), Some(path.as_path()));
+        assert_eq!(error.code(), Some("E0001"));
+        assert_eq!(error.span(), Some(Span::new(5, 6)));
+    }
 }
@@ -0,0 +1,222 @@
+use parse::{Error, ErrorKind};
+use parse::tokenize::magic_comment;
+
+use std::{io, str};
+
+/// The byte order mark, which marks a file as UTF-8.
+const BYTE_ORDER_MARK: &[u8] = b"\xEF\xBB\xBF";
+
+/// An encoding which source code can be written in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding
+{
+    /// `UTF-8`, the default.
+    Utf8,
+    /// `US-ASCII`, where every byte must be below 128.
+    Ascii,
+    /// `ASCII-8BIT`, also called `BINARY`, where bytes above 127 have no
+    /// meaning and so are read as the characters with the same code.
+    Binary,
+    /// `ISO-8859-1`, where every byte is the character with the same code.
+    Latin1,
+}
+
+/// Source code, decoded from the encoding it was written in.
+///
+/// The spans of tokens and errors are offsets into the decoded text, which
+/// for UTF-8 files is the file itself without its byte order mark.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Source
+{
+    text: String,
+    encoding: Encoding,
+}
+
+/// The characters of some source code.
+#[derive(Clone, Debug)]
+pub struct IntoChars
+{
+    text: String,
+    offset: usize,
+}
+
+impl Encoding
+{
+    /// Looks up an encoding by one of the names Ruby accepts for it, ignoring
+    /// case and Emacs' line ending suffixes such as `-unix`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let name = ["-unix", "-dos", "-mac"].iter()
+            .find_map(|suffix| name.strip_suffix(suffix))
+            .unwrap_or(&name);
+
+        match name {
+            "utf-8" | "utf8" | "cp65001" => Some(Encoding::Utf8),
+            "us-ascii" | "ascii" | "ansi_x3.4-1968" | "646" => Some(Encoding::Ascii),
+            "ascii-8bit" | "binary" => Some(Encoding::Binary),
+            "iso-8859-1" | "iso8859-1" | "latin1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    /// Gets the name Ruby gives the encoding.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Ascii => "US-ASCII",
+            Encoding::Binary => "ASCII-8BIT",
+            Encoding::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    /// Encodes text which was decoded from this encoding.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 | Encoding::Ascii => text.as_bytes().to_vec(),
+            Encoding::Binary | Encoding::Latin1 => text.chars().map(|c| c as u8).collect(),
+        }
+    }
+}
+
+impl Source
+{
+    /// Decodes source code, detecting its encoding from a byte order mark or
+    /// an `encoding` magic comment.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if let Some(bytes) = bytes.strip_prefix(BYTE_ORDER_MARK) {
+            return Source::decode(bytes, Encoding::Utf8);
+        }
+
+        let encoding = match magic_encoding(bytes) {
+            Some(name) => match Encoding::from_name(&name) {
+                Some(encoding) => encoding,
+                None => return Err(ErrorKind::UnsupportedEncoding(name).into()),
+            },
+            None => Encoding::Utf8,
+        };
+
+        Source::decode(bytes, encoding)
+    }
+
+    /// Reads and decodes source code.
+    pub fn from_reader<R>(mut reader: R) -> Result<Self, Error>
+        where R: io::Read {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Source::from_bytes(&bytes)
+    }
+
+    /// Decodes source code written in a certain encoding.
+    pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<Self, Error> {
+        let text = match encoding {
+            Encoding::Utf8 => str::from_utf8(bytes).map_err(|error| {
+                invalid_bytes(bytes, encoding, error.valid_up_to())
+            })?.to_owned(),
+            Encoding::Ascii => match bytes.iter().position(|byte| !byte.is_ascii()) {
+                Some(offset) => return Err(invalid_bytes(bytes, encoding, offset)),
+                None => bytes.iter().map(|&byte| byte as char).collect(),
+            },
+            Encoding::Binary | Encoding::Latin1 => bytes.iter().map(|&byte| byte as char).collect(),
+        };
+
+        Ok(Source { text, encoding })
+    }
+
+    /// Gets the decoded text.
+    pub fn text(&self) -> &str { &self.text }
+
+    /// Gets the encoding the source was written in.
+    pub fn encoding(&self) -> Encoding { self.encoding }
+}
+
+impl IntoIterator for Source
+{
+    type Item = char;
+    type IntoIter = IntoChars;
+
+    fn into_iter(self) -> IntoChars {
+        IntoChars { text: self.text, offset: 0 }
+    }
+}
+
+impl Iterator for IntoChars
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.text[self.offset..].chars().next()?;
+        self.offset += c.len_utf8();
+
+        Some(c)
+    }
+}
+
+impl From<String> for Source
+{
+    fn from(text: String) -> Self {
+        Source { text, encoding: Encoding::Utf8 }
+    }
+}
+
+/// Finds the encoding named by a magic comment, which must be on the first
+/// line, or the second after a shebang.
+///
+/// Only the comments are looked at, as the source cannot be tokenized until
+/// it has been decoded.
+fn magic_encoding(bytes: &[u8]) -> Option<String> {
+    let lines = if bytes.starts_with(b"#!") { 2 } else { 1 };
+
+    bytes.split(|&byte| byte == b'\n').take(lines)
+        // Magic comments are plain ASCII, so anything else can be ignored.
+        .map(String::from_utf8_lossy)
+        .take_while(|line| line.trim_start().starts_with('#'))
+        .find_map(|line| magic_comment::encoding(line.trim_start()))
+}
+
+/// Creates an error for bytes which are invalid in an encoding.
+fn invalid_bytes(bytes: &[u8], encoding: Encoding, offset: usize) -> Error {
+    let line = bytes[..offset].iter().filter(|&&byte| byte == b'\n').count() + 1;
+    ErrorKind::InvalidEncoding(encoding.name(), offset, line).into()
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn detects_encodings_from_magic_comments() {
+        assert_eq!(Source::from_bytes(b"x = 1\n").unwrap().encoding(), Encoding::Utf8);
+        assert_eq!(Source::from_bytes(b"# encoding: ascii-8bit\n\"\xFF\"\n").unwrap().encoding(), Encoding::Binary);
+        assert_eq!(Source::from_bytes(b"#!/usr/bin/env ruby\n# -*- coding: iso-8859-1-unix -*-\n").unwrap().encoding(), Encoding::Latin1);
+
+        // Only the first line counts without a shebang.
+        assert_eq!(Source::from_bytes(b"\n# encoding: us-ascii\n").unwrap().encoding(), Encoding::Utf8);
+
+        // Code is never tokenized, and a comment after it is not magic.
+        assert_eq!(Source::from_bytes(b"x = `ls`\n").unwrap().encoding(), Encoding::Utf8);
+        assert_eq!(Source::from_bytes(b"a = 1 ; b = ~x # encoding: latin1\n").unwrap().encoding(), Encoding::Utf8);
+    }
+
+    #[test]
+    fn decodes_text() {
+        assert_eq!(Source::from_bytes(b"\xEF\xBB\xBFputs 1\n").unwrap().text(), "puts 1\n");
+        assert_eq!(Source::from_bytes(b"# encoding: latin1\n'\xE9'\n").unwrap().text(), "# encoding: latin1\n'\u{e9}'\n");
+        assert_eq!(Source::from(String::from("puts 1")).into_iter().collect::<String>(), "puts 1");
+        assert_eq!(Encoding::Latin1.encode("'\u{e9}'"), b"'\xE9'");
+    }
+
+    #[test]
+    fn rejects_invalid_source() {
+        match *Source::from_bytes(b"x = 1\ny = '\xFF'\n").unwrap_err().kind() {
+            ErrorKind::InvalidEncoding("UTF-8", 11, 2) => (),
+            ref kind => panic!("expected an invalid encoding error but got {:?}", kind),
+        }
+
+        match *Source::from_bytes(b"# encoding: shift_jis\n").unwrap_err().kind() {
+            ErrorKind::UnsupportedEncoding(ref name) => assert_eq!(name, "shift_jis"),
+            ref kind => panic!("expected an unsupported encoding error but got {:?}", kind),
+        }
+    }
+}
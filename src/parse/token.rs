@@ -1,41 +1,133 @@
+use parse::Keyword;
+use symbol::Symbol;
 use std::fmt;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Token
 {
-    /// A standalone work.
-    Word(String),
+    /// A reserved word.
+    Keyword(Keyword),
+    /// A name beginning with an uppercase letter.
+    Constant(Symbol),
+    /// A variable or method name, including any `$`, `@` or `@@` sigil.
+    Identifier(Symbol),
+    /// A method name ending in `?`, `!` or, in a definition, `=`.
+    MethodName(Symbol),
+    /// A name followed by a colon, as in `key: value`.
+    ///
+    /// The colon is not part of the name.
+    Label(Symbol),
     /// A symbol.
     Symbol(&'static str),
     /// A string in quotes.
     String(String),
     /// An integer.
     Integer(i64),
+    /// A character literal, such as `?a`.
+    Character(char),
+    /// A character which cannot begin any token, such as a backtick, which
+    /// the parser reports as unexpected.
+    Unknown(char),
     EndOfLine,
     EndOfFile,
 }
 
+/// A kind of token, used to describe what the parser expected to find.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind
+{
+    /// A specific keyword.
+    Keyword(Keyword),
+    /// A specific symbol.
+    Symbol(&'static str),
+    Constant,
+    Identifier,
+    MethodName,
+    Label,
+    String,
+    Integer,
+    Character,
+    Unknown,
+    /// A new line or semicolon.
+    Terminator,
+    EndOfFile,
+    /// Anything that can begin an expression.
+    Expression,
+}
+
 impl Token
 {
     /// The `class` keyword.
-    pub fn class() -> Token { Token::Word("class".to_owned()) }
-    pub fn module() -> Token { Token::Word("module".to_owned()) }
-    pub fn def() -> Token { Token::Word("def".to_owned()) }
-    pub fn end() -> Token { Token::Word("end".to_owned()) }
+    pub fn class() -> Token { Token::Keyword(Keyword::Class) }
+    pub fn module() -> Token { Token::Keyword(Keyword::Module) }
+    pub fn def() -> Token { Token::Keyword(Keyword::Def) }
+    pub fn end() -> Token { Token::Keyword(Keyword::End) }
+    pub fn self_() -> Token { Token::Keyword(Keyword::Self_) }
+    pub fn super_() -> Token { Token::Keyword(Keyword::Super) }
+    pub fn yield_() -> Token { Token::Keyword(Keyword::Yield) }
+    pub fn return_() -> Token { Token::Keyword(Keyword::Return) }
+    pub fn alias() -> Token { Token::Keyword(Keyword::Alias) }
+    pub fn undef() -> Token { Token::Keyword(Keyword::Undef) }
+    pub fn defined() -> Token { Token::Keyword(Keyword::Defined) }
+    pub fn do_() -> Token { Token::Keyword(Keyword::Do) }
+    pub fn for_() -> Token { Token::Keyword(Keyword::For) }
+    pub fn in_() -> Token { Token::Keyword(Keyword::In) }
+    pub fn begin() -> Token { Token::Keyword(Keyword::Begin) }
+    pub fn rescue() -> Token { Token::Keyword(Keyword::Rescue) }
+    pub fn else_() -> Token { Token::Keyword(Keyword::Else) }
+    pub fn ensure() -> Token { Token::Keyword(Keyword::Ensure) }
+    pub fn then() -> Token { Token::Keyword(Keyword::Then) }
+    /// The `BEGIN` keyword.
+    pub fn begin_block() -> Token { Token::Keyword(Keyword::BeginBlock) }
+    /// The `END` keyword.
+    pub fn end_block() -> Token { Token::Keyword(Keyword::EndBlock) }
 
     pub fn left_paren() -> Token { Token::Symbol("(") }
     pub fn right_paren() -> Token { Token::Symbol(")") }
+    pub fn left_brace() -> Token { Token::Symbol("{") }
+    pub fn right_brace() -> Token { Token::Symbol("}") }
+    pub fn left_bracket() -> Token { Token::Symbol("[") }
+    pub fn right_bracket() -> Token { Token::Symbol("]") }
+    pub fn pipe() -> Token { Token::Symbol("|") }
 
     pub fn less_than() -> Token { Token::Symbol("<") }
     pub fn greater_than() -> Token { Token::Symbol(">") }
 
+    pub fn dot() -> Token { Token::Symbol(".") }
     pub fn colon() -> Token { Token::Symbol(":") }
+    pub fn question_mark() -> Token { Token::Symbol("?") }
+    pub fn double_colon() -> Token { Token::Symbol("::") }
     pub fn comma() -> Token { Token::Symbol(",") }
     pub fn negate() -> Token { Token::Symbol("-") }
 
     pub fn rocket() -> Token { Token::Symbol("=>") }
     pub fn equals() -> Token { Token::Symbol("=") }
 
+    /// Checks if this token can begin an argument to a method called
+    /// without parentheses, as in `puts "hello"`.
+    ///
+    /// Operators never begin an argument, so `foo - 1` is a subtraction.
+    pub fn can_begin_argument(&self) -> bool {
+        match *self {
+            Token::Keyword(keyword) => keyword.can_begin_argument(),
+            Token::Constant(..) | Token::Identifier(..) | Token::MethodName(..) | Token::Label(..) => true,
+            Token::String(..) | Token::Integer(..) | Token::Character(..) => true,
+            Token::Symbol(":") | Token::Symbol("(") => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this token can be the last token of an expression.
+    pub fn can_end_expression(&self) -> bool {
+        match *self {
+            Token::Keyword(keyword) => !keyword.expects_operand(),
+            Token::Constant(..) | Token::Identifier(..) | Token::MethodName(..) => true,
+            Token::String(..) | Token::Integer(..) | Token::Character(..) => true,
+            Token::Symbol(")") | Token::Symbol("]") | Token::Symbol("}") => true,
+            _ => false,
+        }
+    }
+
     pub fn is_terminator(&self) -> bool {
         match *self {
             Token::EndOfLine |
@@ -45,16 +137,84 @@ impl Token
     }
 }
 
+impl TokenKind
+{
+    /// Gets the kind of a token.
+    pub fn of(token: &Token) -> Self {
+        match *token {
+            Token::Keyword(keyword) => TokenKind::Keyword(keyword),
+            Token::Symbol(";") | Token::EndOfLine => TokenKind::Terminator,
+            Token::Symbol(symbol) => TokenKind::Symbol(symbol),
+            Token::Constant(..) => TokenKind::Constant,
+            Token::Identifier(..) => TokenKind::Identifier,
+            Token::MethodName(..) => TokenKind::MethodName,
+            Token::Label(..) => TokenKind::Label,
+            Token::String(..) => TokenKind::String,
+            Token::Integer(..) => TokenKind::Integer,
+            Token::Character(..) => TokenKind::Character,
+            Token::Unknown(..) => TokenKind::Unknown,
+            Token::EndOfFile => TokenKind::EndOfFile,
+        }
+    }
+
+    /// Checks if a token is of this kind.
+    pub fn matches(self, token: &Token) -> bool {
+        match self {
+            TokenKind::Expression => token.can_begin_argument(),
+            kind => kind == TokenKind::of(token),
+        }
+    }
+}
+
+/// Escapes a character for a character literal, so that `?` followed by it
+/// reads back as the same character.
+///
+/// Only printable ASCII other than a backslash is left as it is. A space is
+/// `\s`, as `? ` is the ternary operator.
+pub fn escape_character(c: char) -> String {
+    match c {
+        ' ' => "\\s".to_owned(),
+        '\\' => "\\\\".to_owned(),
+        c if c.is_ascii_graphic() => c.to_string(),
+        c => c.escape_default().to_string(),
+    }
+}
+
 impl fmt::Display for Token
 {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Token::Word(ref word) => word.fmt(fmt),
+            Token::Keyword(keyword) => keyword.fmt(fmt),
+            Token::Constant(ref name) | Token::Identifier(ref name) | Token::MethodName(ref name) => name.fmt(fmt),
+            Token::Label(ref name) => write!(fmt, "{}:", name),
             Token::Symbol(sym) => sym.fmt(fmt),
             Token::String(ref s) => write!(fmt, "\"{}\"", s),
             Token::Integer(i) => i.fmt(fmt),
+            Token::Character(c) => write!(fmt, "?{}", escape_character(c)),
+            Token::Unknown(c) => c.escape_default().fmt(fmt),
             Token::EndOfLine => "end-of-line".fmt(fmt),
             Token::EndOfFile => "end-of-file".fmt(fmt),
         }
     }
 }
+
+impl fmt::Display for TokenKind
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TokenKind::Keyword(keyword) => write!(fmt, "`{}`", keyword),
+            TokenKind::Symbol(symbol) => write!(fmt, "`{}`", symbol),
+            TokenKind::Constant => "constant".fmt(fmt),
+            TokenKind::Identifier => "identifier".fmt(fmt),
+            TokenKind::MethodName => "method name".fmt(fmt),
+            TokenKind::Label => "label".fmt(fmt),
+            TokenKind::String => "string".fmt(fmt),
+            TokenKind::Integer => "integer".fmt(fmt),
+            TokenKind::Character => "character literal".fmt(fmt),
+            TokenKind::Unknown => "unknown character".fmt(fmt),
+            TokenKind::Terminator => "newline".fmt(fmt),
+            TokenKind::EndOfFile => "end of file".fmt(fmt),
+            TokenKind::Expression => "expression".fmt(fmt),
+        }
+    }
+}
@@ -1,117 +1,619 @@
-use parse::Token;
-use std::iter::Peekable;
+use parse::{Token, Keyword, OpenConstruct};
+use ast;
+
+use cst;
+use span::Span;
+use symbol::Symbol;
+
+use std::collections::VecDeque;
+use std::mem;
 
 /// A list of symbols.
+///
+/// Every prefix of a symbol must also be a symbol, as they are read greedily.
 const SYMBOLS: &'static [&'static str] = &[
-    "::", "&&", "||", "=>", "->",
+    "::", "&&", "||", "=>", "->", "&.",
+    "==", "===", "!=", "=~", "!~", "<=", ">=", "<=>", "**", "<<", ">>",
     "{", "}", "(", ")", "[", "]", "<", ">",
     ".", ",", ";", "&", "|", "@", "=",
-    ":", "!", "?", "%", "/", "\\", "*", "+", "-",
+    ":", "!", "?", "%", "/", "\\", "*", "+", "-", "^",
 ];
 
+/// Symbols which cannot end an expression, so that a new line following
+/// them continues the expression onto the next line.
+const CONTINUATION_SYMBOLS: &[&str] = &[
+    "::", "&&", "||", "=>", "->", "&.",
+    "==", "===", "!=", "=~", "!~", "<=", ">=", "<=>", "**", "<<", ">>",
+    "{", "(", "[", "<", ">", ".", ",", "&", "=",
+    ":", "?", "%", "/", "*", "+", "-", "^",
+];
+
+/// Keywords which continue an expression onto the next line.
+const CONTINUATION_KEYWORDS: &[Keyword] = &[Keyword::And, Keyword::Or, Keyword::Not];
+
 const COMMENT_CHAR: char = '#';
 
+/// The start of a multi-line comment.
+///
+/// It must appear at the very start of a line.
+const BLOCK_COMMENT_START: &str = "=begin";
+/// The end of a multi-line comment.
+const BLOCK_COMMENT_END: &str = "=end";
+
+/// A line containing only this marks the end of the program.
+///
+/// Everything after it is exposed to the program as the `DATA` section.
+const END_MARKER: &str = "__END__";
+
 /// A tokenizer.
 pub struct Tokenizer<I: Iterator<Item=char>>
 {
-    chars: Peekable<I>,
+    chars: I,
+    /// Characters which have been read from `chars` but not consumed.
+    lookahead: VecDeque<char>,
     /// We generate a fake new line at the end of every program.
     sent_last_new_line: bool,
+    /// Whether the next character is the first on its line.
+    at_line_start: bool,
+    /// The text following an `__END__` line, if any.
+    data: Option<String>,
+    /// The current line number, starting from one.
+    line: usize,
+    /// Whether a token has been read on the current line.
+    line_has_code: bool,
+    /// Whether any token other than a new line has been read.
+    seen_code: bool,
+    /// The magic comments read so far.
+    pragmas: Vec<ast::Pragma>,
+    /// The comments read so far.
+    comments: Vec<ast::Comment>,
+    /// The last token that was read.
+    last_token: Option<Token>,
+    /// Whether we are reading the name of a method being defined, where
+    /// keywords are ordinary names and setters such as `name=` are allowed.
+    reading_method_name: bool,
+    /// The text consumed since the last token was handed out.
+    text: String,
+    /// Where the current token starts in `text`, after its leading trivia.
+    token_start: usize,
+    /// The number of bytes consumed so far.
+    offset: usize,
+    /// The offset of the start of the current token.
+    token_offset: usize,
+    /// The line the current token starts on.
+    token_line: usize,
+    /// The number of spaces and tabs at the start of the current line.
+    indent: usize,
+    /// Whether we are still reading the indentation of the current line.
+    in_indent: bool,
+    /// The string which was still open at the end of the file, if any.
+    unterminated_string: Option<OpenConstruct>,
 }
 
 impl<I> Tokenizer<I> where I: Iterator<Item=char>
 {
     /// Creates a new tokenizer.
     pub fn new(characters: I) -> Self {
-        Tokenizer { chars: characters.peekable(), sent_last_new_line: false }
+        Tokenizer {
+            chars: characters,
+            lookahead: VecDeque::new(),
+            sent_last_new_line: false,
+            at_line_start: true,
+            data: None,
+            line: 1,
+            line_has_code: false,
+            seen_code: false,
+            pragmas: Vec::new(),
+            comments: Vec::new(),
+            last_token: None,
+            reading_method_name: false,
+            text: String::new(),
+            token_start: 0,
+            offset: 0,
+            token_offset: 0,
+            token_line: 1,
+            indent: 0,
+            in_indent: true,
+            unterminated_string: None,
+        }
+    }
+
+    /// Gets the magic comments read so far.
+    pub fn pragmas(&self) -> &[ast::Pragma] {
+        &self.pragmas
+    }
+
+    /// Takes the comments read so far.
+    pub fn take_comments(&mut self) -> Vec<ast::Comment> {
+        mem::take(&mut self.comments)
+    }
+
+    /// Gets the text following the `__END__` marker.
+    ///
+    /// This is only available once the marker has been reached.
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_ref().map(|data| &data[..])
+    }
+
+    /// Gets the string which was missing its closing quote, once the end of
+    /// the file has been reached.
+    pub fn unterminated_string(&self) -> Option<&OpenConstruct> {
+        self.unterminated_string.as_ref()
+    }
+
+    /// Gets where the last token read is in the source.
+    pub fn span(&self) -> Span {
+        Span::new(self.token_offset, self.offset)
+    }
+
+    /// Reads the next token along with its exact text and the whitespace
+    /// and comments before it.
+    pub fn next_syntax_token(&mut self) -> Option<cst::SyntaxToken> {
+        let token = self.next()?;
+
+        let text = self.text.split_off(self.token_start);
+        let leading_trivia = mem::take(&mut self.text);
+        self.token_start = 0;
+
+        Some(cst::SyntaxToken {
+            token,
+            leading_trivia,
+            text,
+            span: self.span(),
+            line: self.token_line,
+            indent: self.indent,
+        })
+    }
+
+    /// Marks the start of a token.
+    fn start_token(&mut self) {
+        self.token_start = self.text.len();
+        self.token_offset = self.offset;
+        self.token_line = self.line;
     }
 
     fn read_token(&mut self) -> Option<Token> {
-        self.eat_whitespace();
-        self.eat_comment();
-        self.eat_whitespace();
+        let preceded_by_space = self.eat_whitespace_and_comments();
+        self.start_token();
+
+        if self.data.is_some() { return None };
+
+        let peeked_char = self.peek()?;
 
-        let peeked_char = if let Some(&c) = self.chars.peek() { c } else { return None };
+        if self.at_line_start && peeked_char == '_' {
+            let word = self.read_word();
 
-        if peeked_char.is_alphabetic() {
+            if word == Token::Identifier(Symbol::intern(END_MARKER)) &&
+                matches!(self.peek(), None | Some('\n')) {
+                self.read_data();
+                None
+            } else {
+                Some(word)
+            }
+        } else if peeked_char.is_alphabetic() || peeked_char == '_' || peeked_char == '$' ||
+            self.looking_at_variable_sigil() {
             Some(self.read_word())
         } else if peeked_char.is_numeric() {
             Some(self.read_number())
         } else if peeked_char == '\n' {
-            self.chars.next(); // Eat new line
-            Some(Token::EndOfLine)
+            self.advance(); // Eat new line
+
+            if self.is_new_line_significant() {
+                Some(Token::EndOfLine)
+            } else {
+                self.read_token()
+            }
         } else if peeked_char == '"' || peeked_char == '\'' {
             Some(self.read_string())
+        } else if peeked_char == '?' && self.is_character_literal(preceded_by_space) {
+            Some(self.read_character())
         } else if SYMBOLS.iter().any(|sym| sym.starts_with(peeked_char)) {
-            let first_char = self.chars.next().unwrap();
-
-            let matches: Vec<_> = SYMBOLS.iter().filter(|sym| sym.starts_with(first_char)).collect();
-
-            if matches.iter().any(|sym| sym.len() > 1) {
-                if let Some(&peeked_second_char) = self.chars.peek() {
-                    let symbol = format!("{}{}", first_char, peeked_second_char);
-
-                    if let Some(exact_match) = SYMBOLS.iter().find(|&&sym| sym == symbol) {
-                        self.chars.next(); // Eat the second symbol.
-                        Some(Token::Symbol(exact_match))
-                    } else {
-                        // Fall back to using only the first char.
-                        let exact_match = SYMBOLS.iter().find(|&&sym| sym == format!("{}", first_char)).unwrap();
-                        Some(Token::Symbol(exact_match))
-                    }
-                } else {
-                    // We should just use the first char
-                    Some(Token::Symbol(matches[0]))
-                }
-            } else { // We matched with a single-char symbol.
-                debug_assert_eq!(matches.len(), 1, "matched with multiple symbols");
-                Some(Token::Symbol(matches[0]))
-            }
+            Some(self.read_symbol())
         } else {
-            println!("failed: {}", peeked_char);
-            panic!("unexpected character: '{:?}'", peeked_char);
+            self.advance();
+            Some(Token::Unknown(peeked_char))
+        }
+    }
+
+    /// Checks if a new line that was just read terminates the current
+    /// expression.
+    fn is_new_line_significant(&mut self) -> bool {
+        let continues_line = match self.last_token {
+            Some(Token::Symbol(symbol)) => CONTINUATION_SYMBOLS.contains(&symbol),
+            Some(Token::Keyword(keyword)) => CONTINUATION_KEYWORDS.contains(&keyword),
+            // `key:` must be followed by a value.
+            Some(Token::Label(..)) => true,
+            _ => false,
+        };
+
+        !continues_line && !self.next_line_continues()
+    }
+
+    /// Checks if the next line of code continues the previous line, as in
+    /// method chains with a leading `.` or `&.`, or a closing bracket.
+    fn next_line_continues(&mut self) -> bool {
+        let mut i = 0;
+
+        // Skip blank lines and comments.
+        loop {
+            match self.peek_nth(i) {
+                Some(c) if c != '\n' && c.is_whitespace() => i += 1,
+                Some('\n') => i += 1,
+                Some(COMMENT_CHAR) => {
+                    while !matches!(self.peek_nth(i), None | Some('\n')) { i += 1 }
+                },
+                _ => break,
+            }
+        }
+
+        match (self.peek_nth(i), self.peek_nth(i + 1)) {
+            (Some('.'), second) => second != Some('.'),
+            (Some('&'), Some('.')) => true,
+            (Some(')'), _) | (Some(']'), _) => true,
+            _ => false,
+        }
+    }
+
+    /// Looks at the character `n` places ahead without consuming anything.
+    fn peek_nth(&mut self, n: usize) -> Option<char> {
+        while self.lookahead.len() <= n {
+            match self.chars.next() {
+                Some(c) => self.lookahead.push_back(c),
+                None => return None,
+            }
+        }
+
+        Some(self.lookahead[n])
+    }
+
+    fn peek(&mut self) -> Option<char> { self.peek_nth(0) }
+
+    /// Checks if the upcoming characters match some text.
+    fn looking_at(&mut self, text: &str) -> bool {
+        text.chars().enumerate().all(|(i, c)| self.peek_nth(i) == Some(c))
+    }
+
+    /// Checks if we are looking at the `@` or `@@` sigil of an instance or
+    /// class variable.
+    fn looking_at_variable_sigil(&mut self) -> bool {
+        let offset = if self.looking_at("@@") { 2 } else if self.looking_at("@") { 1 } else { return false };
+
+        self.peek_nth(offset).is_some_and(|c| c.is_alphabetic() || c == '_')
+    }
+
+    /// Checks if the upcoming characters are a marker like `=begin`, followed
+    /// by whitespace or the end of the file.
+    fn looking_at_marker(&mut self, marker: &str) -> bool {
+        self.looking_at(marker) &&
+            self.peek_nth(marker.chars().count()).is_none_or(char::is_whitespace)
+    }
+
+    /// Consumes the next character.
+    fn advance(&mut self) -> Option<char> {
+        let c = match self.lookahead.pop_front() {
+            Some(c) => Some(c),
+            None => self.chars.next(),
+        };
+
+        if let Some(c) = c {
+            self.text.push(c);
+            self.offset += c.len_utf8();
+
+            if self.in_indent && (c == ' ' || c == '\t') {
+                self.indent += 1;
+            } else {
+                self.in_indent = false;
+            }
+        }
+
+        self.at_line_start = c == Some('\n');
+        if self.at_line_start {
+            self.line += 1;
+            self.line_has_code = false;
+            self.indent = 0;
+            self.in_indent = true;
         }
+
+        c
     }
 
-    fn eat_whitespace(&mut self) {
-        while let Some(&c) = self.chars.peek() {
+    /// Eats whitespace and comments, returning whether anything was eaten.
+    fn eat_whitespace_and_comments(&mut self) -> bool {
+        let mut ate_anything = false;
+
+        loop {
+            ate_anything |= self.eat_whitespace();
+
+            if self.at_line_start && self.looking_at_marker(BLOCK_COMMENT_START) {
+                self.eat_block_comment();
+            } else if self.peek() == Some(COMMENT_CHAR) {
+                self.eat_comment();
+            } else {
+                break;
+            }
+
+            ate_anything = true;
+        }
+
+        ate_anything
+    }
+
+    /// Eats whitespace, returning whether anything was eaten.
+    fn eat_whitespace(&mut self) -> bool {
+        let mut ate_anything = false;
+
+        while let Some(c) = self.peek() {
             if c != '\n' && c.is_whitespace() {
-                self.chars.next(); // Eat the character.
+                self.advance(); // Eat the character.
+            } else if c == '\\' && self.peek_nth(1) == Some('\n') {
+                // A backslash continues the line.
+                self.advance();
+                self.advance();
             } else {
                 break;
             }
+
+            ate_anything = true;
         }
+
+        ate_anything
     }
 
+    /// Eats a comment up until the end of the line.
     fn eat_comment(&mut self) {
-        if self.chars.peek() == Some(&COMMENT_CHAR) {
-            while self.chars.peek() != Some(&'\n') {
-                self.chars.next(); // Eat the character.
+        let own_line = !self.line_has_code;
+        let (start, offset) = (self.text.len(), self.offset);
+
+        self.eat_rest_of_line();
+
+        let text = self.text[start..].to_owned();
+        if own_line {
+            let pragmas = magic_comment::parse(&text[1..], self.line, self.seen_code);
+            self.pragmas.extend(pragmas);
+        }
+
+        let span = Span::new(offset, self.offset);
+        self.comments.push(ast::Comment::new(text, span, self.line, own_line));
+    }
+
+    /// Eats an `=begin`/`=end` comment.
+    fn eat_block_comment(&mut self) {
+        let (start, offset, line) = (self.text.len(), self.offset, self.line);
+
+        loop {
+            self.eat_rest_of_line();
+
+            if self.advance().is_none() { break };
+
+            if self.looking_at_marker(BLOCK_COMMENT_END) {
+                self.eat_rest_of_line();
+                break;
             }
         }
+
+        let text = self.text[start..].to_owned();
+        let span = Span::new(offset, self.offset);
+        self.comments.push(ast::Comment::new(text, span, line, true));
     }
 
+    /// Eats everything up until the next new line.
+    fn eat_rest_of_line(&mut self) {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.advance();
+        }
+    }
+
+    /// Reads a name or keyword.
+    ///
+    /// The word is taken from the consumed text rather than built up, so
+    /// that only names which have not been seen before allocate.
     fn read_word(&mut self) -> Token {
-        let mut chars = Vec::new();
+        // Global variables are prefixed with a dollar sign.
+        if self.peek() == Some('$') {
+            self.advance();
+        }
 
-        while let Some(&c) = self.chars.peek() {
-            if c.is_alphanumeric() || c == '_' || c == '!' || c == '?' {
-                self.chars.next(); // Eat the char
-                chars.push(c)
+        // Instance and class variables are prefixed with `@` and `@@`.
+        while self.peek() == Some('@') {
+            self.advance();
+        }
+
+        let has_sigil = self.text.len() > self.token_start;
+
+        while let Some(c) = self.peek() {
+            if is_identifier_char(c) {
+                self.advance(); // Eat the char
             } else {
                 break;
             }
         }
 
-        Token::Word(chars.into_iter().collect())
+        // Method names may end in `?` or `!`, but `foo!=bar` is `foo != bar`.
+        if let Some(c) = self.peek() {
+            if !has_sigil && (c == '?' || c == '!') {
+                let suffix_allowed = match (self.peek_nth(1), self.peek_nth(2)) {
+                    (Some('='), Some('=')) | (Some('='), Some('~')) | (Some('='), Some('>')) => true,
+                    (Some('='), _) => false,
+                    _ => true,
+                };
+
+                if suffix_allowed {
+                    self.advance();
+                }
+            }
+        }
+
+        // Setters are defined as `def name=(value)`.
+        if self.reading_method_name && !has_sigil && !self.text.ends_with(['?', '!']) &&
+            self.peek() == Some('=') && !matches!(self.peek_nth(1), Some('=') | Some('~') | Some('>')) {
+            self.advance();
+        }
+
+        self.classify_word(has_sigil)
+    }
+
+    /// Works out which kind of token the word just read is, from its
+    /// spelling and the tokens around it.
+    fn classify_word(&mut self, has_sigil: bool) -> Token {
+        let follows_separator = matches!(self.last_token,
+                                         Some(Token::Symbol(".")) | Some(Token::Symbol("&.")) |
+                                         Some(Token::Symbol("::")));
+
+        // `key: value`, but not `Foo::Bar` or the `a:` in the ternary `x ?a:b`.
+        let is_label = !has_sigil && !follows_separator && !self.reading_method_name &&
+            self.peek() == Some(':') && self.peek_nth(1) != Some(':') &&
+            self.last_token != Some(Token::question_mark());
+
+        // Keywords are ordinary method names in `obj.class` and `def end`,
+        // but not in `def self.foo`.
+        let is_method_name = follows_separator || (self.reading_method_name && self.peek() != Some('.'));
+
+        let word = &self.text[self.token_start..];
+
+        if is_label {
+            let label = Symbol::intern(word);
+            self.advance(); // Eat the colon.
+            return Token::Label(label);
+        }
+
+        if !is_method_name {
+            if let Some(keyword) = Keyword::from_word(word) {
+                return Token::Keyword(keyword);
+            }
+        }
+
+        if word.ends_with(['?', '!', '=']) {
+            Token::MethodName(Symbol::intern(word))
+        } else if word.starts_with(char::is_uppercase) {
+            Token::Constant(Symbol::intern(word))
+        } else {
+            Token::Identifier(Symbol::intern(word))
+        }
+    }
+
+    /// Checks if the `?` at the current position begins a character literal
+    /// such as `?a`, rather than being the ternary operator.
+    fn is_character_literal(&mut self, preceded_by_space: bool) -> bool {
+        let first_char = match self.peek_nth(1) {
+            Some(c) if !c.is_whitespace() => c,
+            _ => return false,
+        };
+
+        // `?ab` is never a character literal.
+        if is_identifier_char(first_char) && self.peek_nth(2).is_some_and(is_identifier_char) {
+            return false;
+        }
+
+        match self.last_token {
+            // After a value, `?` is the ternary operator unless it looks like
+            // an argument to a method call, as in `puts ?a`. `x ?a:b` is a
+            // ternary.
+            Some(ref token) if token.can_end_expression() => {
+                preceded_by_space && (first_char == '\\' || self.peek_nth(2) != Some(':'))
+            },
+            _ => true,
+        }
+    }
+
+    /// Reads a character literal, such as `?a` or `?\n`.
+    fn read_character(&mut self) -> Token {
+        self.advance(); // Eat the question mark.
+
+        let c = self.advance().unwrap();
+
+        if c == '\\' {
+            Token::Character(self.read_escape())
+        } else {
+            Token::Character(c)
+        }
+    }
+
+    /// Reads the character following a backslash.
+    fn read_escape(&mut self) -> char {
+        let c = match self.advance() {
+            Some(c) => c,
+            None => return '\\',
+        };
+
+        match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            's' => ' ',
+            '0' => '\0',
+            'e' => '\u{1b}',
+            'a' => '\u{7}',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            'u' => {
+                let braced = self.peek() == Some('{');
+                if braced { self.advance(); }
+
+                let mut digits = String::new();
+                while let Some(c) = self.peek() {
+                    if !c.is_ascii_hexdigit() || (!braced && digits.len() == 4) { break };
+
+                    self.advance();
+                    digits.push(c);
+                }
+
+                if braced && self.peek() == Some('}') { self.advance(); }
+
+                u32::from_str_radix(&digits, 16).ok()
+                    .and_then(::std::char::from_u32)
+                    .unwrap_or('\u{fffd}')
+            },
+            'x' => {
+                let mut digits = String::new();
+                while let Some(c) = self.peek() {
+                    if !c.is_ascii_hexdigit() || digits.len() == 2 { break };
+
+                    self.advance();
+                    digits.push(c);
+                }
+
+                u32::from_str_radix(&digits, 16).ok()
+                    .and_then(::std::char::from_u32)
+                    .unwrap_or('\u{fffd}')
+            },
+            c => c,
+        }
+    }
+
+    /// Reads everything after the `__END__` marker.
+    fn read_data(&mut self) {
+        self.advance(); // Eat the new line.
+        let rest: String = self.lookahead.drain(..).chain(self.chars.by_ref()).collect();
+        self.text.push_str(&rest);
+        self.offset += rest.len();
+        self.data = Some(rest);
+    }
+
+    /// Reads the longest symbol at the current position.
+    fn read_symbol(&mut self) -> Token {
+        let mut symbol = self.advance().unwrap().to_string();
+
+        while let Some(c) = self.peek() {
+            let longer_symbol = format!("{}{}", symbol, c);
+
+            if SYMBOLS.contains(&&longer_symbol[..]) {
+                self.advance();
+                symbol = longer_symbol;
+            } else {
+                break;
+            }
+        }
+
+        Token::Symbol(SYMBOLS.iter().find(|&&sym| sym == symbol).unwrap())
     }
 
     fn read_number(&mut self) -> Token {
         let mut chars = Vec::new();
 
-        while let Some(&c) = self.chars.peek() {
+        while let Some(c) = self.peek() {
             if c.is_numeric() {
-                self.chars.next(); // Eat the char
+                self.advance(); // Eat the char
                 chars.push(c)
             } else {
                 break;
@@ -125,17 +627,31 @@ impl<I> Tokenizer<I> where I: Iterator<Item=char>
     }
 
     fn read_string(&mut self) -> Token {
-        self.chars.next(); // Eat the quote.
+        let quote = self.advance().unwrap();
+        let start = OpenConstruct {
+            description: quote.to_string(),
+            closer: if quote == '"' { "\"" } else { "'" },
+            span: Span::new(self.offset - 1, self.offset),
+            line: self.token_line,
+            indent: self.indent,
+        };
 
         let mut chars = Vec::new();
 
-        while let Some(&c) = self.chars.peek() {
-            if c != '"' && c != '\'' {
-                self.chars.next(); // Eat the char
-                chars.push(c)
-            } else {
-                self.chars.next(); // Eat the quote.
-                break;
+        loop {
+            match self.peek() {
+                Some(c) if c != '"' && c != '\'' => {
+                    self.advance(); // Eat the char
+                    chars.push(c)
+                },
+                Some(..) => {
+                    self.advance(); // Eat the quote.
+                    break;
+                },
+                None => {
+                    self.unterminated_string = Some(start);
+                    break;
+                },
             }
         }
 
@@ -149,24 +665,120 @@ impl<I: Iterator<Item=char>> Iterator for Tokenizer<I>
 
     fn next(&mut self) -> Option<Token> {
         if let Some(token) = self.read_token() {
-            println!("token: {:?}", token);
+            if token != Token::EndOfLine {
+                self.line_has_code = true;
+                self.seen_code = true;
+            }
+
+            self.reading_method_name = match token {
+                Token::Keyword(Keyword::Def) => true,
+                // `def self.foo`
+                Token::Symbol(".") => self.reading_method_name,
+                Token::Keyword(Keyword::Self_) | Token::Constant(..) | Token::Identifier(..) => {
+                    self.reading_method_name && self.peek() == Some('.')
+                },
+                _ => false,
+            };
+
+            self.last_token = Some(token.clone());
+
             Some(token)
         } else {
             if self.sent_last_new_line {
                 None
             } else {
+                // The new line we make up has no text of its own.
                 self.sent_last_new_line = true;
+                self.start_token();
                 Some(Token::EndOfLine)
             }
         }
     }
 }
 
+/// Checks if a character can appear in an identifier.
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Utilities for recognizing magic comments.
+pub(crate) mod magic_comment
+{
+    use ast;
+
+    /// Reads the pragmas out of a comment, excluding the leading `#`.
+    ///
+    /// Most pragmas are only recognized before the first line of code.
+    pub fn parse(text: &str, line: usize, seen_code: bool) -> Vec<ast::Pragma> {
+        let text = text.trim();
+        let mut pragmas = Vec::new();
+
+        // The encoding must be on the first line, or the second after a shebang.
+        if line <= 2 && !seen_code {
+            if let Some(encoding) = encoding(text) {
+                pragmas.push(ast::Pragma::Encoding(encoding));
+            }
+        }
+
+        // Emacs-style comments hold several directives, such as
+        // `-*- coding: utf-8; frozen_string_literal: true -*-`.
+        let directives = if text.len() >= 6 && text.starts_with("-*-") && text.ends_with("-*-") {
+            text[3..text.len() - 3].split(';').collect()
+        } else {
+            vec![text]
+        };
+
+        for directive in directives {
+            let mut parts = directive.splitn(2, ':');
+
+            let key = parts.next().unwrap().trim().to_lowercase().replace('-', "_");
+            let value = match parts.next().and_then(|value| value.split_whitespace().next()) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let pragma = match &key[..] {
+                "frozen_string_literal" if !seen_code => match &value.to_lowercase()[..] {
+                    "true" => Some(ast::Pragma::FrozenStringLiteral(true)),
+                    "false" => Some(ast::Pragma::FrozenStringLiteral(false)),
+                    _ => None,
+                },
+                "typed" if !seen_code => Some(ast::Pragma::Typed(value.to_owned())),
+                "shareable_constant_value" => Some(ast::Pragma::ShareableConstantValue(value.to_owned())),
+                _ => None,
+            };
+
+            pragmas.extend(pragma);
+        }
+
+        pragmas
+    }
+
+    /// Finds an encoding name following `coding:` or `coding=`.
+    ///
+    /// This covers `encoding: utf-8`, Emacs-style and Vim-style comments.
+    pub fn encoding(text: &str) -> Option<String> {
+        let index = text.find("coding")?;
+        let rest = &text[index + "coding".len()..];
+
+        if !rest.starts_with(':') && !rest.starts_with('=') {
+            return None;
+        }
+
+        let name: String = rest[1..].trim_start().chars()
+            .take_while(|&c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+            .collect();
+
+        if name.is_empty() { None } else { Some(name) }
+    }
+}
+
 #[cfg(test)]
 mod test
 {
     use super::*;
-    use parse::Token;
+    use parse::{Token, Keyword};
+    use ast;
 
     fn tokenize(s: &str) -> Vec<Token> {
         let t = Tokenizer::new(s.chars());
@@ -175,26 +787,26 @@ mod test
 
     #[test]
     fn can_read_simple_word() {
-        assert_eq!(tokenize("abcdef"), vec![Token::Word("abcdef".to_owned()),
+        assert_eq!(tokenize("abcdef"), vec![Token::Identifier(Symbol::intern("abcdef")),
                                             Token::EndOfLine]);
     }
 
     #[test]
     fn can_handle_whitespace_at_start_of_word() {
-        assert_eq!(tokenize("     abcdef"), vec![Token::Word("abcdef".to_owned()),
+        assert_eq!(tokenize("     abcdef"), vec![Token::Identifier(Symbol::intern("abcdef")),
                                                  Token::EndOfLine]);
     }
 
     #[test]
     fn can_read_multiple_words() {
-        assert_eq!(tokenize("\tabcdef hg"), vec![Token::Word("abcdef".to_owned()),
-                                                 Token::Word("hg".to_owned()),
+        assert_eq!(tokenize("\tabcdef hg"), vec![Token::Identifier(Symbol::intern("abcdef")),
+                                                 Token::Identifier(Symbol::intern("hg")),
                                                  Token::EndOfLine]);
     }
 
     #[test]
     fn considers_underscores_a_part_of_words() {
-        assert_eq!(tokenize("\tabcdef_hg"), vec![Token::Word("abcdef_hg".to_owned()), Token::EndOfLine]);
+        assert_eq!(tokenize("\tabcdef_hg"), vec![Token::Identifier(Symbol::intern("abcdef_hg")), Token::EndOfLine]);
     }
 
     #[test]
@@ -212,7 +824,7 @@ mod test
 
     #[test]
     fn can_read_new_line() {
-        assert_eq!(tokenize(" \nb"), vec![Token::EndOfLine, Token::Word("b".to_owned()), Token::EndOfLine]);
+        assert_eq!(tokenize(" \nb"), vec![Token::EndOfLine, Token::Identifier(Symbol::intern("b")), Token::EndOfLine]);
     }
 
     #[test]
@@ -223,16 +835,240 @@ mod test
 
     #[test]
     fn can_read_double_colon() {
-        assert_eq!(tokenize("Abc::Def"), vec![Token::Word("Abc".to_owned()),
+        assert_eq!(tokenize("Abc::Def"), vec![Token::Constant(Symbol::intern("Abc")),
                                               Token::Symbol("::"),
-                                              Token::Word("Def".to_owned()),
+                                              Token::Constant(Symbol::intern("Def")),
+                                              Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_global_variable() {
+        assert_eq!(tokenize("$stdout"), vec![Token::Identifier(Symbol::intern("$stdout")),
+                                             Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_instance_and_class_variables() {
+        assert_eq!(tokenize("@a @@b"), vec![Token::Identifier(Symbol::intern("@a")),
+                                            Token::Identifier(Symbol::intern("@@b")),
+                                            Token::EndOfLine]);
+    }
+
+    #[test]
+    fn stops_at_end_marker() {
+        let mut tokenizer = Tokenizer::new("abc\n__END__\nfoo bar\n".chars());
+
+        assert_eq!(tokenizer.by_ref().collect::<Vec<_>>(),
+                   vec![Token::Identifier(Symbol::intern("abc")), Token::EndOfLine, Token::EndOfLine]);
+        assert_eq!(tokenizer.data(), Some("foo bar\n"));
+    }
+
+    #[test]
+    fn only_treats_end_marker_at_start_of_line_specially() {
+        assert_eq!(tokenize("a __END__"), vec![Token::Identifier(Symbol::intern("a")),
+                                               Token::Identifier(Symbol::intern("__END__")),
+                                               Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_comment_on_last_line() {
+        assert_eq!(tokenize("abc # comment"), vec![Token::Identifier(Symbol::intern("abc")),
+                                                   Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_skip_block_comments() {
+        assert_eq!(tokenize("a\n=begin\nb c\n=end trailing\nd"), vec![Token::Identifier(Symbol::intern("a")),
+                                                                        Token::EndOfLine,
+                                                                        Token::EndOfLine,
+                                                                        Token::Identifier(Symbol::intern("d")),
+                                                                        Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_magic_comments() {
+        let mut tokenizer = Tokenizer::new("# -*- coding: utf-8 -*-\n# frozen_string_literal: true\n# typed: strict\nx\n# frozen_string_literal: false\n# shareable_constant_value: literal\n".chars());
+        tokenizer.by_ref().count();
+
+        assert_eq!(tokenizer.pragmas(), &[
+            ast::Pragma::Encoding("utf-8".to_owned()),
+            ast::Pragma::FrozenStringLiteral(true),
+            ast::Pragma::Typed("strict".to_owned()),
+            ast::Pragma::ShareableConstantValue("literal".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn ignores_magic_comments_after_code_on_the_same_line() {
+        let mut tokenizer = Tokenizer::new("x # shareable_constant_value: literal".chars());
+        tokenizer.by_ref().count();
+
+        assert_eq!(tokenizer.pragmas(), &[]);
+    }
+
+    #[test]
+    fn symbols_are_prefix_closed() {
+        for symbol in SYMBOLS {
+            for (i, _) in symbol.char_indices().skip(1) {
+                assert!(SYMBOLS.contains(&&symbol[..i]), "prefix of '{}' is not a symbol", symbol);
+            }
+        }
+    }
+
+    #[test]
+    fn can_read_longest_symbol() {
+        assert_eq!(tokenize("<=> === &."), vec![Token::Symbol("<=>"),
+                                                Token::Symbol("==="),
+                                                Token::Symbol("&."),
+                                                Token::EndOfLine]);
+    }
+
+    #[test]
+    fn ignores_new_line_after_operator() {
+        assert_eq!(tokenize("a +\n\n b"), vec![Token::Identifier(Symbol::intern("a")),
+                                             Token::Symbol("+"),
+                                             Token::Identifier(Symbol::intern("b")),
+                                             Token::EndOfLine]);
+    }
+
+    #[test]
+    fn ignores_escaped_new_line() {
+        assert_eq!(tokenize("a \\\n b"), vec![Token::Identifier(Symbol::intern("a")),
+                                              Token::Identifier(Symbol::intern("b")),
                                               Token::EndOfLine]);
     }
 
+    #[test]
+    fn ignores_new_line_before_leading_dot() {
+        assert_eq!(tokenize("a\n  # comment\n  .b\n  &.c"), vec![Token::Identifier(Symbol::intern("a")),
+                                                                 Token::Symbol("."),
+                                                                 Token::Identifier(Symbol::intern("b")),
+                                                                 Token::Symbol("&."),
+                                                                 Token::Identifier(Symbol::intern("c")),
+                                                                 Token::EndOfLine]);
+    }
+
+    #[test]
+    fn keeps_new_line_before_range() {
+        assert_eq!(tokenize("a\n..b"), vec![Token::Identifier(Symbol::intern("a")),
+                                            Token::EndOfLine,
+                                            Token::Symbol("."),
+                                            Token::Symbol("."),
+                                            Token::Identifier(Symbol::intern("b")),
+                                            Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_character_literals() {
+        assert_eq!(tokenize("?a ?\\n ?\\u{1F600} ?\\u00e9"), vec![Token::Character('a'),
+                                                                Token::Character('\n'),
+                                                                Token::Character('\u{1F600}'),
+                                                                Token::Character('\u{e9}'),
+                                                                Token::EndOfLine]);
+    }
+
+    #[test]
+    fn reads_unknown_characters_as_tokens() {
+        assert_eq!(tokenize("`ls`"), vec![Token::Unknown('`'),
+                                          Token::Identifier(Symbol::intern("ls")),
+                                          Token::Unknown('`'),
+                                          Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_character_literal_argument() {
+        assert_eq!(tokenize("puts ?a"), vec![Token::Identifier(Symbol::intern("puts")),
+                                             Token::Character('a'),
+                                             Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_ternary_without_spaces() {
+        assert_eq!(tokenize("x ?a:b"), vec![Token::Identifier(Symbol::intern("x")),
+                                            Token::Symbol("?"),
+                                            Token::Identifier(Symbol::intern("a")),
+                                            Token::Symbol(":"),
+                                            Token::Identifier(Symbol::intern("b")),
+                                            Token::EndOfLine]);
+        assert_eq!(tokenize("x ? a : b"), vec![Token::Identifier(Symbol::intern("x")),
+                                               Token::Symbol("?"),
+                                               Token::Identifier(Symbol::intern("a")),
+                                               Token::Symbol(":"),
+                                               Token::Identifier(Symbol::intern("b")),
+                                               Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_method_name_suffixes() {
+        assert_eq!(tokenize("empty? save! foo!=bar a?b"), vec![Token::MethodName(Symbol::intern("empty?")),
+                                                               Token::MethodName(Symbol::intern("save!")),
+                                                               Token::Identifier(Symbol::intern("foo")),
+                                                               Token::Symbol("!="),
+                                                               Token::Identifier(Symbol::intern("bar")),
+                                                               Token::MethodName(Symbol::intern("a?")),
+                                                               Token::Identifier(Symbol::intern("b")),
+                                                               Token::EndOfLine]);
+    }
+
     #[test]
     fn can_read_positive_integer() {
         assert_eq!(tokenize("123 45"), vec![Token::Integer(123),
                                             Token::Integer(45),
                                             Token::EndOfLine]);
     }
+
+    fn identifier(name: &str) -> Token { Token::Identifier(Symbol::intern(name)) }
+
+    #[test]
+    fn can_read_keywords() {
+        assert_eq!(tokenize("class Foo; end"), vec![Token::Keyword(Keyword::Class),
+                                                    Token::Constant(Symbol::intern("Foo")),
+                                                    Token::Symbol(";"),
+                                                    Token::Keyword(Keyword::End),
+                                                    Token::EndOfLine]);
+        assert_eq!(tokenize("defined?(x)")[0], Token::Keyword(Keyword::Defined));
+    }
+
+    #[test]
+    fn keywords_after_dots_are_method_names() {
+        assert_eq!(tokenize("obj.class&.end"), vec![identifier("obj"),
+                                                    Token::Symbol("."),
+                                                    identifier("class"),
+                                                    Token::Symbol("&."),
+                                                    identifier("end"),
+                                                    Token::EndOfLine]);
+    }
+
+    #[test]
+    fn keywords_and_setters_can_be_defined() {
+        assert_eq!(tokenize("def end; end"), vec![Token::Keyword(Keyword::Def),
+                                                  identifier("end"),
+                                                  Token::Symbol(";"),
+                                                  Token::Keyword(Keyword::End),
+                                                  Token::EndOfLine]);
+        assert_eq!(tokenize("def self.name=(v)"), vec![Token::Keyword(Keyword::Def),
+                                                       Token::Keyword(Keyword::Self_),
+                                                       Token::Symbol("."),
+                                                       Token::MethodName(Symbol::intern("name=")),
+                                                       Token::Symbol("("),
+                                                       identifier("v"),
+                                                       Token::Symbol(")"),
+                                                       Token::EndOfLine]);
+        assert_eq!(tokenize("name=v"), vec![identifier("name"),
+                                            Token::Symbol("="),
+                                            identifier("v"),
+                                            Token::EndOfLine]);
+    }
+
+    #[test]
+    fn can_read_labels() {
+        assert_eq!(tokenize("foo if: 1, b:\n2"), vec![identifier("foo"),
+                                                      Token::Label(Symbol::intern("if")),
+                                                      Token::Integer(1),
+                                                      Token::Symbol(","),
+                                                      Token::Label(Symbol::intern("b")),
+                                                      Token::Integer(2),
+                                                      Token::EndOfLine]);
+        assert_eq!(tokenize("Foo::Bar")[0], Token::Constant(Symbol::intern("Foo")));
+    }
 }
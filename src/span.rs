@@ -0,0 +1,87 @@
+//! Locations in source code.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// A range of bytes in the source.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span
+{
+    /// The offset of the first byte.
+    pub start: usize,
+    /// The offset just past the last byte.
+    pub end: usize,
+}
+
+/// A position in the source, for showing to people.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineColumn
+{
+    /// The line number, starting from one.
+    pub line: usize,
+    /// The column in characters, starting from one.
+    pub column: usize,
+}
+
+impl Span
+{
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Creates an empty span at an offset, such as where something is missing.
+    pub fn at(offset: usize) -> Self {
+        Span::new(offset, offset)
+    }
+
+    /// Creates a span covering this one, another and everything between.
+    pub fn to(self, other: Span) -> Self {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn len(&self) -> usize { self.end - self.start }
+    pub fn is_empty(&self) -> bool { self.start == self.end }
+
+    /// Gets the text covered by the span.
+    pub fn text(self, source: &str) -> &str {
+        &source[self.start..self.end]
+    }
+}
+
+impl LineColumn
+{
+    /// Finds the line and column of an offset in the source.
+    pub fn of(offset: usize, source: &str) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+
+        LineColumn {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn finds_lines_and_columns() {
+        let source = "ab\nçd\n";
+
+        assert_eq!(LineColumn::of(0, source), LineColumn { line: 1, column: 1 });
+        assert_eq!(LineColumn::of(3, source), LineColumn { line: 2, column: 1 });
+        assert_eq!(LineColumn::of(5, source), LineColumn { line: 2, column: 2 });
+        assert_eq!(LineColumn::of(7, source), LineColumn { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn joins_spans() {
+        assert_eq!(Span::new(4, 6).to(Span::new(1, 2)), Span::new(1, 6));
+        assert_eq!(Span::new(1, 4).text("abcdef"), "bcd");
+    }
+}
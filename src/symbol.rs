@@ -0,0 +1,131 @@
+//! Interned strings.
+//!
+//! Names are interned into a table shared by the whole process, so that
+//! comparing them compares integers. Interned strings live for as long as
+//! the process.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+/// An interned string.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+/// The table of interned strings.
+#[derive(Debug, Default)]
+struct Interner
+{
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Symbol
+{
+    /// Gets the symbol for a string, adding it to the table if it is new.
+    pub fn intern(text: &str) -> Self {
+        if let Some(&symbol) = interner().read().unwrap().symbols.get(text) {
+            return symbol;
+        }
+
+        let mut interner = interner().write().unwrap();
+        if let Some(&symbol) = interner.symbols.get(text) {
+            return symbol;
+        }
+
+        let text: &'static str = Box::leak(text.to_owned().into_boxed_str());
+        let symbol = Symbol(interner.strings.len() as u32);
+        interner.strings.push(text);
+        interner.symbols.insert(text, symbol);
+
+        symbol
+    }
+
+    /// Gets the string the symbol was interned from.
+    pub fn as_str(self) -> &'static str {
+        interner().read().unwrap().strings[self.0 as usize]
+    }
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl<'a> From<&'a str> for Symbol
+{
+    fn from(text: &'a str) -> Self {
+        Symbol::intern(text)
+    }
+}
+
+impl From<String> for Symbol
+{
+    fn from(text: String) -> Self {
+        Symbol::intern(&text)
+    }
+}
+
+impl PartialEq<str> for Symbol
+{
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Symbol
+{
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for Symbol
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), fmt)
+    }
+}
+
+impl fmt::Display for Symbol
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Symbol
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Symbol
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        String::deserialize(deserializer).map(Symbol::from)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn interns_equal_strings_to_the_same_symbol() {
+        let foo = Symbol::intern("foo");
+
+        assert_eq!(Symbol::intern("foo"), foo);
+        assert_ne!(Symbol::intern("bar"), foo);
+        assert_eq!(foo.as_str(), "foo");
+        assert_eq!(foo, "foo");
+        assert_eq!(format!("{} {:?}", foo, foo), "foo \"foo\"");
+    }
+}
@@ -0,0 +1,9 @@
+@a = $b = @@c = 1
+A = 1
+::B = 2
+C::D = 3
+a, b = 1, 2
+*c, (d, e) = f
+g, = h
+x = x
+foo.bar, baz[0] = 1, 2
@@ -0,0 +1,12 @@
+list.each do |x|
+  puts x
+end
+pairs.map { |a, b| a + b }
+pairs.map { |(a, b)| a }
+list.each { |first, *rest| rest }
+list.each { |*| }
+foo {}
+x = 1
+list.each { |y| x + y }
+list.each { |y| z = y }
+z
@@ -0,0 +1,12 @@
+puts 'hello'
+foo.bar(1, 2)
+foo&.bar
+Foo::Bar.new(:a, key: 'value', :empty? => nil)
+a = 1
+a.b = 2
+a[1]
+a[1] = 2
+a.b.c
+Foo::BAR
+::Foo
+Foo(1)
@@ -0,0 +1,32 @@
+begin
+  a
+rescue Foo, Bar => e
+  e
+else
+  b
+ensure
+  c
+end
+begin
+  a
+  b
+end
+begin
+  a
+rescue
+  b
+end
+for i in list
+  puts i
+end
+for a, b in pairs
+end
+def foo
+  super
+  super(1)
+  super()
+  yield
+  yield 1, 2
+  return
+  return 1, 2
+end
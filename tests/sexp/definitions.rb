@@ -0,0 +1,26 @@
+module Foo::Bar
+  class Baz < ::Base
+    attr_reader :a
+
+    def initialize
+      @a = 1
+    end
+
+    def self.create
+      new
+    end
+
+    private
+
+    def helper
+      a = 1
+      a
+    end
+
+    public def visible
+    end
+  end
+end
+
+def top_level
+end
@@ -0,0 +1,12 @@
+alias foo bar
+alias $new $old
+undef foo, bar
+BEGIN { a }
+END { b }
+x = ?a
+:empty?
+nil
+true
+false
+self
+'a #{string}'
@@ -0,0 +1,9 @@
+1 + 2 * 3
+-a ** 2
+-1
+(1 + 2) * 3
+a && b || c
+x == y ? 1 : 2
+a != b
+a <=> b
+defined?(a)
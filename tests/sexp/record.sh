@@ -0,0 +1,8 @@
+#!/bin/sh
+# Records the expected S-expressions for each Ruby file here with the
+# `ruby-parse` command from the `parser` gem.
+set -e
+cd "$(dirname "$0")"
+for source in *.rb; do
+    ruby-parse "$source" > "${source%.rb}.sexp"
+done
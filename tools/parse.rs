@@ -1,29 +1,81 @@
 extern crate rubic;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
-use std::{fs, env};
-use std::io::prelude::*;
+use rubic::diagnostic::Renderer;
+use rubic::parse::Source;
 
-fn parse(file_path: &str)
-    -> Result<(), rubic::parse::Error> {
-    println!("Tokenizing '{}'", file_path);
+use std::{fs, env, process};
+use std::io::{self, IsTerminal};
 
-    let mut file = fs::File::open(file_path)?;
+/// How to print the AST.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Output
+{
+    Debug,
+    Json,
+    /// The S-expressions printed by the `parser` gem's `ruby-parse`.
+    Sexp,
+}
+
+/// Parses a file, printing its AST and any errors.
+///
+/// Returns whether the file parsed without errors.
+fn parse(file_path: &str, output: Output)
+    -> Result<bool, rubic::parse::Error> {
+    let source = Source::from_reader(fs::File::open(file_path)?)?;
+
+    let parser = rubic::parse::Parser::from(source.text());
+    let parse = parser.parse_with_recovery();
 
-    let mut file_data = String::new();
-    file.read_to_string(&mut file_data)?;
+    match output {
+        Output::Debug => {
+            println!("Tokenizing '{}'", file_path);
+            println!("{:#?}", parse.program);
+        },
+        Output::Json => print_json(&parse.program),
+        Output::Sexp => println!("{}", rubic::ast::Sexp::from_program(&parse.program)),
+    }
+
+    let renderer = if io::stderr().is_terminal() { Renderer::ansi() } else { Renderer::plain() };
+    for error in parse.errors.iter() {
+        eprintln!("{}", renderer.render(&error.to_diagnostic(), file_path, source.text()));
+    }
 
-    let parser = rubic::parse::Parser::new(file_data.chars());
-    let ast = parser.parse()?;
+    Ok(parse.errors.is_empty())
+}
 
-    println!("{:#?}", ast);
+#[cfg(feature = "serde")]
+fn print_json(program: &rubic::ast::Program) {
+    println!("{}", serde_json::to_string_pretty(program).expect("the AST can always be serialized"));
+}
 
-    Ok(())
+#[cfg(not(feature = "serde"))]
+fn print_json(_: &rubic::ast::Program) {
+    eprintln!("error: printing JSON needs the `serde` feature");
+    process::exit(1);
 }
 
 fn main() {
-    if let Some(file_path) = env::args().skip(1).next() {
-        if let Err(e) = parse(&file_path) {
-            println!("error: {}", e);
+    let mut output = Output::Debug;
+    let mut file_path = None;
+
+    for argument in env::args().skip(1) {
+        match &argument[..] {
+            "--json" => output = Output::Json,
+            "--sexp" => output = Output::Sexp,
+            _ => file_path = Some(argument),
+        }
+    }
+
+    if let Some(file_path) = file_path {
+        match parse(&file_path, output) {
+            Ok(true) => (),
+            Ok(false) => process::exit(1),
+            Err(e) => {
+                println!("error: {}", e);
+                process::exit(1);
+            },
         }
     } else {
         println!("please enter an input file");
@@ -0,0 +1,148 @@
+extern crate rubic;
+
+use rubic::diagnostic::Renderer;
+use rubic::format::{self, Formatter};
+use rubic::parse::{Parser, Source};
+
+use std::{fs, env, process};
+use std::io::{self, Read, Write, IsTerminal};
+
+const USAGE: &str = "\
+usage: rubic fmt [options] [FILE...]
+
+Formats Ruby files in place, or standard input to standard output if no
+files are given.
+
+options:
+    --check      list the files which are not formatted, without changing them
+    --diff       show how the files would change, without changing them
+    --width N    keep lines within N characters where possible (default 100)
+";
+
+/// What to do with the formatted code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Mode
+{
+    check: bool,
+    diff: bool,
+}
+
+/// What happened to a file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Outcome
+{
+    Unchanged,
+    Changed,
+    Failed,
+}
+
+/// Formats one file, or standard input if there is no path.
+fn format_file(path: Option<&str>, formatter: &Formatter, mode: Mode) -> Result<Outcome, rubic::Error> {
+    let name = path.unwrap_or("<stdin>");
+
+    let source = match path {
+        Some(path) => Source::from_reader(fs::File::open(path)?)?,
+        None => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            Source::from_bytes(&bytes)?
+        },
+    };
+
+    let tree = match Parser::from(source.text()).parse_lossless() {
+        Ok(tree) => tree,
+        Err(error) => {
+            let renderer = if io::stderr().is_terminal() { Renderer::ansi() } else { Renderer::plain() };
+            eprintln!("{}", renderer.render(&error.to_diagnostic(), name, source.text()));
+            return Ok(Outcome::Failed);
+        },
+    };
+
+    let formatted = formatter.format_tree(&tree)?;
+    let outcome = if formatted == source.text() { Outcome::Unchanged } else { Outcome::Changed };
+
+    if mode.diff {
+        print!("{}", format::unified_diff(source.text(), &formatted, name, name));
+    } else if mode.check {
+        if outcome == Outcome::Changed {
+            println!("{}", name);
+        }
+    } else {
+        let bytes = source.encoding().encode(&formatted);
+        match path {
+            Some(path) if outcome == Outcome::Changed => fs::write(path, bytes)?,
+            Some(..) => (),
+            None => io::stdout().write_all(&bytes)?,
+        }
+    }
+
+    Ok(outcome)
+}
+
+fn fmt(arguments: &[String]) -> i32 {
+    let mut mode = Mode { check: false, diff: false };
+    let mut formatter = Formatter::new();
+    let mut paths = Vec::new();
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match &argument[..] {
+            "--check" => mode.check = true,
+            "--diff" => mode.diff = true,
+            "--width" => match arguments.next().and_then(|width| width.parse().ok()) {
+                Some(width) => formatter = formatter.with_width(width),
+                None => {
+                    eprint!("error: --width needs a number\n\n{}", USAGE);
+                    return 2;
+                },
+            },
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return 0;
+            },
+            _ if argument.starts_with('-') && argument != "-" => {
+                eprint!("error: unknown option '{}'\n\n{}", argument, USAGE);
+                return 2;
+            },
+            _ => paths.push(argument.clone()),
+        }
+    }
+
+    let paths: Vec<_> = match paths.is_empty() {
+        true => vec![None],
+        false => paths.iter().map(|path| Some(&path[..]).filter(|&path| path != "-")).collect(),
+    };
+
+    let mut status = 0;
+    for path in paths {
+        match format_file(path, &formatter, mode) {
+            Ok(Outcome::Unchanged) => (),
+            Ok(Outcome::Changed) => if mode.check || mode.diff { status = status.max(1) },
+            Ok(Outcome::Failed) => status = 2,
+            Err(error) => {
+                eprintln!("error: {}: {}", path.unwrap_or("<stdin>"), error);
+                status = 2;
+            },
+        }
+    }
+
+    status
+}
+
+fn main() {
+    let arguments: Vec<_> = env::args().skip(1).collect();
+
+    let status = match arguments.first().map(|command| &command[..]) {
+        Some("fmt") => fmt(&arguments[1..]),
+        Some("-h") | Some("--help") => {
+            print!("{}", USAGE);
+            0
+        },
+        _ => {
+            eprint!("{}", USAGE);
+            2
+        },
+    };
+
+    process::exit(status);
+}
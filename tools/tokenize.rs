@@ -1,18 +1,14 @@
 extern crate rubic;
 
 use std::{fs, env};
-use std::io::prelude::*;
 
 fn tokenize(file_path: &str)
     -> Result<(), rubic::parse::Error> {
     println!("Tokenizing '{}'", file_path);
 
-    let mut file = fs::File::open(file_path)?;
+    let source = rubic::parse::Source::from_reader(fs::File::open(file_path)?)?;
 
-    let mut file_data = String::new();
-    file.read_to_string(&mut file_data)?;
-
-    let tokenizer = rubic::parse::Tokenizer::new(file_data.chars());
+    let tokenizer = rubic::parse::Tokenizer::new(source.text().chars());
 
     for token in tokenizer {
         println!("{:?}", token);